@@ -0,0 +1,52 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use cita_crypto_trait::{CreateKey, Sign};
+use cita_ed25519::{pubkey_to_address, pubkeys_to_addresses, KeyPair, Message, PubKey, Signature};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+fn verify_address(c: &mut Criterion) {
+    let keypair = KeyPair::gen_keypair();
+    let other = KeyPair::gen_keypair();
+    let message = Message::from([7u8; 32]);
+    let signature = Signature::sign(keypair.privkey(), &message).unwrap();
+    let address = pubkey_to_address(keypair.pubkey());
+    let other_address = pubkey_to_address(other.pubkey());
+
+    c.bench_function("verify_address/match", |b| {
+        b.iter(|| signature.verify_address(black_box(&address), black_box(&message)))
+    });
+    c.bench_function("verify_address/mismatch", |b| {
+        b.iter(|| signature.verify_address(black_box(&other_address), black_box(&message)))
+    });
+    c.bench_function("verify_public", |b| {
+        b.iter(|| signature.verify_public(black_box(keypair.pubkey()), black_box(&message)))
+    });
+    c.bench_function("recover", |b| {
+        b.iter(|| signature.recover(black_box(&message)))
+    });
+}
+
+fn bulk_addresses(c: &mut Criterion) {
+    let pubkeys: Vec<PubKey> = (0..100_000).map(PubKey::from_low_u64_be).collect();
+    c.bench_function("pubkey_to_address/100k", |b| {
+        b.iter(|| pubkeys.iter().map(pubkey_to_address).collect::<Vec<_>>())
+    });
+    c.bench_function("pubkeys_to_addresses/100k", |b| {
+        b.iter(|| pubkeys_to_addresses(black_box(&pubkeys)))
+    });
+}
+
+criterion_group!(benches, verify_address, bulk_addresses);
+criterion_main!(benches);
@@ -0,0 +1,103 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `#[derive(Signable)]`, re-exported by `cita-ed25519`.
+//!
+//! The generated `signing_hash` feeds the struct name and each field's name
+//! and RLP encoding, in declaration order, into `cita_ed25519::SigningHasher`.
+//! `#[signable(skip)]` leaves a field out and `#[signable(rename = "...")]`
+//! changes the name hashed for a field or, on the struct, for the struct.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, LitStr};
+
+#[derive(Default)]
+struct SignableAttrs {
+    skip: bool,
+    rename: Option<String>,
+}
+
+fn parse_attrs(attrs: &[Attribute]) -> syn::Result<SignableAttrs> {
+    let mut parsed = SignableAttrs::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("signable")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                parsed.skip = true;
+                Ok(())
+            } else if meta.path.is_ident("rename") {
+                parsed.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `skip` or `rename = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(parsed)
+}
+
+#[proc_macro_derive(Signable, attributes(signable))]
+pub fn derive_signable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "Signable can only be derived for structs",
+            ))
+        }
+    };
+
+    let struct_name = parse_attrs(&input.attrs)?
+        .rename
+        .unwrap_or_else(|| input.ident.to_string());
+
+    let mut calls = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let attrs = parse_attrs(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let (member, default_name) = match (&field.ident, fields) {
+            (Some(ident), Fields::Named(_)) => (quote!(#ident), ident.to_string()),
+            _ => {
+                let index = syn::Index::from(i);
+                (quote!(#index), i.to_string())
+            }
+        };
+        let name = attrs.rename.unwrap_or(default_name);
+        calls.push(quote!(.field(#name, &self.#member)));
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::cita_ed25519::Signable for #ident #ty_generics #where_clause {
+            fn signing_hash(&self) -> ::cita_ed25519::Message {
+                ::cita_ed25519::SigningHasher::new(#struct_name)
+                    #(#calls)*
+                    .finish()
+            }
+        }
+    })
+}
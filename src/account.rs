@@ -0,0 +1,172 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{pubkey_to_address, Address, Error, Message, MultisigAccount, PubKey};
+use super::{SessionCertificate, Signature};
+use crate::clock::now;
+use cita_crypto_trait::Sign;
+use serde::{Deserialize, Serialize};
+
+/// How signatures for an account are checked.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AccountDescriptor {
+    /// One key signs directly.
+    SingleKey(PubKey),
+    /// At least `threshold` members of the group sign.
+    Multisig(MultisigAccount),
+    /// `root` signs directly or through a chain of session certificates no
+    /// longer than `max_depth`.
+    Delegated { root: PubKey, max_depth: u8 },
+}
+
+/// What a signer presents to [`verify_for_account`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AccountProof {
+    Single(Signature),
+    Multisig(Vec<Signature>),
+    /// `chain[0]` is issued by the root and each later certificate by the
+    /// previous session key; `signature` is by the last session key.
+    Delegated {
+        chain: Vec<SessionCertificate>,
+        signature: Signature,
+    },
+}
+
+impl AccountDescriptor {
+    /// The address this rule governs. A delegated account keeps its root's
+    /// address, so a single-key account can move to delegation in place.
+    pub fn address(&self) -> Address {
+        match self {
+            AccountDescriptor::SingleKey(pubkey) => pubkey_to_address(pubkey),
+            AccountDescriptor::Multisig(account) => account.address(),
+            AccountDescriptor::Delegated { root, .. } => pubkey_to_address(root),
+        }
+    }
+}
+
+/// Check `proof` over `message` against the rule in `descriptor`.
+///
+/// A proof of a different kind than the descriptor is rejected.
+pub fn verify_for_account(
+    descriptor: &AccountDescriptor,
+    message: &Message,
+    proof: &AccountProof,
+) -> Result<(), Error> {
+    verify_for_account_at(descriptor, message, proof, now())
+}
+
+/// [`verify_for_account`] with certificates checked at `time` seconds since
+/// the Unix epoch.
+pub fn verify_for_account_at(
+    descriptor: &AccountDescriptor,
+    message: &Message,
+    proof: &AccountProof,
+    time: u64,
+) -> Result<(), Error> {
+    match (descriptor, proof) {
+        (AccountDescriptor::SingleKey(pubkey), AccountProof::Single(signature)) => {
+            signature.verify_public(pubkey, message)?;
+            Ok(())
+        }
+        (AccountDescriptor::Multisig(account), AccountProof::Multisig(signatures)) => {
+            if account.verify(message, signatures) {
+                Ok(())
+            } else {
+                Err(Error::InvalidSignature)
+            }
+        }
+        (
+            AccountDescriptor::Delegated { root, max_depth },
+            AccountProof::Delegated { chain, signature },
+        ) => {
+            if chain.len() > *max_depth as usize {
+                return Err(Error::InvalidEvidence);
+            }
+            let mut signer = *root;
+            for certificate in chain {
+                certificate.verify(&signer)?;
+                if !certificate.is_valid_at(time) {
+                    return Err(Error::Expired);
+                }
+                signer = certificate.session_pubkey;
+            }
+            signature.verify_public(&signer, message)?;
+            Ok(())
+        }
+        _ => Err(Error::InvalidSignature),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IdentityKey, KeyPair};
+    use cita_crypto_trait::CreateKey;
+    use std::time::Duration;
+
+    #[test]
+    fn test_single_and_multisig_accounts() {
+        let keys: Vec<KeyPair> = (0..3).map(|_| KeyPair::gen_keypair()).collect();
+        let msg = Message::from_low_u64_be(7);
+        let sigs: Vec<Signature> = keys
+            .iter()
+            .map(|k| Signature::sign(k.privkey(), &msg).unwrap())
+            .collect();
+
+        let single = AccountDescriptor::SingleKey(*keys[0].pubkey());
+        assert_eq!(single.address(), pubkey_to_address(keys[0].pubkey()));
+        verify_for_account(&single, &msg, &AccountProof::Single(sigs[0].clone())).unwrap();
+        assert!(verify_for_account(&single, &msg, &AccountProof::Single(sigs[1].clone())).is_err());
+        let wrong_kind = AccountProof::Multisig(vec![sigs[0].clone()]);
+        assert!(verify_for_account(&single, &msg, &wrong_kind).is_err());
+
+        let pubkeys: Vec<PubKey> = keys.iter().map(|k| *k.pubkey()).collect();
+        let multisig = AccountDescriptor::Multisig(MultisigAccount::new(2, &pubkeys).unwrap());
+        let json = serde_json::to_string(&multisig).unwrap();
+        let multisig: AccountDescriptor = serde_json::from_str(&json).unwrap();
+        let two = AccountProof::Multisig(sigs[..2].to_vec());
+        verify_for_account(&multisig, &msg, &two).unwrap();
+        let one = AccountProof::Multisig(sigs[..1].to_vec());
+        assert!(verify_for_account(&multisig, &msg, &one).is_err());
+    }
+
+    #[test]
+    fn test_delegated_account() {
+        let identity = IdentityKey::new(KeyPair::gen_keypair());
+        let root = *identity.pubkey();
+        let (first, cert0) = identity.issue_session_key(Duration::from_secs(60)).unwrap();
+        let (second, cert1) = IdentityKey::new(first)
+            .issue_session_key(Duration::from_secs(60))
+            .unwrap();
+        let msg = Message::from_low_u64_be(8);
+        let proof = AccountProof::Delegated {
+            chain: vec![cert0.clone(), cert1.clone()],
+            signature: Signature::sign(second.privkey(), &msg).unwrap(),
+        };
+
+        let descriptor = AccountDescriptor::Delegated { root, max_depth: 2 };
+        assert_eq!(descriptor.address(), pubkey_to_address(&root));
+        verify_for_account(&descriptor, &msg, &proof).unwrap();
+        let late = cert1.not_after + 1;
+        assert!(verify_for_account_at(&descriptor, &msg, &proof, late).is_err());
+
+        let shallow = AccountDescriptor::Delegated { root, max_depth: 1 };
+        assert!(verify_for_account(&shallow, &msg, &proof).is_err());
+        let broken = AccountProof::Delegated {
+            chain: vec![cert1],
+            signature: Signature::sign(second.privkey(), &msg).unwrap(),
+        };
+        assert!(verify_for_account(&descriptor, &msg, &broken).is_err());
+    }
+}
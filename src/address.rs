@@ -0,0 +1,141 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Address derivation beyond the default 20-byte scheme of
+//! [`pubkey_to_address`](crate::pubkey_to_address).
+
+use super::{pubkey_to_address, Error, KeyPair, PubKey, HASH_BYTES_LEN};
+use crate::hasher::hash_data;
+use cita_crypto_trait::CreateKey;
+use sodiumoxide::crypto::generichash;
+use sodiumoxide::crypto::hash::sha256;
+
+/// Turns a public key into address bytes.
+pub trait AddressScheme {
+    fn address(&self, pubkey: &PubKey) -> Vec<u8>;
+}
+
+/// The hash an address is cut from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AddressHash {
+    /// The hash selected by the crate's hash feature.
+    #[default]
+    Crypt,
+    Sha256,
+    Blake2b256,
+}
+
+impl AddressHash {
+    fn hash(self, bytes: &[u8]) -> [u8; HASH_BYTES_LEN] {
+        match self {
+            AddressHash::Crypt => hash_data(bytes).0,
+            AddressHash::Sha256 => sha256::hash(bytes).0,
+            AddressHash::Blake2b256 => {
+                let mut out = [0u8; HASH_BYTES_LEN];
+                let digest = generichash::hash(bytes, Some(HASH_BYTES_LEN), None).unwrap();
+                out.copy_from_slice(digest.as_ref());
+                out
+            }
+        }
+    }
+}
+
+/// The last `length` bytes of a hash of the public key, optionally behind
+/// a version byte. The default is the crate's own 20-byte scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashedAddress {
+    hash: AddressHash,
+    length: usize,
+    version: Option<u8>,
+}
+
+impl Default for HashedAddress {
+    fn default() -> Self {
+        HashedAddress {
+            hash: AddressHash::Crypt,
+            length: 20,
+            version: None,
+        }
+    }
+}
+
+impl HashedAddress {
+    pub fn new(hash: AddressHash, length: usize, version: Option<u8>) -> Result<Self, Error> {
+        if length == 0 || length > HASH_BYTES_LEN {
+            return Err(Error::InvalidAddressScheme);
+        }
+        Ok(HashedAddress {
+            hash,
+            length,
+            version,
+        })
+    }
+
+    /// Address bytes with this scheme's length and version byte, if any.
+    pub fn encoded_len(&self) -> usize {
+        self.length + self.version.is_some() as usize
+    }
+}
+
+impl AddressScheme for HashedAddress {
+    fn address(&self, pubkey: &PubKey) -> Vec<u8> {
+        let digest = self.hash.hash(pubkey.as_ref());
+        let mut address = Vec::with_capacity(self.encoded_len());
+        address.extend(self.version);
+        address.extend_from_slice(&digest[HASH_BYTES_LEN - self.length..]);
+        address
+    }
+}
+
+/// The scheme behind [`pubkey_to_address`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DefaultAddress;
+
+impl AddressScheme for DefaultAddress {
+    fn address(&self, pubkey: &PubKey) -> Vec<u8> {
+        pubkey_to_address(pubkey).0.to_vec()
+    }
+}
+
+impl KeyPair {
+    pub fn address_with<S: AddressScheme + ?Sized>(&self, scheme: &S) -> Vec<u8> {
+        scheme.address(self.pubkey())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_schemes() {
+        let keypair = KeyPair::gen_keypair();
+        let pubkey = keypair.pubkey();
+        assert_eq!(
+            HashedAddress::default().address(pubkey),
+            keypair.address().0
+        );
+        assert_eq!(DefaultAddress.address(pubkey), keypair.address().0);
+
+        let wide = HashedAddress::new(AddressHash::Sha256, 32, Some(0x01)).unwrap();
+        let address = keypair.address_with(&wide);
+        assert_eq!(address.len(), wide.encoded_len());
+        assert_eq!(address[0], 0x01);
+        assert_eq!(&address[1..], &sha256::hash(pubkey.as_ref()).0[..]);
+
+        let blake = HashedAddress::new(AddressHash::Blake2b256, 20, None).unwrap();
+        assert_ne!(blake.address(pubkey), keypair.address().0);
+        assert!(HashedAddress::new(AddressHash::Crypt, 33, None).is_err());
+    }
+}
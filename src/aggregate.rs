@@ -0,0 +1,173 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Non-interactive half-aggregation of Ed25519 signatures over distinct
+//! messages (Chalkias, Garillot, Kondi and Nikolaenko, 2021).
+//!
+//! The aggregate keeps every `R` and folds the `S` halves into one scalar
+//! `S = sum(z_i * S_i)`, where each `z_i` hashes the whole batch. It verifies
+//! as `S * B == sum(z_i * R_i + z_i * k_i * A_i)`.
+
+use super::{Error, Message, PubKey, Signature};
+use crate::curve::{
+    base_mul, hash_to_scalar, is_canonical_scalar, point_add, point_mul, scalar_add, scalar_mul,
+    Point, Scalar,
+};
+use cita_crypto_trait::Sign;
+use cita_types::H256;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+
+const AGGREGATE_DOMAIN: &[u8] = b"cita-ed25519 half-aggregation";
+
+/// `n` signatures in `32 * (n + 1)` bytes; the signers' public keys are not
+/// included and must be supplied to [`verify_aggregated`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregateSignature {
+    pub commitments: Vec<H256>,
+    pub s: H256,
+}
+
+impl Encodable for AggregateSignature {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2);
+        s.append_list(&self.commitments);
+        s.append(&self.s);
+    }
+}
+
+impl Decodable for AggregateSignature {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 2 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(AggregateSignature {
+            commitments: rlp.list_at(0)?,
+            s: rlp.val_at(1)?,
+        })
+    }
+}
+
+// z_i for every signature, bound to all commitments, keys and messages.
+fn coefficients(commitments: &[H256], pubkeys: &[PubKey], messages: &[Message]) -> Vec<Scalar> {
+    let mut transcript = AGGREGATE_DOMAIN.to_vec();
+    for ((r, a), m) in commitments.iter().zip(pubkeys).zip(messages) {
+        transcript.extend_from_slice(r.as_ref());
+        transcript.extend_from_slice(a.as_ref());
+        transcript.extend_from_slice(m.as_ref());
+    }
+    (0..commitments.len() as u64)
+        .map(|i| hash_to_scalar(&[&transcript, &i.to_le_bytes()]))
+        .collect()
+}
+
+/// Aggregate `signatures`, each over the message at the same position in
+/// `messages`. Every signature is verified first.
+pub fn aggregate(
+    signatures: &[Signature],
+    messages: &[Message],
+) -> Result<AggregateSignature, Error> {
+    if signatures.is_empty() || signatures.len() != messages.len() {
+        return Err(Error::InvalidMessage);
+    }
+    let mut commitments = Vec::with_capacity(signatures.len());
+    let mut pubkeys = Vec::with_capacity(signatures.len());
+    for (signature, message) in signatures.iter().zip(messages) {
+        signature.recover(message)?;
+        commitments.push(H256::from_slice(&signature.0[..32]));
+        pubkeys.push(PubKey::from_slice(signature.pk()));
+    }
+
+    let z = coefficients(&commitments, &pubkeys, messages);
+    let mut s = [0u8; 32];
+    for (signature, z) in signatures.iter().zip(&z) {
+        let mut s_i = [0u8; 32];
+        s_i.copy_from_slice(&signature.0[32..64]);
+        s = scalar_add(&s, &scalar_mul(z, &s_i));
+    }
+    Ok(AggregateSignature {
+        commitments,
+        s: H256::from(s),
+    })
+}
+
+/// Check `aggregate` against the messages and public keys it was built from,
+/// in the same order.
+pub fn verify_aggregated(
+    aggregate: &AggregateSignature,
+    messages: &[Message],
+    pubkeys: &[PubKey],
+) -> Result<bool, Error> {
+    let n = aggregate.commitments.len();
+    if n == 0 || messages.len() != n || pubkeys.len() != n {
+        return Err(Error::InvalidMessage);
+    }
+    if !is_canonical_scalar(&aggregate.s.0) {
+        return Err(Error::InvalidSignature);
+    }
+
+    let z = coefficients(&aggregate.commitments, pubkeys, messages);
+    let mut sum: Option<Point> = None;
+    for i in 0..n {
+        let r = &aggregate.commitments[i].0;
+        let a = &pubkeys[i].0;
+        let k = hash_to_scalar(&[r, a, messages[i].as_ref()]);
+        for term in &[point_mul(&z[i], r)?, point_mul(&scalar_mul(&z[i], &k), a)?] {
+            sum = Some(match sum {
+                Some(acc) => point_add(&acc, term)?,
+                None => *term,
+            });
+        }
+    }
+
+    let lhs = base_mul(&aggregate.s.0).map_err(|_| Error::InvalidSignature)?;
+    if sum == Some(lhs) {
+        Ok(true)
+    } else {
+        Err(Error::InvalidSignature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+    use cita_crypto_trait::CreateKey;
+
+    #[test]
+    fn test_half_aggregation() {
+        let keys: Vec<KeyPair> = (0..5).map(|_| KeyPair::gen_keypair()).collect();
+        let messages: Vec<Message> = (0..5).map(Message::from_low_u64_be).collect();
+        let signatures: Vec<Signature> = keys
+            .iter()
+            .zip(&messages)
+            .map(|(k, m)| Signature::sign(k.privkey(), m).unwrap())
+            .collect();
+        let pubkeys: Vec<PubKey> = keys.iter().map(|k| *k.pubkey()).collect();
+
+        let agg = aggregate(&signatures, &messages).unwrap();
+        assert_eq!(
+            rlp::decode::<AggregateSignature>(&rlp::encode(&agg)).unwrap(),
+            agg
+        );
+        assert!(verify_aggregated(&agg, &messages, &pubkeys).unwrap());
+
+        let mut swapped = messages.clone();
+        swapped.swap(0, 1);
+        assert!(verify_aggregated(&agg, &swapped, &pubkeys).is_err());
+        let mut forged = agg;
+        forged.s.0[0] ^= 1;
+        assert!(verify_aggregated(&forged, &messages, &pubkeys).is_err());
+    }
+}
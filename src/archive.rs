@@ -0,0 +1,118 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `rkyv` support, so archived blocks can read signatures in place.
+//!
+//! [`Signature`] derives the `rkyv` traits directly. `PubKey`, `Address` and
+//! `PrivKey` are `cita-types` hashes, so fields of those types take
+//! `#[rkyv(with = FixedHash)]` and archive as plain byte arrays.
+
+use super::{Address, ArchivedSignature, Error, Message, PrivKey, PubKey, Signature};
+use crate::signature::verify_detached_bytes;
+use rkyv::rancor::Fallible;
+use rkyv::with::{ArchiveWith, DeserializeWith, SerializeWith};
+use rkyv::{Archive, Place, Serialize};
+use std::convert::TryFrom;
+
+/// Archives a fixed-size hash as its bytes.
+pub struct FixedHash;
+
+macro_rules! fixed_hash_with {
+    ($t:ty, $n:expr) => {
+        impl ArchiveWith<$t> for FixedHash {
+            type Archived = [u8; $n];
+            type Resolver = [(); $n];
+
+            fn resolve_with(field: &$t, resolver: Self::Resolver, out: Place<Self::Archived>) {
+                field.0.resolve(resolver, out);
+            }
+        }
+
+        impl<S: Fallible + ?Sized> SerializeWith<$t, S> for FixedHash {
+            fn serialize_with(field: &$t, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+                field.0.serialize(serializer)
+            }
+        }
+
+        impl<D: Fallible + ?Sized> DeserializeWith<[u8; $n], $t, D> for FixedHash {
+            fn deserialize_with(field: &[u8; $n], _: &mut D) -> Result<$t, D::Error> {
+                Ok(<$t>::from(*field))
+            }
+        }
+    };
+}
+
+fixed_hash_with!(Address, 20);
+fixed_hash_with!(PubKey, 32);
+fixed_hash_with!(PrivKey, 64);
+
+impl ArchivedSignature {
+    pub fn sig(&self) -> &[u8] {
+        &self.0[0..64]
+    }
+
+    pub fn pk(&self) -> &[u8] {
+        &self.0[64..96]
+    }
+
+    /// Verify against the embedded public key without leaving the archive.
+    pub fn recover(&self, message: &Message) -> Result<PubKey, Error> {
+        let pubkey = <&[u8; 32]>::try_from(self.pk()).map_err(|_| Error::InvalidPubKey)?;
+        if verify_detached_bytes(&self.0, pubkey, message) {
+            Ok(PubKey::from(*pubkey))
+        } else {
+            Err(Error::InvalidSignature)
+        }
+    }
+
+    pub fn to_signature(&self) -> Signature {
+        Signature(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+    use cita_crypto_trait::{CreateKey, Sign};
+    use rkyv::rancor::Error as RkyvError;
+
+    #[derive(Archive, rkyv::Serialize, rkyv::Deserialize, Debug, PartialEq)]
+    struct Vote {
+        #[rkyv(with = FixedHash)]
+        voter: Address,
+        signature: Signature,
+    }
+
+    #[test]
+    fn test_archived_signature() {
+        let keypair = KeyPair::gen_keypair();
+        let msg = Message::from_low_u64_be(6);
+        let vote = Vote {
+            voter: keypair.address(),
+            signature: Signature::sign(keypair.privkey(), &msg).unwrap(),
+        };
+        let bytes = rkyv::to_bytes::<RkyvError>(&vote).unwrap();
+        let archived = rkyv::access::<ArchivedVote, RkyvError>(&bytes).unwrap();
+        assert_eq!(archived.voter, keypair.address().0);
+        assert_eq!(archived.signature.recover(&msg).unwrap(), *keypair.pubkey());
+        assert!(archived
+            .signature
+            .recover(&Message::from_low_u64_be(7))
+            .is_err());
+
+        let decoded = rkyv::from_bytes::<Vote, RkyvError>(&bytes).unwrap();
+        assert_eq!(decoded, vote);
+    }
+}
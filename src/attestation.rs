@@ -0,0 +1,142 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Error, KeyPair, Message, PubKey, Signature};
+use crate::hasher::hash_data;
+use cita_crypto_trait::{CreateKey, Sign};
+use cita_types::H256;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+
+const ATTESTATION_DOMAIN: &[u8] = b"cita-ed25519 key attestation";
+
+/// The conditions a key was generated under.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AttestationMetadata {
+    /// Serial of the HSM holding the key, empty for software keys.
+    pub hsm_serial: String,
+    /// Seconds since the Unix epoch.
+    pub generated_at: u64,
+    /// Hash of the key-generation policy that was followed.
+    pub policy_hash: H256,
+}
+
+/// A statement by an attester that `subject` was generated under `metadata`.
+///
+/// The subject key also signs the statement, proving the attester saw the
+/// private key and not just a public key copied from elsewhere.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyAttestation {
+    pub subject: PubKey,
+    pub attester: PubKey,
+    pub metadata: AttestationMetadata,
+    pub subject_signature: Signature,
+    pub attester_signature: Signature,
+}
+
+fn statement_hash(subject: &PubKey, attester: &PubKey, metadata: &AttestationMetadata) -> Message {
+    let mut s = RlpStream::new_list(6);
+    s.append(&ATTESTATION_DOMAIN);
+    s.append(subject);
+    s.append(attester);
+    s.append(&metadata.hsm_serial);
+    s.append(&metadata.generated_at);
+    s.append(&metadata.policy_hash);
+    hash_data(&s.out())
+}
+
+impl KeyAttestation {
+    pub fn create(
+        keypair: &KeyPair,
+        attester_keypair: &KeyPair,
+        metadata: AttestationMetadata,
+    ) -> Result<Self, Error> {
+        let message = statement_hash(keypair.pubkey(), attester_keypair.pubkey(), &metadata);
+        Ok(KeyAttestation {
+            subject: *keypair.pubkey(),
+            attester: *attester_keypair.pubkey(),
+            subject_signature: Signature::sign(keypair.privkey(), &message)?,
+            attester_signature: Signature::sign(attester_keypair.privkey(), &message)?,
+            metadata,
+        })
+    }
+
+    /// Check both signatures. Whether `attester` is trusted is up to the
+    /// caller.
+    pub fn verify(&self) -> Result<(), Error> {
+        let message = statement_hash(&self.subject, &self.attester, &self.metadata);
+        self.subject_signature
+            .verify_public(&self.subject, &message)?;
+        self.attester_signature
+            .verify_public(&self.attester, &message)?;
+        Ok(())
+    }
+}
+
+impl Encodable for KeyAttestation {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(7);
+        s.append(&self.subject);
+        s.append(&self.attester);
+        s.append(&self.metadata.hsm_serial);
+        s.append(&self.metadata.generated_at);
+        s.append(&self.metadata.policy_hash);
+        s.append(&self.subject_signature);
+        s.append(&self.attester_signature);
+    }
+}
+
+impl Decodable for KeyAttestation {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 7 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(KeyAttestation {
+            subject: rlp.val_at(0)?,
+            attester: rlp.val_at(1)?,
+            metadata: AttestationMetadata {
+                hsm_serial: rlp.val_at(2)?,
+                generated_at: rlp.val_at(3)?,
+                policy_hash: rlp.val_at(4)?,
+            },
+            subject_signature: rlp.val_at(5)?,
+            attester_signature: rlp.val_at(6)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_attestation() {
+        let validator = KeyPair::gen_keypair();
+        let ca = KeyPair::gen_keypair();
+        let metadata = AttestationMetadata {
+            hsm_serial: "HSM-0042".to_owned(),
+            generated_at: 1_700_000_000,
+            policy_hash: H256::from_low_u64_be(7),
+        };
+        let attestation = KeyAttestation::create(&validator, &ca, metadata).unwrap();
+        assert!(attestation.verify().is_ok());
+
+        let decoded: KeyAttestation = rlp::decode(&rlp::encode(&attestation)).unwrap();
+        assert_eq!(decoded, attestation);
+
+        let mut forged = attestation;
+        forged.metadata.hsm_serial = "HSM-0043".to_owned();
+        assert!(forged.verify().is_err());
+    }
+}
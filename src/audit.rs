@@ -0,0 +1,382 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An append-only log of everything a [`Signer`] signed, kept as a Merkle
+//! tree in the style of Certificate Transparency (RFC 6962).
+//!
+//! The service periodically signs a [`Checkpoint`] over the tree. Given one,
+//! an [`InclusionProof`] shows that a signature was logged; an auditor
+//! holding a copy of the log can also show that one was not.
+
+use super::{Error, KeyPair, Message, PubKey, Signature, Signer, HASH_BYTES_LEN};
+use crate::hasher::hash_data;
+use cita_crypto_trait::{CreateKey, Sign};
+use cita_types::H256;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+
+const CHECKPOINT_DOMAIN: &[u8] = b"cita-ed25519 signature log checkpoint";
+const RECORD_LEN: usize = HASH_BYTES_LEN + 96;
+
+fn leaf_hash(message: &Message, signature: &Signature) -> H256 {
+    hash_data(&[&[0u8][..], message.as_ref(), signature.as_ref()].concat())
+}
+
+fn node_hash(left: &H256, right: &H256) -> H256 {
+    hash_data(&[&[1u8][..], left.as_ref(), right.as_ref()].concat())
+}
+
+// The largest power of two below `n`, for n > 1.
+fn split_point(n: usize) -> usize {
+    let mut k = 1;
+    while k * 2 < n {
+        k *= 2;
+    }
+    k
+}
+
+fn tree_hash(leaves: &[H256]) -> H256 {
+    match leaves.len() {
+        0 => hash_data(&[]),
+        1 => leaves[0],
+        n => {
+            let k = split_point(n);
+            node_hash(&tree_hash(&leaves[..k]), &tree_hash(&leaves[k..]))
+        }
+    }
+}
+
+fn audit_path(index: usize, leaves: &[H256]) -> Vec<H256> {
+    let n = leaves.len();
+    if n <= 1 {
+        return Vec::new();
+    }
+    let k = split_point(n);
+    let (mut path, sibling) = if index < k {
+        (audit_path(index, &leaves[..k]), tree_hash(&leaves[k..]))
+    } else {
+        (audit_path(index - k, &leaves[k..]), tree_hash(&leaves[..k]))
+    };
+    path.push(sibling);
+    path
+}
+
+/// A signed statement of the log's size and root at some point.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub size: u64,
+    pub root: H256,
+    pub signature: Signature,
+}
+
+impl Checkpoint {
+    fn signing_hash(size: u64, root: &H256) -> Message {
+        let mut s = RlpStream::new_list(3);
+        s.append(&CHECKPOINT_DOMAIN);
+        s.append(&size);
+        s.append(root);
+        hash_data(&s.out())
+    }
+
+    /// Check that `pubkey`, the service's log key, signed this checkpoint.
+    pub fn verify(&self, pubkey: &PubKey) -> Result<(), Error> {
+        self.signature
+            .verify_public(pubkey, &Self::signing_hash(self.size, &self.root))
+            .map(|_| ())
+    }
+}
+
+impl Encodable for Checkpoint {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3);
+        s.append(&self.size);
+        s.append(&self.root);
+        s.append(&self.signature);
+    }
+}
+
+impl Decodable for Checkpoint {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 3 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(Checkpoint {
+            size: rlp.val_at(0)?,
+            root: rlp.val_at(1)?,
+            signature: rlp.val_at(2)?,
+        })
+    }
+}
+
+/// Evidence that the entry at `index` is in a log of `size` entries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub index: u64,
+    pub size: u64,
+    pub path: Vec<H256>,
+}
+
+impl InclusionProof {
+    /// Check that `signature` over `message` is in the log `checkpoint`
+    /// describes. The checkpoint's own signature is checked separately with
+    /// [`Checkpoint::verify`].
+    pub fn verify(
+        &self,
+        message: &Message,
+        signature: &Signature,
+        checkpoint: &Checkpoint,
+    ) -> Result<(), Error> {
+        if self.size != checkpoint.size || self.index >= self.size {
+            return Err(Error::InvalidEvidence);
+        }
+        // RFC 9162, section 2.1.3.2.
+        let (mut f, mut s) = (self.index, self.size - 1);
+        let mut r = leaf_hash(message, signature);
+        for p in &self.path {
+            if s == 0 {
+                return Err(Error::InvalidEvidence);
+            }
+            if f & 1 == 1 || f == s {
+                r = node_hash(p, &r);
+                while f & 1 == 0 && f != 0 {
+                    f >>= 1;
+                    s >>= 1;
+                }
+            } else {
+                r = node_hash(&r, p);
+            }
+            f >>= 1;
+            s >>= 1;
+        }
+        if s == 0 && r == checkpoint.root {
+            Ok(())
+        } else {
+            Err(Error::InvalidEvidence)
+        }
+    }
+}
+
+impl Encodable for InclusionProof {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3);
+        s.append(&self.index);
+        s.append(&self.size);
+        s.append_list(&self.path);
+    }
+}
+
+impl Decodable for InclusionProof {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 3 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(InclusionProof {
+            index: rlp.val_at(0)?,
+            size: rlp.val_at(1)?,
+            path: rlp.list_at(2)?,
+        })
+    }
+}
+
+/// The log file: fixed-size `message || signature` records, each synced to
+/// disk before the signature is handed out.
+pub struct SignatureLog {
+    file: File,
+    leaves: Vec<H256>,
+}
+
+impl SignatureLog {
+    /// Open the log at `path`, creating it if needed.
+    ///
+    /// A partly written last record, left by a crash during `append`, is
+    /// truncated away. Its signature was never handed out, since `append`
+    /// syncs the record first.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        let complete = bytes.len() - bytes.len() % RECORD_LEN;
+        if complete != bytes.len() {
+            file.set_len(complete as u64)?;
+            file.sync_data()?;
+            bytes.truncate(complete);
+        }
+        let leaves = bytes
+            .chunks(RECORD_LEN)
+            .map(|record| {
+                let (message, signature) = record.split_at(HASH_BYTES_LEN);
+                let signature = Signature::try_from(signature)?;
+                Ok(leaf_hash(&Message::from_slice(message), &signature))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(SignatureLog { file, leaves })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.leaves.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Record `signature` over `message` and return its index.
+    pub fn append(&mut self, message: &Message, signature: &Signature) -> Result<u64, Error> {
+        self.file
+            .write_all(&[message.as_ref(), signature.as_ref()].concat())?;
+        self.file.sync_data()?;
+        self.leaves.push(leaf_hash(message, signature));
+        Ok(self.len() - 1)
+    }
+
+    /// Sign `message` with `signer` and log the result before returning it.
+    pub fn sign(&mut self, signer: &Signer, message: &Message) -> Result<(Signature, u64), Error> {
+        let signature = signer.sign(message)?;
+        let index = self.append(message, &signature)?;
+        Ok((signature, index))
+    }
+
+    pub fn root(&self) -> H256 {
+        tree_hash(&self.leaves)
+    }
+
+    /// Sign the current size and root with the log key.
+    pub fn checkpoint(&self, keypair: &KeyPair) -> Result<Checkpoint, Error> {
+        let root = self.root();
+        let signature = Signature::sign(
+            keypair.privkey(),
+            &Checkpoint::signing_hash(self.len(), &root),
+        )?;
+        Ok(Checkpoint {
+            size: self.len(),
+            root,
+            signature,
+        })
+    }
+
+    /// Proof for the entry at `index` against a checkpoint of `size` entries,
+    /// which may be older than the current log.
+    pub fn inclusion_proof(&self, index: u64, size: u64) -> Result<InclusionProof, Error> {
+        if index >= size || size > self.len() {
+            return Err(Error::InvalidEvidence);
+        }
+        Ok(InclusionProof {
+            index,
+            size,
+            path: audit_path(index as usize, &self.leaves[..size as usize]),
+        })
+    }
+
+    /// The index of `signature` over `message`, if it was logged.
+    pub fn find(&self, message: &Message, signature: &Signature) -> Option<u64> {
+        let leaf = leaf_hash(message, signature);
+        self.leaves
+            .iter()
+            .position(|l| *l == leaf)
+            .map(|i| i as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn test_split_point() {
+        let expected = [(2, 1), (3, 2), (4, 2), (5, 4), (8, 4), (9, 8), (17, 16)];
+        for (n, k) in expected {
+            assert_eq!(split_point(n), k, "n = {}", n);
+        }
+    }
+
+    #[test]
+    fn test_signature_log() {
+        let path = env::temp_dir().join(format!("cita-ed25519-{}-signatures.log", process::id()));
+        let _ = fs::remove_file(&path);
+        let signer = Signer::from(KeyPair::gen_keypair().into_parts().0);
+        let log_key = KeyPair::gen_keypair();
+
+        let mut log = SignatureLog::open(&path).unwrap();
+        let mut signed = Vec::new();
+        for i in 0..7 {
+            let message = Message::from_low_u64_be(i);
+            let (signature, index) = log.sign(&signer, &message).unwrap();
+            assert_eq!(index, i);
+            signed.push((message, signature));
+        }
+        let old = log.checkpoint(&log_key).unwrap();
+        drop(log);
+
+        let mut log = SignatureLog::open(&path).unwrap();
+        assert_eq!(log.root(), old.root);
+        let message = Message::from_low_u64_be(7);
+        signed.push((message, log.sign(&signer, &message).unwrap().0));
+        let checkpoint = log.checkpoint(&log_key).unwrap();
+        checkpoint.verify(log_key.pubkey()).unwrap();
+        let checkpoint: Checkpoint = rlp::decode(&rlp::encode(&checkpoint)).unwrap();
+
+        for (i, (message, signature)) in signed.iter().enumerate() {
+            let proof = log.inclusion_proof(i as u64, checkpoint.size).unwrap();
+            proof.verify(message, signature, &checkpoint).unwrap();
+            if i < 7 {
+                let proof = log.inclusion_proof(i as u64, old.size).unwrap();
+                proof.verify(message, signature, &old).unwrap();
+            }
+        }
+        let proof = log.inclusion_proof(3, checkpoint.size).unwrap();
+        let (message, signature) = &signed[4];
+        assert!(proof.verify(message, signature, &checkpoint).is_err());
+
+        let unlogged = signer.sign(&Message::from_low_u64_be(99)).unwrap();
+        assert_eq!(log.find(&Message::from_low_u64_be(99), &unlogged), None);
+        assert_eq!(log.find(message, signature), Some(4));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_open_truncates_torn_record() {
+        let path = env::temp_dir().join(format!("cita-ed25519-{}-torn.log", process::id()));
+        let _ = fs::remove_file(&path);
+        let signer = Signer::from(KeyPair::gen_keypair().into_parts().0);
+        let mut log = SignatureLog::open(&path).unwrap();
+        let message = Message::from_low_u64_be(1);
+        let (signature, _) = log.sign(&signer, &message).unwrap();
+        let root = log.root();
+        drop(log);
+
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[0xab; RECORD_LEN / 2]).unwrap();
+        drop(file);
+
+        let mut log = SignatureLog::open(&path).unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log.root(), root);
+        assert_eq!(fs::metadata(&path).unwrap().len(), RECORD_LEN as u64);
+        let next = Message::from_low_u64_be(2);
+        assert_eq!(log.sign(&signer, &next).unwrap().1, 1);
+        assert_eq!(log.find(&message, &signature), Some(0));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,338 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{pubkey_to_address, Address, Error, Message, PubKey, Signature, VerifyHandle};
+use crate::curve::{
+    base_mul, hash_to_scalar, is_canonical_scalar, point_add, point_mul, reduce32, scalar_add,
+    scalar_mul, Point, Scalar,
+};
+use crate::hasher::hash_data_many;
+use crate::limits::{LimitExceeded, Limits};
+use crate::meter::{charge, Operation};
+use cita_crypto_trait::Sign;
+use sodiumoxide::randombytes::randombytes_into;
+use sodiumoxide::utils::memcmp;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// One signature to check in a batch.
+pub struct BatchItem {
+    pub message: Message,
+    pub signature: Signature,
+    pub pubkey: PubKey,
+}
+
+fn batch_equation_holds(items: &[BatchItem]) -> Result<(), Error> {
+    // 128-bit random weights: a forged signature survives with probability
+    // 2^-128.
+    let mut s_sum: Scalar = [0u8; 32];
+    let mut rhs: Option<Point> = None;
+    for item in items {
+        if !item.signature.embedded_pubkey_matches(&item.pubkey) {
+            return Err(Error::InvalidPubKey);
+        }
+        let mut r = [0u8; 32];
+        let mut s = [0u8; 32];
+        r.copy_from_slice(&item.signature.0[..32]);
+        s.copy_from_slice(&item.signature.0[32..64]);
+        if !is_canonical_scalar(&s) {
+            return Err(Error::InvalidSignature);
+        }
+        let mut weight = [0u8; 32];
+        randombytes_into(&mut weight[..16]);
+        let z = reduce32(&weight);
+        let k = hash_to_scalar(&[&r, &item.pubkey.0, item.message.as_ref()]);
+
+        s_sum = scalar_add(&s_sum, &scalar_mul(&z, &s));
+        for term in &[
+            point_mul(&z, &r)?,
+            point_mul(&scalar_mul(&z, &k), &item.pubkey.0)?,
+        ] {
+            rhs = Some(match rhs {
+                Some(acc) => point_add(&acc, term)?,
+                None => *term,
+            });
+        }
+    }
+    let lhs = base_mul(&s_sum).map_err(|_| Error::InvalidSignature)?;
+    match rhs {
+        Some(rhs) if memcmp(&lhs, &rhs) => Ok(()),
+        _ => Err(Error::InvalidSignature),
+    }
+}
+
+// Every item fails when there are more than the default limit allows.
+fn reject_oversized<T>(len: usize) -> Option<Vec<Result<T, Error>>> {
+    let max = Limits::default().max_batch_len;
+    if len <= max {
+        return None;
+    }
+    let exceeded = LimitExceeded::BatchLen {
+        len: len as u64,
+        max: max as u64,
+    };
+    Some(
+        (0..len)
+            .map(|_| Err(Error::LimitExceeded(exceeded)))
+            .collect(),
+    )
+}
+
+/// Verify every item, using one combined check when all are valid.
+///
+/// When the combined check fails the items are verified one by one, so the
+/// result for each item matches [`Sign::verify_public`]. Batches longer than
+/// the default [`Limits`] fail item by item with `LimitExceeded`; see
+/// [`verify_batch_unbounded`].
+pub fn verify_batch(items: &[BatchItem]) -> Vec<Result<(), Error>> {
+    reject_oversized(items.len()).unwrap_or_else(|| verify_batch_unbounded(items))
+}
+
+/// [`verify_batch`] without the default batch limit, for callers that bound
+/// their input themselves.
+pub fn verify_batch_unbounded(items: &[BatchItem]) -> Vec<Result<(), Error>> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("verify_batch", items = items.len()).entered();
+    // Charged only on success; the fallback charges each item it verifies.
+    if items.len() > 1 && batch_equation_holds(items).is_ok() {
+        charge(Operation::Verify, items.len() as u64);
+        return items.iter().map(|_| Ok(())).collect();
+    }
+    items
+        .iter()
+        .map(|item| {
+            item.signature
+                .verify_public(&item.pubkey, &item.message)
+                .map(|_| ())
+        })
+        .collect()
+}
+
+// Below this many signatures per thread, spawning costs more than it saves.
+const RECOVER_CHUNK_MIN: usize = 64;
+
+fn recover_chunk(items: &[(Message, Signature)]) -> Vec<Result<Address, Error>> {
+    // Senders repeat within a block, so hash each distinct key once.
+    let mut addresses: HashMap<PubKey, Address> = HashMap::new();
+    items
+        .iter()
+        .map(|(message, signature)| {
+            let pubkey = signature.recover(message)?;
+            Ok(*addresses
+                .entry(pubkey)
+                .or_insert_with(|| pubkey_to_address(&pubkey)))
+        })
+        .collect()
+}
+
+/// Verify each signature against its own embedded key and derive the
+/// signer's address, spread over the available cores.
+///
+/// Like [`verify_batch`], fails item by item beyond the default batch limit;
+/// see [`recover_addresses_unbounded`].
+pub fn recover_addresses(items: &[(Message, Signature)]) -> Vec<Result<Address, Error>> {
+    reject_oversized(items.len()).unwrap_or_else(|| recover_addresses_unbounded(items))
+}
+
+/// [`recover_addresses`] without the default batch limit.
+pub fn recover_addresses_unbounded(items: &[(Message, Signature)]) -> Vec<Result<Address, Error>> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = items.len().div_ceil(threads).max(RECOVER_CHUNK_MIN);
+    if items.len() <= chunk {
+        return recover_chunk(items);
+    }
+    thread::scope(|scope| {
+        let workers: Vec<_> = items
+            .chunks(chunk)
+            .map(|part| scope.spawn(move || recover_chunk(part)))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    })
+}
+
+// Hashing is cheap; threads only pay off for large key sets.
+const ADDRESS_CHUNK_MIN: usize = 4096;
+
+fn addresses_chunk(pubkeys: &[PubKey]) -> Vec<Address> {
+    charge(Operation::AddressHash, pubkeys.len() as u64);
+    let inputs: Vec<&[u8]> = pubkeys.iter().map(|pubkey| &pubkey.0[..]).collect();
+    hash_data_many(&inputs)
+        .into_iter()
+        .map(Address::from)
+        .collect()
+}
+
+/// [`pubkey_to_address`] for a large set of keys, spread over the
+/// available cores and hashed through [`Hasher::hash_many`].
+///
+/// [`Hasher::hash_many`]: crate::Hasher::hash_many
+pub fn pubkeys_to_addresses(pubkeys: &[PubKey]) -> Vec<Address> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = pubkeys.len().div_ceil(threads).max(ADDRESS_CHUNK_MIN);
+    if pubkeys.len() <= chunk {
+        return addresses_chunk(pubkeys);
+    }
+    thread::scope(|scope| {
+        let workers: Vec<_> = pubkeys
+            .chunks(chunk)
+            .map(|part| scope.spawn(move || addresses_chunk(part)))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    })
+}
+
+type Pending = (BatchItem, Sender<Result<(), Error>>);
+
+/// Collects submitted signatures into batches of up to `max_batch` items,
+/// waiting at most `max_delay` after the first item of a batch arrives.
+pub struct BatchingVerifier {
+    sender: Option<Sender<Pending>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl BatchingVerifier {
+    pub fn new(max_batch: usize, max_delay: Duration) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let worker = thread::spawn(move || run_batches(&receiver, max_batch.max(1), max_delay));
+        BatchingVerifier {
+            sender: Some(sender),
+            worker: Some(worker),
+        }
+    }
+
+    pub fn submit(&self, message: Message, signature: Signature, pubkey: PubKey) -> VerifyHandle {
+        let (reply, receiver) = mpsc::channel();
+        let item = BatchItem {
+            message,
+            signature,
+            pubkey,
+        };
+        if let Some(sender) = &self.sender {
+            // If the worker is gone the handle reports `Cancelled`.
+            let _ = sender.send((item, reply));
+        }
+        VerifyHandle::new(receiver)
+    }
+}
+
+impl Drop for BatchingVerifier {
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn run_batches(receiver: &Receiver<Pending>, max_batch: usize, max_delay: Duration) {
+    while let Ok(first) = receiver.recv() {
+        let deadline = Instant::now() + max_delay;
+        let mut batch = vec![first];
+        while batch.len() < max_batch {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match receiver.recv_timeout(remaining) {
+                Ok(pending) => batch.push(pending),
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        let (items, replies): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+        // `max_batch` is the caller's own bound.
+        for (result, reply) in verify_batch_unbounded(&items).into_iter().zip(replies) {
+            let _ = reply.send(result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+    use cita_crypto_trait::CreateKey;
+
+    fn item(keypair: &KeyPair, i: u64) -> BatchItem {
+        let message = Message::from_low_u64_be(i);
+        BatchItem {
+            signature: Signature::sign(keypair.privkey(), &message).unwrap(),
+            pubkey: *keypair.pubkey(),
+            message,
+        }
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let keys: Vec<KeyPair> = (0..4).map(|_| KeyPair::gen_keypair()).collect();
+        let mut items: Vec<BatchItem> = (0..8).map(|i| item(&keys[i % 4], i as u64)).collect();
+        assert!(batch_equation_holds(&items).is_ok());
+        assert!(verify_batch(&items).iter().all(|r| r.is_ok()));
+
+        items[5].signature.0[40] ^= 1;
+        let results = verify_batch(&items);
+        assert!(results[5].is_err());
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 7);
+    }
+
+    #[test]
+    fn test_recover_addresses() {
+        let keys: Vec<KeyPair> = (0..3).map(|_| KeyPair::gen_keypair()).collect();
+        let mut items: Vec<(Message, Signature)> = (0..200)
+            .map(|i| {
+                let item = item(&keys[i % 3], i as u64);
+                (item.message, item.signature)
+            })
+            .collect();
+        items[150].0 = Message::from_low_u64_be(1000);
+        let results = recover_addresses(&items);
+        assert_eq!(results.len(), 200);
+        assert!(results[150].is_err());
+        for (i, result) in results.iter().enumerate().filter(|(i, _)| *i != 150) {
+            assert_eq!(result.as_ref().unwrap(), &keys[i % 3].address());
+        }
+    }
+
+    #[test]
+    fn test_pubkeys_to_addresses() {
+        let pubkeys: Vec<PubKey> = (0..10_000).map(PubKey::from_low_u64_be).collect();
+        let addresses = pubkeys_to_addresses(&pubkeys);
+        assert_eq!(addresses.len(), pubkeys.len());
+        for i in [0, 4095, 4096, 9999] {
+            assert_eq!(addresses[i], pubkey_to_address(&pubkeys[i]));
+        }
+    }
+
+    #[test]
+    fn test_batching_verifier() {
+        let keypair = KeyPair::gen_keypair();
+        let verifier = BatchingVerifier::new(4, Duration::from_millis(5));
+        let handles: Vec<VerifyHandle> = (0..10)
+            .map(|i| {
+                let mut item = item(&keypair, i);
+                if i == 3 {
+                    item.message = Message::from_low_u64_be(100);
+                }
+                verifier.submit(item.message, item.signature, item.pubkey)
+            })
+            .collect();
+        let results: Vec<bool> = handles.into_iter().map(|h| h.wait().is_ok()).collect();
+        assert_eq!(results.iter().filter(|ok| !**ok).count(), 1);
+        assert!(!results[3]);
+    }
+}
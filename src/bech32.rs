@@ -0,0 +1,133 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! BIP-173 bech32 text form of addresses.
+//!
+//! The 20 address bytes are written as 32 base32 characters after the
+//! human-readable part and the `1` separator, followed by the six-character
+//! checksum. There is no witness version; this is not a Bitcoin address.
+
+use super::{Address, Error};
+
+pub(crate) const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [
+    0x3b6a_57b2,
+    0x2650_8e6d,
+    0x1ea1_19fa,
+    0x3d42_33dd,
+    0x2a14_62b3,
+];
+
+fn polymod(values: impl Iterator<Item = u8>) -> u32 {
+    values.fold(1, |chk, value| {
+        let top = chk >> 25;
+        let chk = ((chk & 0x01ff_ffff) << 5) ^ u32::from(value);
+        GENERATOR
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| (top >> i) & 1 == 1)
+            .fold(chk, |chk, (_, g)| chk ^ g)
+    })
+}
+
+fn hrp_expand(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    hrp.bytes()
+        .map(|b| b >> 5)
+        .chain(Some(0))
+        .chain(hrp.bytes().map(|b| b & 0x1f))
+}
+
+// Lower-case printable ASCII, 1 to 83 characters.
+pub(crate) fn valid_hrp(hrp: &str) -> bool {
+    (1..=83).contains(&hrp.len())
+        && hrp
+            .bytes()
+            .all(|b| (33..=126).contains(&b) && !b.is_ascii_uppercase())
+}
+
+/// Split `data` into 5-bit groups, padding the last one with zero bits.
+pub(crate) fn to_base32(data: &[u8]) -> Vec<u8> {
+    let mut groups = Vec::with_capacity((data.len() * 8).div_ceil(5));
+    let (mut acc, mut bits) = (0u32, 0);
+    for byte in data {
+        acc = (acc << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            groups.push(((acc >> bits) & 0x1f) as u8);
+        }
+    }
+    if bits > 0 {
+        groups.push(((acc << (5 - bits)) & 0x1f) as u8);
+    }
+    groups
+}
+
+fn encode(hrp: &str, data: &[u8]) -> String {
+    let checksum = polymod(hrp_expand(hrp).chain(data.iter().copied()).chain([0; 6])) ^ 1;
+    let checksum = (0..6).map(|i| ((checksum >> (5 * (5 - i))) & 0x1f) as u8);
+    let mut encoded = String::with_capacity(hrp.len() + 1 + data.len() + 6);
+    encoded.push_str(hrp);
+    encoded.push('1');
+    encoded.extend(
+        data.iter()
+            .copied()
+            .chain(checksum)
+            .map(|v| CHARSET[v as usize] as char),
+    );
+    encoded
+}
+
+/// Encode `address` in bech32 under the human-readable part `hrp`, which
+/// must be lower case.
+pub fn address_to_bech32(hrp: &str, address: &Address) -> Result<String, Error> {
+    if !valid_hrp(hrp) {
+        return Err(Error::InvalidPrefix);
+    }
+    Ok(encode(hrp, &to_base32(&address.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_serialize::hex::FromHex;
+
+    #[test]
+    fn test_bip173_vector() {
+        // Witness version 0 followed by the program, from BIP-173.
+        let program = "751e76e8199196d454941c45d1b3a323f1433bd6"
+            .from_hex()
+            .unwrap();
+        let mut data = vec![0];
+        data.extend(to_base32(&program));
+        assert_eq!(
+            encode("bc", &data),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+    }
+
+    #[test]
+    fn test_address_to_bech32() {
+        let address = Address::from_slice(
+            &"751e76e8199196d454941c45d1b3a323f1433bd6"
+                .from_hex()
+                .unwrap(),
+        );
+        let encoded = address_to_bech32("cita", &address).unwrap();
+        assert!(encoded.starts_with("cita1w508d6qejxtdg4y5r3zarvary0c5xw7k"));
+        assert_eq!(encoded.len(), "cita1".len() + 32 + 6);
+        assert!(address_to_bech32("CITA", &address).is_err());
+        assert!(address_to_bech32("", &address).is_err());
+    }
+}
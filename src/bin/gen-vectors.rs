@@ -0,0 +1,129 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Emit conformance vectors for SDKs in other languages.
+//!
+//! ```text
+//! gen-vectors [--count N] [--seed TEXT] [--format json|csv]
+//! ```
+//!
+//! Every seed and message is derived from `--seed`, so the same arguments
+//! always print the same vectors. Addresses use the hash selected by the
+//! crate's hash feature, so generate one set per feature.
+
+use cita_crypto_trait::{CreateKey, Sign};
+use cita_ed25519::{KeyPair, Message, Signature};
+use rustc_serialize::hex::ToHex;
+use serde::Serialize;
+use sodiumoxide::crypto::hash::sha256;
+use std::env;
+use std::process;
+
+#[derive(Serialize)]
+struct Vector {
+    index: u64,
+    seed: String,
+    privkey: String,
+    pubkey: String,
+    address: String,
+    message: String,
+    signature: String,
+    signature_rlp: String,
+}
+
+struct Options {
+    count: u64,
+    seed: String,
+    csv: bool,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: gen-vectors [--count N] [--seed TEXT] [--format json|csv]");
+    process::exit(2);
+}
+
+fn parse_args() -> Options {
+    let mut options = Options {
+        count: 16,
+        seed: "cita-ed25519".to_owned(),
+        csv: false,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--count" => options.count = value.parse().unwrap_or_else(|_| usage()),
+            "--seed" => options.seed = value,
+            "--format" => {
+                options.csv = match value.as_str() {
+                    "json" => false,
+                    "csv" => true,
+                    _ => usage(),
+                }
+            }
+            _ => usage(),
+        }
+    }
+    options
+}
+
+// sha256(master || label || index), so seeds and messages never collide.
+fn derive(master: &str, label: &[u8], index: u64) -> [u8; 32] {
+    let mut state = sha256::State::new();
+    state.update(master.as_bytes());
+    state.update(label);
+    state.update(&index.to_be_bytes());
+    state.finalize().0
+}
+
+fn vector(master: &str, index: u64) -> Vector {
+    let seed = derive(master, b"seed", index);
+    let keypair = KeyPair::from_seed(&seed);
+    let message = Message::from(derive(master, b"message", index));
+    let signature = Signature::sign(keypair.privkey(), &message).unwrap();
+    Vector {
+        index,
+        seed: seed.to_hex(),
+        privkey: keypair.privkey().0.to_hex(),
+        pubkey: keypair.pubkey().0.to_hex(),
+        address: keypair.address().0.to_hex(),
+        message: message.0.to_hex(),
+        signature: String::from(signature.clone()),
+        signature_rlp: rlp::encode(&signature).to_hex(),
+    }
+}
+
+fn main() {
+    let options = parse_args();
+    let vectors = (0..options.count).map(|index| vector(&options.seed, index));
+    if options.csv {
+        println!("index,seed,privkey,pubkey,address,message,signature,signature_rlp");
+        for v in vectors {
+            println!(
+                "{},{},{},{},{},{},{},{}",
+                v.index,
+                v.seed,
+                v.privkey,
+                v.pubkey,
+                v.address,
+                v.message,
+                v.signature,
+                v.signature_rlp
+            );
+        }
+    } else {
+        let vectors: Vec<Vector> = vectors.collect();
+        println!("{}", serde_json::to_string_pretty(&vectors).unwrap());
+    }
+}
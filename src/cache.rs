@@ -0,0 +1,396 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A cache of verified transaction signatures, so transactions checked on
+//! entering the mempool are not verified again at block import.
+
+use super::{recover_addresses, Address, Error, Message, Signature};
+use cita_types::H256;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Decides which entry a full [`SignatureCache`] drops.
+pub trait EvictionPolicy {
+    fn inserted(&mut self, key: &H256);
+    fn accessed(&mut self, key: &H256);
+    /// Called for evictions and explicit invalidations alike.
+    fn removed(&mut self, key: &H256);
+    /// The entry to drop to make room; it is then passed to `removed`.
+    fn victim(&self) -> Option<H256>;
+    /// Whether an entry should be treated as absent even though it is held.
+    fn is_stale(&self, _key: &H256) -> bool {
+        false
+    }
+}
+
+/// Least recently used.
+#[derive(Debug, Default)]
+pub struct Lru {
+    tick: u64,
+    ticks: HashMap<H256, u64>,
+    order: BTreeMap<u64, H256>,
+}
+
+impl Lru {
+    fn touch(&mut self, key: &H256) {
+        if let Some(old) = self.ticks.insert(*key, self.tick) {
+            self.order.remove(&old);
+        }
+        self.order.insert(self.tick, *key);
+        self.tick += 1;
+    }
+
+    fn contains(&self, key: &H256) -> bool {
+        self.ticks.contains_key(key)
+    }
+
+    fn len(&self) -> usize {
+        self.ticks.len()
+    }
+}
+
+impl EvictionPolicy for Lru {
+    fn inserted(&mut self, key: &H256) {
+        self.touch(key);
+    }
+
+    fn accessed(&mut self, key: &H256) {
+        self.touch(key);
+    }
+
+    fn removed(&mut self, key: &H256) {
+        if let Some(tick) = self.ticks.remove(key) {
+            self.order.remove(&tick);
+        }
+    }
+
+    fn victim(&self) -> Option<H256> {
+        self.order.values().next().copied()
+    }
+}
+
+/// Entries expire `ttl` after insertion; when full, the oldest goes first.
+#[derive(Debug)]
+pub struct Ttl {
+    ttl: Duration,
+    inserted_at: HashMap<H256, Instant>,
+    order: Lru,
+}
+
+impl Ttl {
+    pub fn new(ttl: Duration) -> Self {
+        Ttl {
+            ttl,
+            inserted_at: HashMap::new(),
+            order: Lru::default(),
+        }
+    }
+}
+
+impl EvictionPolicy for Ttl {
+    fn inserted(&mut self, key: &H256) {
+        self.inserted_at.insert(*key, Instant::now());
+        self.order.touch(key);
+    }
+
+    fn accessed(&mut self, _key: &H256) {}
+
+    fn removed(&mut self, key: &H256) {
+        self.inserted_at.remove(key);
+        self.order.removed(key);
+    }
+
+    fn victim(&self) -> Option<H256> {
+        self.order.victim()
+    }
+
+    fn is_stale(&self, key: &H256) -> bool {
+        match self.inserted_at.get(key) {
+            Some(at) => at.elapsed() >= self.ttl,
+            None => true,
+        }
+    }
+}
+
+/// 2Q (Johnson and Shasha, 1994): new entries wait in a FIFO and only join
+/// the main LRU if they come back after being evicted, so a burst of
+/// one-off transactions cannot flush the ones seen repeatedly.
+#[derive(Debug)]
+pub struct TwoQueue {
+    recent: Lru,
+    frequent: Lru,
+    ghosts: VecDeque<H256>,
+    recent_max: usize,
+    ghost_max: usize,
+}
+
+impl TwoQueue {
+    /// Sized for a cache of `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        TwoQueue {
+            recent: Lru::default(),
+            frequent: Lru::default(),
+            ghosts: VecDeque::new(),
+            recent_max: (capacity / 4).max(1),
+            ghost_max: (capacity / 2).max(1),
+        }
+    }
+}
+
+impl EvictionPolicy for TwoQueue {
+    fn inserted(&mut self, key: &H256) {
+        if let Some(at) = self.ghosts.iter().position(|ghost| ghost == key) {
+            self.ghosts.remove(at);
+            self.frequent.touch(key);
+        } else {
+            self.recent.touch(key);
+        }
+    }
+
+    fn accessed(&mut self, key: &H256) {
+        if self.frequent.contains(key) {
+            self.frequent.touch(key);
+        }
+    }
+
+    fn removed(&mut self, key: &H256) {
+        if self.recent.contains(key) {
+            self.recent.removed(key);
+            if self.ghosts.len() == self.ghost_max {
+                self.ghosts.pop_front();
+            }
+            self.ghosts.push_back(*key);
+        } else {
+            self.frequent.removed(key);
+        }
+    }
+
+    fn victim(&self) -> Option<H256> {
+        if self.recent.len() > self.recent_max || self.frequent.len() == 0 {
+            self.recent.victim()
+        } else {
+            self.frequent.victim()
+        }
+    }
+}
+
+struct CachedEntry {
+    message: Message,
+    signature: Signature,
+    address: Address,
+    block: Option<u64>,
+}
+
+/// Verified signers keyed by transaction hash.
+///
+/// A hit also requires the same message and signature, so a cached entry
+/// never vouches for different bytes under a known hash.
+pub struct SignatureCache<P: EvictionPolicy> {
+    capacity: usize,
+    entries: HashMap<H256, CachedEntry>,
+    blocks: HashMap<u64, Vec<H256>>,
+    policy: P,
+}
+
+impl<P: EvictionPolicy> SignatureCache<P> {
+    pub fn new(capacity: usize, policy: P) -> Self {
+        SignatureCache {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            blocks: HashMap::new(),
+            policy,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Record that `signature` over `message` was verified as `address`.
+    pub fn insert(
+        &mut self,
+        tx_hash: H256,
+        message: Message,
+        signature: Signature,
+        address: Address,
+    ) {
+        self.invalidate(&tx_hash);
+        if self.entries.len() >= self.capacity {
+            if let Some(victim) = self.policy.victim() {
+                self.invalidate(&victim);
+            }
+        }
+        self.entries.insert(
+            tx_hash,
+            CachedEntry {
+                message,
+                signature,
+                address,
+                block: None,
+            },
+        );
+        self.policy.inserted(&tx_hash);
+    }
+
+    /// The verified signer, if this exact signature was cached.
+    pub fn get(
+        &mut self,
+        tx_hash: &H256,
+        message: &Message,
+        signature: &Signature,
+    ) -> Option<Address> {
+        if self.policy.is_stale(tx_hash) {
+            self.invalidate(tx_hash);
+            return None;
+        }
+        let entry = self.entries.get(tx_hash)?;
+        if entry.message != *message || entry.signature != *signature {
+            return None;
+        }
+        let address = entry.address;
+        self.policy.accessed(tx_hash);
+        Some(address)
+    }
+
+    /// Note that the transaction went into block `height`, for
+    /// [`SignatureCache::invalidate_block`].
+    pub fn mark_included(&mut self, tx_hash: &H256, height: u64) {
+        if let Some(entry) = self.entries.get_mut(tx_hash) {
+            entry.block = Some(height);
+            self.blocks.entry(height).or_default().push(*tx_hash);
+        }
+    }
+
+    pub fn invalidate(&mut self, tx_hash: &H256) {
+        if let Some(entry) = self.entries.remove(tx_hash) {
+            self.policy.removed(tx_hash);
+            if let Some(height) = entry.block {
+                if let Some(hashes) = self.blocks.get_mut(&height) {
+                    hashes.retain(|hash| hash != tx_hash);
+                }
+            }
+        }
+    }
+
+    /// Drop every entry marked as included in block `height`, once it is
+    /// final or has been reverted.
+    pub fn invalidate_block(&mut self, height: u64) {
+        for tx_hash in self.blocks.remove(&height).unwrap_or_default() {
+            self.invalidate(&tx_hash);
+        }
+    }
+
+    /// [`recover_addresses`] for the entries not already cached; fresh
+    /// results are cached.
+    pub fn recover_addresses(
+        &mut self,
+        items: &[(H256, Message, Signature)],
+    ) -> Vec<Result<Address, Error>> {
+        let mut results: Vec<Option<Result<Address, Error>>> = items
+            .iter()
+            .map(|(tx_hash, message, signature)| self.get(tx_hash, message, signature).map(Ok))
+            .collect();
+        let misses: Vec<usize> = (0..items.len()).filter(|&i| results[i].is_none()).collect();
+        let pairs: Vec<(Message, Signature)> = misses
+            .iter()
+            .map(|&i| (items[i].1, items[i].2.clone()))
+            .collect();
+        for (i, result) in misses.into_iter().zip(recover_addresses(&pairs)) {
+            if let Ok(address) = result {
+                let (tx_hash, message, signature) = &items[i];
+                self.insert(*tx_hash, *message, signature.clone(), address);
+            }
+            results[i] = Some(result);
+        }
+        results.into_iter().map(Option::unwrap).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeyPair, Signature};
+    use cita_crypto_trait::{CreateKey, Sign};
+
+    fn key(i: u64) -> H256 {
+        H256::from_low_u64_be(i)
+    }
+
+    #[test]
+    fn test_eviction_policies() {
+        let mut lru = Lru::default();
+        for i in 0..3 {
+            lru.inserted(&key(i));
+        }
+        lru.accessed(&key(0));
+        assert_eq!(lru.victim(), Some(key(1)));
+
+        let ttl = Ttl::new(Duration::ZERO);
+        assert!(ttl.is_stale(&key(0)));
+
+        // A one-off scan does not push out an entry that came back.
+        let mut two_q = TwoQueue::new(8);
+        two_q.inserted(&key(0));
+        two_q.removed(&key(0));
+        two_q.inserted(&key(0));
+        for i in 1..5 {
+            two_q.inserted(&key(i));
+        }
+        assert_eq!(two_q.victim(), Some(key(1)));
+    }
+
+    #[test]
+    fn test_signature_cache() {
+        let keypair = KeyPair::gen_keypair();
+        let items: Vec<(H256, Message, Signature)> = (0..4)
+            .map(|i| {
+                let message = Message::from_low_u64_be(i);
+                let signature = Signature::sign(keypair.privkey(), &message).unwrap();
+                (key(100 + i), message, signature)
+            })
+            .collect();
+
+        let mut cache = SignatureCache::new(3, Lru::default());
+        let results = cache.recover_addresses(&items[..2]);
+        assert!(results
+            .iter()
+            .all(|r| r.as_ref().unwrap() == &keypair.address()));
+        assert_eq!(cache.len(), 2);
+        let (tx_hash, message, signature) = &items[0];
+        assert_eq!(
+            cache.get(tx_hash, message, signature),
+            Some(keypair.address())
+        );
+        assert_eq!(cache.get(tx_hash, &items[1].1, &items[1].2), None);
+
+        // Item 1 is least recently used when item 3 arrives.
+        cache.recover_addresses(&items[2..]);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get(&items[1].0, &items[1].1, &items[1].2), None);
+
+        let mut bad = items[1].clone();
+        bad.2 .0[5] ^= 1;
+        assert!(cache.recover_addresses(&[bad])[0].is_err());
+        assert_eq!(cache.len(), 3);
+
+        cache.mark_included(&items[0].0, 7);
+        cache.mark_included(&items[2].0, 7);
+        cache.invalidate_block(7);
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Stable byte encodings for hashing in consensus code.
+//!
+//! Every type here encodes as its RLP form. Those bytes only change together
+//! with [`CANONICAL_FORMAT_VERSION`], so downstream code can hash them and
+//! compare the hashes across crate versions.
+
+use super::{
+    Address, AggregateSignature, BundleEntry, BundleSignatures, Checkpoint, ConflictingVote,
+    CosignCommitment, Countersignature, DoubleSignEvidence, EscrowBlob, GuardianShare,
+    HandoffRequest, HandoffResponse, InclusionProof, KeyAttestation, Migration, MultiSealed,
+    PartialSignature, PubKey, SessionCertificate, SetCheckpoint, Signature, SuiteKey,
+    TimelockCommitment, UnsignedBundle, Watermark, WrappedKey, ADDR_BYTES_LEN, PUBKEY_BYTES_LEN,
+    SIGNATURE_BYTES_LEN,
+};
+
+/// Version of the encodings produced by [`CanonicalBytes`].
+pub const CANONICAL_FORMAT_VERSION: u32 = 1;
+
+// The version 1 encodings depend on these lengths. Changing one must come
+// with a version bump, or this stops compiling.
+const _: () = assert!(
+    CANONICAL_FORMAT_VERSION != 1
+        || (SIGNATURE_BYTES_LEN == 96 && PUBKEY_BYTES_LEN == 32 && ADDR_BYTES_LEN == 20)
+);
+
+/// Types with an encoding that is stable across crate versions.
+pub trait CanonicalBytes {
+    fn canonical_bytes(&self) -> Vec<u8>;
+}
+
+macro_rules! canonical_rlp {
+    ($($t:ty),* $(,)?) => {
+        $(
+            impl CanonicalBytes for $t {
+                fn canonical_bytes(&self) -> Vec<u8> {
+                    rlp::encode(self).to_vec()
+                }
+            }
+        )*
+    };
+}
+
+canonical_rlp!(
+    Signature,
+    PubKey,
+    Address,
+    AggregateSignature,
+    ConflictingVote,
+    Countersignature,
+    DoubleSignEvidence,
+    EscrowBlob,
+    GuardianShare,
+    KeyAttestation,
+    Migration,
+    MultiSealed,
+    SessionCertificate,
+    SuiteKey,
+    Watermark,
+    WrappedKey,
+    Checkpoint,
+    InclusionProof,
+    CosignCommitment,
+    PartialSignature,
+    HandoffRequest,
+    HandoffResponse,
+    SetCheckpoint,
+    BundleEntry,
+    UnsignedBundle,
+    BundleSignatures,
+    TimelockCommitment,
+);
+
+#[cfg(feature = "pq-hybrid")]
+canonical_rlp!(crate::HybridSignature);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cita_types::H256;
+
+    // Changing any of these is a breaking change to the canonical format.
+    #[test]
+    fn test_canonical_bytes_are_stable() {
+        let mut sig = [0u8; 96];
+        sig.iter_mut().enumerate().for_each(|(i, b)| *b = i as u8);
+        let bytes = Signature::from(sig).canonical_bytes();
+        assert_eq!(&bytes[..2], &[0xb8, 0x60]);
+        assert_eq!(&bytes[2..], &sig[..]);
+
+        let pubkey = PubKey::from_low_u64_be(0x0102);
+        let mut expected = vec![0xa0];
+        expected.extend_from_slice(&pubkey.0);
+        assert_eq!(pubkey.canonical_bytes(), expected);
+
+        let address = Address::from_low_u64_be(7);
+        let mut expected = vec![0x94];
+        expected.extend_from_slice(&address.0);
+        assert_eq!(address.canonical_bytes(), expected);
+    }
+
+    #[test]
+    fn test_canonical_bytes_of_composites_are_stable() {
+        let root = H256::from_low_u64_be(2);
+        let checkpoint = Checkpoint {
+            size: 1,
+            root,
+            signature: Signature::default(),
+        };
+        let mut expected = vec![0xf8, 0x84, 0x01, 0xa0];
+        expected.extend_from_slice(&root.0);
+        expected.extend_from_slice(&[0xb8, 0x60]);
+        expected.extend_from_slice(&[0u8; 96]);
+        assert_eq!(checkpoint.canonical_bytes(), expected);
+
+        let commitment = CosignCommitment {
+            r1: H256::from_low_u64_be(1),
+            r2: H256::from_low_u64_be(2),
+        };
+        let mut expected = vec![0xf8, 0x42, 0xa0];
+        expected.extend_from_slice(&commitment.r1.0);
+        expected.push(0xa0);
+        expected.extend_from_slice(&commitment.r2.0);
+        assert_eq!(commitment.canonical_bytes(), expected);
+
+        let partial = PartialSignature {
+            s: H256::from_low_u64_be(3),
+        };
+        let mut expected = vec![0xe1, 0xa0];
+        expected.extend_from_slice(&partial.s.0);
+        assert_eq!(partial.canonical_bytes(), expected);
+
+        let entry = BundleEntry {
+            message: H256::from_low_u64_be(4),
+            context: "ab".to_owned(),
+        };
+        let mut expected_entry = vec![0xe4, 0xa0];
+        expected_entry.extend_from_slice(&entry.message.0);
+        expected_entry.extend_from_slice(&[0x82, b'a', b'b']);
+        assert_eq!(entry.canonical_bytes(), expected_entry);
+
+        let bundle = UnsignedBundle {
+            signer: Address::from_low_u64_be(5),
+            entries: vec![entry],
+        };
+        let mut expected = vec![0xf8, 0x3b, 0x94];
+        expected.extend_from_slice(&bundle.signer.0);
+        expected.push(0xe5);
+        expected.extend_from_slice(&expected_entry);
+        assert_eq!(bundle.canonical_bytes(), expected);
+
+        let signatures = BundleSignatures {
+            bundle_id: H256::from_low_u64_be(6),
+            signatures: vec![Signature::default()],
+        };
+        let mut expected = vec![0xf8, 0x85, 0xa0];
+        expected.extend_from_slice(&signatures.bundle_id.0);
+        expected.extend_from_slice(&[0xf8, 0x62, 0xb8, 0x60]);
+        expected.extend_from_slice(&[0u8; 96]);
+        assert_eq!(signatures.canonical_bytes(), expected);
+    }
+}
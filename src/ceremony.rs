@@ -0,0 +1,250 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Auditable transcripts of key generation and rotation ceremonies.
+//!
+//! Each entry is signed by the participant who made it and chained to the
+//! entry before it. When the ceremony ends every participant signs a summary
+//! naming the final transcript hash, so the whole record can be checked
+//! later from the participants' public keys alone.
+
+use super::{Error, KeyPair, Message, PubKey, Signature};
+use crate::hasher::hash_data;
+use cita_crypto_trait::{CreateKey, Sign};
+use cita_types::H256;
+use rlp::RlpStream;
+use serde::{Deserialize, Serialize};
+
+const CEREMONY_DOMAIN: &[u8] = b"cita-ed25519 ceremony";
+
+/// One signed step of a ceremony, such as a commitment or a key share
+/// hand-off. Only the hash of the step's payload is recorded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptEntry {
+    pub sequence: u64,
+    pub participant: PubKey,
+    pub kind: String,
+    pub commitment: H256,
+    pub previous: H256,
+    pub signature: Signature,
+}
+
+impl TranscriptEntry {
+    fn hash(
+        sequence: u64,
+        participant: &PubKey,
+        kind: &str,
+        commitment: &H256,
+        previous: &H256,
+    ) -> Message {
+        let mut s = RlpStream::new_list(6);
+        s.append(&CEREMONY_DOMAIN);
+        s.append(&sequence);
+        s.append(participant);
+        s.append(&kind);
+        s.append(commitment);
+        s.append(previous);
+        hash_data(&s.out())
+    }
+
+    fn signing_hash(&self) -> Message {
+        Self::hash(
+            self.sequence,
+            &self.participant,
+            &self.kind,
+            &self.commitment,
+            &self.previous,
+        )
+    }
+}
+
+/// A ceremony in progress, or its complete transcript.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ceremony {
+    pub id: String,
+    pub participants: Vec<PubKey>,
+    pub entries: Vec<TranscriptEntry>,
+}
+
+/// The participants' joint statement that the transcript ended at
+/// `transcript_hash`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CeremonySummary {
+    pub id: String,
+    pub transcript_hash: H256,
+    pub entry_count: u64,
+    pub signatures: Vec<Signature>,
+}
+
+impl Ceremony {
+    pub fn new(id: &str, participants: &[PubKey]) -> Self {
+        Ceremony {
+            id: id.to_owned(),
+            participants: participants.to_vec(),
+            entries: Vec::new(),
+        }
+    }
+
+    // The hash the first entry chains to, fixing the id and participants.
+    fn genesis_hash(&self) -> H256 {
+        let mut s = RlpStream::new_list(3);
+        s.append(&CEREMONY_DOMAIN);
+        s.append(&self.id);
+        s.append_list(&self.participants);
+        hash_data(&s.out())
+    }
+
+    /// Hash of the last entry, or of the ceremony's header before any.
+    pub fn transcript_hash(&self) -> H256 {
+        self.entries
+            .last()
+            .map_or_else(|| self.genesis_hash(), |entry| entry.signing_hash())
+    }
+
+    /// Append a step made by `keypair`, committing to `payload`.
+    pub fn record(
+        &mut self,
+        keypair: &KeyPair,
+        kind: &str,
+        payload: &[u8],
+    ) -> Result<&TranscriptEntry, Error> {
+        if !self.participants.contains(keypair.pubkey()) {
+            return Err(Error::InvalidPubKey);
+        }
+        let sequence = self.entries.len() as u64;
+        let commitment = hash_data(payload);
+        let previous = self.transcript_hash();
+        let message =
+            TranscriptEntry::hash(sequence, keypair.pubkey(), kind, &commitment, &previous);
+        self.entries.push(TranscriptEntry {
+            sequence,
+            participant: *keypair.pubkey(),
+            kind: kind.to_owned(),
+            commitment,
+            previous,
+            signature: Signature::sign(keypair.privkey(), &message)?,
+        });
+        Ok(self.entries.last().unwrap())
+    }
+
+    /// Check the chain of entries and every entry's signature.
+    pub fn verify(&self) -> Result<(), Error> {
+        let mut previous = self.genesis_hash();
+        for (i, entry) in self.entries.iter().enumerate() {
+            if entry.sequence != i as u64
+                || entry.previous != previous
+                || !self.participants.contains(&entry.participant)
+            {
+                return Err(Error::InvalidSignature);
+            }
+            let message = entry.signing_hash();
+            entry
+                .signature
+                .verify_public(&entry.participant, &message)?;
+            previous = message;
+        }
+        Ok(())
+    }
+
+    /// An unsigned summary of the transcript so far.
+    pub fn summary(&self) -> CeremonySummary {
+        CeremonySummary {
+            id: self.id.clone(),
+            transcript_hash: self.transcript_hash(),
+            entry_count: self.entries.len() as u64,
+            signatures: Vec::new(),
+        }
+    }
+
+    /// Check the transcript and that every participant signed `summary`
+    /// of it.
+    pub fn verify_summary(&self, summary: &CeremonySummary) -> Result<(), Error> {
+        self.verify()?;
+        if summary.id != self.id
+            || summary.transcript_hash != self.transcript_hash()
+            || summary.entry_count != self.entries.len() as u64
+        {
+            return Err(Error::InvalidMessage);
+        }
+        let message = summary.signing_hash();
+        for participant in &self.participants {
+            let signature = summary
+                .signatures
+                .iter()
+                .find(|s| s.pk() == participant.as_ref())
+                .ok_or(Error::InvalidSignature)?;
+            signature.verify_public(participant, &message)?;
+        }
+        Ok(())
+    }
+}
+
+impl CeremonySummary {
+    pub fn signing_hash(&self) -> Message {
+        let mut s = RlpStream::new_list(4);
+        s.append(&CEREMONY_DOMAIN);
+        s.append(&self.id);
+        s.append(&self.transcript_hash);
+        s.append(&self.entry_count);
+        hash_data(&s.out())
+    }
+
+    pub fn sign(&mut self, keypair: &KeyPair) -> Result<(), Error> {
+        let signature = Signature::sign(keypair.privkey(), &self.signing_hash())?;
+        self.signatures.retain(|s| s.pk() != signature.pk());
+        self.signatures.push(signature);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ceremony_transcript() {
+        let keys: Vec<KeyPair> = (0..3).map(|_| KeyPair::gen_keypair()).collect();
+        let pubkeys: Vec<PubKey> = keys.iter().map(|k| *k.pubkey()).collect();
+        let mut ceremony = Ceremony::new("rotation-2026-10", &pubkeys);
+        for key in &keys {
+            ceremony
+                .record(key, "commitment", key.pubkey().as_ref())
+                .unwrap();
+        }
+        ceremony
+            .record(&keys[0], "new-validator-set", b"v2")
+            .unwrap();
+        assert!(ceremony
+            .record(&KeyPair::gen_keypair(), "commitment", b"x")
+            .is_err());
+
+        let mut summary = ceremony.summary();
+        for key in &keys[..2] {
+            summary.sign(key).unwrap();
+        }
+        assert!(ceremony.verify_summary(&summary).is_err());
+        summary.sign(&keys[2]).unwrap();
+
+        let json = serde_json::to_string(&(&ceremony, &summary)).unwrap();
+        let (ceremony, summary): (Ceremony, CeremonySummary) = serde_json::from_str(&json).unwrap();
+        ceremony.verify_summary(&summary).unwrap();
+
+        let mut dropped = ceremony.clone();
+        dropped.entries.remove(1);
+        assert!(dropped.verify().is_err());
+        let mut extended = ceremony;
+        extended.record(&keys[1], "late", b"").unwrap();
+        assert!(extended.verify_summary(&summary).is_err());
+    }
+}
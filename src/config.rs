@@ -0,0 +1,250 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tamper-evident node configuration files.
+//!
+//! The signature covers the parsed configuration in canonical JSON form, so
+//! whitespace, comments and key order can change without invalidating it.
+//! Floating-point values have no canonical form, so files holding them
+//! cannot be signed.
+//! JSON files carry the signature under a top-level `config_signature` key.
+//! TOML files carry it in a `[config_signature]` table appended at the end,
+//! which leaves the rest of the file untouched. The table must stay the last
+//! one in the file and hold only `signer` and `signature`; anything after it
+//! would not be covered by the signature, so verification rejects it.
+
+use super::{canonical_json, Error, KeyPair, Message, PubKey, Signature};
+use crate::hasher::hash_data;
+use crate::hex::Hex;
+use cita_crypto_trait::{CreateKey, Sign};
+use serde_json::Value;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+const CONFIG_DOMAIN: &[u8] = b"cita-ed25519 config";
+const SIGNATURE_KEY: &str = "config_signature";
+const TOML_HEADER: &str = "[config_signature]";
+
+fn is_toml(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "toml")
+}
+
+fn config_hash(config: &Value) -> Result<Message, Error> {
+    let mut bytes = CONFIG_DOMAIN.to_vec();
+    bytes.extend_from_slice(&canonical_json(config)?);
+    Ok(hash_data(&bytes))
+}
+
+// Byte offset of the line holding the `[config_signature]` header, which
+// may end in CRLF.
+fn toml_block_start(text: &str) -> Option<usize> {
+    let mut at = 0;
+    for line in text.split_inclusive('\n') {
+        if line.trim_end_matches(&['\r', '\n'][..]) == TOML_HEADER {
+            return Some(at);
+        }
+        at += line.len();
+    }
+    None
+}
+
+// The signature block must be the last table and hold only `signer` and
+// `signature`, so nothing unsigned can follow it.
+fn parse_toml_block(block: &str) -> Result<Value, Error> {
+    let mut block: toml::Table = toml::from_str(block).map_err(|_| Error::InvalidEncoding)?;
+    let signature = block.remove(SIGNATURE_KEY).ok_or(Error::InvalidEncoding)?;
+    let fields = signature.as_table().ok_or(Error::InvalidEncoding)?;
+    let only_signature_fields = fields.len() == 2
+        && fields.get("signer").is_some_and(toml::Value::is_str)
+        && fields.get("signature").is_some_and(toml::Value::is_str);
+    if !block.is_empty() || !only_signature_fields {
+        return Err(Error::InvalidEncoding);
+    }
+    serde_json::to_value(signature).map_err(|_| Error::InvalidEncoding)
+}
+
+// Split a file into its configuration and the signature block, if any.
+fn parse(path: &Path, text: &str) -> Result<(Value, String, Option<Value>), Error> {
+    if is_toml(path) {
+        let (body, block) = match toml_block_start(text) {
+            Some(at) => (&text[..at], Some(&text[at..])),
+            None => (text, None),
+        };
+        let config: toml::Value = toml::from_str(body).map_err(|_| Error::InvalidEncoding)?;
+        let block = block.map(parse_toml_block).transpose()?;
+        let config = serde_json::to_value(config).map_err(|_| Error::InvalidEncoding)?;
+        Ok((config, body.to_owned(), block))
+    } else {
+        let mut config: Value = serde_json::from_str(text).map_err(|_| Error::InvalidEncoding)?;
+        let block = config
+            .as_object_mut()
+            .ok_or(Error::InvalidEncoding)?
+            .remove(SIGNATURE_KEY);
+        Ok((config, String::new(), block))
+    }
+}
+
+/// Sign the configuration at `path` with `keypair`, replacing any existing
+/// signature block.
+pub fn sign_config<P: AsRef<Path>>(path: P, keypair: &KeyPair) -> Result<(), Error> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    let (mut config, body, _) = parse(path, &text)?;
+    let signature = Signature::sign(keypair.privkey(), &config_hash(&config)?)?;
+    let signer = Hex(&keypair.pubkey().0).to_string();
+    let signature = String::from(signature);
+
+    let signed = if is_toml(path) {
+        // Keep the file's own line endings.
+        let eol = if body.contains("\r\n") { "\r\n" } else { "\n" };
+        let body = body.trim_end_matches(&['\r', '\n'][..]);
+        let gap = if body.is_empty() { "" } else { eol };
+        format!(
+            "{body}{eol}{gap}{header}{eol}signer = \"{signer}\"{eol}signature = \"{signature}\"{eol}",
+            body = body,
+            eol = eol,
+            gap = gap,
+            header = TOML_HEADER,
+            signer = signer,
+            signature = signature,
+        )
+    } else {
+        config.as_object_mut().unwrap().insert(
+            SIGNATURE_KEY.to_owned(),
+            serde_json::json!({ "signer": signer, "signature": signature }),
+        );
+        serde_json::to_string_pretty(&config).map_err(|_| Error::InvalidEncoding)? + "\n"
+    };
+    fs::write(path, signed)?;
+    Ok(())
+}
+
+/// Check the signature block of the configuration at `path` and that its
+/// signer is one of `trusted_keys`. Returns the signer.
+pub fn verify_config<P: AsRef<Path>>(path: P, trusted_keys: &[PubKey]) -> Result<PubKey, Error> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    let (config, _, block) = parse(path, &text)?;
+    let block = block.ok_or(Error::InvalidSignature)?;
+    let field = |name: &str| {
+        block
+            .get(name)
+            .and_then(Value::as_str)
+            .ok_or(Error::InvalidSignature)
+            .and_then(crate::decode_hex_ct)
+    };
+    let signer = PubKey::from_slice(
+        &<[u8; 32]>::try_from(&field("signer")?[..]).map_err(|_| Error::InvalidPubKey)?,
+    );
+    let signature = Signature::try_from(field("signature")?)?;
+    if !trusted_keys.contains(&signer) {
+        return Err(Error::InvalidPubKey);
+    }
+    signature.verify_public(&signer, &config_hash(&config)?)?;
+    Ok(signer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::path::PathBuf;
+    use std::process;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("cita-ed25519-{}-{}", process::id(), name))
+    }
+
+    #[test]
+    fn test_signed_toml_config() {
+        let path = temp_path("node.toml");
+        fs::write(
+            &path,
+            "# node settings\nport = 50000\n\n[network]\npeers = [\"a\", \"b\"]\n",
+        )
+        .unwrap();
+        let keypair = KeyPair::gen_keypair();
+        sign_config(&path, &keypair).unwrap();
+        // Signing again replaces the block instead of stacking another one.
+        sign_config(&path, &keypair).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("# node settings\n"));
+        assert_eq!(text.matches("[config_signature]").count(), 1);
+        assert_eq!(
+            verify_config(&path, &[*keypair.pubkey()]).unwrap(),
+            *keypair.pubkey()
+        );
+        assert!(verify_config(&path, &[*KeyPair::gen_keypair().pubkey()]).is_err());
+
+        // Reformatting is fine, changing a value is not.
+        fs::write(&path, text.replace("port = 50000", "port    =   50000 # p")).unwrap();
+        assert!(verify_config(&path, &[*keypair.pubkey()]).is_ok());
+        fs::write(&path, text.replace("50000", "50001")).unwrap();
+        assert!(verify_config(&path, &[*keypair.pubkey()]).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_signed_toml_config_rejects_trailing_content() {
+        let path = temp_path("trailing.toml");
+        fs::write(&path, "port = 50000\n").unwrap();
+        let keypair = KeyPair::gen_keypair();
+        sign_config(&path, &keypair).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(verify_config(&path, &[*keypair.pubkey()]).is_ok());
+
+        fs::write(&path, format!("{}\n[rpc]\nallow_all = true\n", text)).unwrap();
+        assert!(verify_config(&path, &[*keypair.pubkey()]).is_err());
+        fs::write(&path, format!("{}extra = 1\n", text)).unwrap();
+        assert!(verify_config(&path, &[*keypair.pubkey()]).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_signed_toml_config_crlf() {
+        let path = temp_path("crlf.toml");
+        fs::write(
+            &path,
+            "port = 50000\r\n\r\n[network]\r\npeers = [\"a\"]\r\n",
+        )
+        .unwrap();
+        let keypair = KeyPair::gen_keypair();
+        sign_config(&path, &keypair).unwrap();
+        sign_config(&path, &keypair).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(text.matches("[config_signature]").count(), 1);
+        assert!(!text.replace("\r\n", "").contains('\n'));
+        assert!(verify_config(&path, &[*keypair.pubkey()]).is_ok());
+
+        fs::write(&path, text.replace("\r\n", "\n")).unwrap();
+        assert!(verify_config(&path, &[*keypair.pubkey()]).is_ok());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_signed_json_config() {
+        let path = temp_path("node.json");
+        fs::write(&path, r#"{"port": 50000, "peers": ["a"]}"#).unwrap();
+        let keypair = KeyPair::gen_keypair();
+        assert!(verify_config(&path, &[*keypair.pubkey()]).is_err());
+        sign_config(&path, &keypair).unwrap();
+        assert!(verify_config(&path, &[*keypair.pubkey()]).is_ok());
+
+        let text = fs::read_to_string(&path).unwrap();
+        fs::write(&path, text.replace("\"a\"", "\"b\"")).unwrap();
+        assert!(verify_config(&path, &[*keypair.pubkey()]).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,291 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Two-party co-signing: MuSig2 (Nick, Ruffing and Seurin, 2021) adapted to
+//! Ed25519.
+//!
+//! Both keys combine into `X = a_1 * A_1 + a_2 * A_2`, with each `a_i` bound
+//! to the pair. In round one every party sends two nonce points; in round two
+//! it sends `s_i = r_i1 + b * r_i2 + c * a_i * x_i`. The sum of the partial
+//! signatures is an ordinary Ed25519 signature under `X`, so verifiers cannot
+//! tell it apart from one made by a single key.
+
+use super::{Error, KeyPair, Message, PubKey, Signature};
+use crate::curve::{
+    base_mul, hash_to_scalar, is_canonical_scalar, point_add, point_mul, reduce, scalar_add,
+    scalar_mul, ExpandedSecret, Point, Scalar,
+};
+use cita_crypto_trait::CreateKey;
+use cita_types::H256;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+use sodiumoxide::randombytes::randombytes_into;
+use sodiumoxide::utils::memzero;
+
+const KEY_DOMAIN: &[u8] = b"cita-ed25519 cosign key";
+const NONCE_DOMAIN: &[u8] = b"cita-ed25519 cosign nonce";
+
+/// The joint public key of two co-signers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CosignKey {
+    // Sorted, so both parties agree on the coefficients.
+    pubkeys: [PubKey; 2],
+    coefficients: [Scalar; 2],
+    pubkey: PubKey,
+}
+
+impl CosignKey {
+    pub fn new(first: &PubKey, second: &PubKey) -> Result<Self, Error> {
+        if first == second {
+            return Err(Error::InvalidPubKey);
+        }
+        let mut pubkeys = [*first, *second];
+        pubkeys.sort();
+        let coefficients = [0, 1]
+            .map(|i| hash_to_scalar(&[KEY_DOMAIN, &pubkeys[0].0, &pubkeys[1].0, &pubkeys[i].0]));
+        let joint = point_add(
+            &point_mul(&coefficients[0], &pubkeys[0].0)?,
+            &point_mul(&coefficients[1], &pubkeys[1].0)?,
+        )?;
+        Ok(CosignKey {
+            pubkeys,
+            coefficients,
+            pubkey: PubKey::from(joint),
+        })
+    }
+
+    /// The key the combined signature verifies under.
+    pub fn pubkey(&self) -> &PubKey {
+        &self.pubkey
+    }
+
+    fn coefficient(&self, pubkey: &PubKey) -> Result<&Scalar, Error> {
+        self.pubkeys
+            .iter()
+            .position(|p| p == pubkey)
+            .map(|i| &self.coefficients[i])
+            .ok_or(Error::InvalidPubKey)
+    }
+}
+
+/// Round one message: a party's two public nonces.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CosignCommitment {
+    pub r1: H256,
+    pub r2: H256,
+}
+
+impl Encodable for CosignCommitment {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2);
+        s.append(&self.r1);
+        s.append(&self.r2);
+    }
+}
+
+impl Decodable for CosignCommitment {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 2 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(CosignCommitment {
+            r1: rlp.val_at(0)?,
+            r2: rlp.val_at(1)?,
+        })
+    }
+}
+
+/// Round two message: a party's share of `S`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialSignature {
+    pub s: H256,
+}
+
+impl Encodable for PartialSignature {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(1);
+        s.append(&self.s);
+    }
+}
+
+impl Decodable for PartialSignature {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 1 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(PartialSignature { s: rlp.val_at(0)? })
+    }
+}
+
+struct SecretNonces([Scalar; 2]);
+
+impl Drop for SecretNonces {
+    fn drop(&mut self) {
+        memzero(&mut self.0[0]);
+        memzero(&mut self.0[1]);
+    }
+}
+
+// Values fixed once both commitments and the message are known.
+struct Challenge {
+    commitments: [CosignCommitment; 2],
+    b: Scalar,
+    big_r: Point,
+    c: Scalar,
+    s: Scalar,
+}
+
+/// One party's side of a single co-signing run.
+///
+/// The secret nonces are erased as soon as the partial signature is made;
+/// a session cannot sign twice.
+pub struct CosignSession<'a> {
+    keypair: &'a KeyPair,
+    other: PubKey,
+    key: CosignKey,
+    nonces: Option<SecretNonces>,
+    commitment: CosignCommitment,
+    challenge: Option<Challenge>,
+}
+
+fn random_scalar() -> Scalar {
+    let mut wide = [0u8; 64];
+    randombytes_into(&mut wide);
+    let r = reduce(&wide);
+    memzero(&mut wide);
+    r
+}
+
+impl<'a> CosignSession<'a> {
+    /// Round one: draw fresh nonces. The returned commitment goes to the
+    /// other party.
+    pub fn new(keypair: &'a KeyPair, other: &PubKey) -> Result<(Self, CosignCommitment), Error> {
+        let key = CosignKey::new(keypair.pubkey(), other)?;
+        let nonces = SecretNonces([random_scalar(), random_scalar()]);
+        let commitment = CosignCommitment {
+            r1: H256::from(base_mul(&nonces.0[0])?),
+            r2: H256::from(base_mul(&nonces.0[1])?),
+        };
+        let session = CosignSession {
+            keypair,
+            other: *other,
+            key,
+            nonces: Some(nonces),
+            commitment: commitment.clone(),
+            challenge: None,
+        };
+        Ok((session, commitment))
+    }
+
+    pub fn key(&self) -> &CosignKey {
+        &self.key
+    }
+
+    /// Round two: sign `message` given the other party's commitment.
+    pub fn partial_sign(
+        &mut self,
+        theirs: &CosignCommitment,
+        message: &Message,
+    ) -> Result<PartialSignature, Error> {
+        let nonces = self.nonces.take().ok_or(Error::NonceReused)?;
+        let ours = self.commitment.clone();
+        let r1 = point_add(&ours.r1.0, &theirs.r1.0)?;
+        let r2 = point_add(&ours.r2.0, &theirs.r2.0)?;
+        let joint = &self.key.pubkey.0;
+        let b = hash_to_scalar(&[NONCE_DOMAIN, joint, &r1, &r2, message.as_ref()]);
+        let big_r = point_add(&r1, &point_mul(&b, &r2)?)?;
+        let c = hash_to_scalar(&[&big_r, joint, message.as_ref()]);
+
+        let mut secret = ExpandedSecret::from_seed(&self.keypair.privkey().0[..32]).scalar;
+        let mut weighted = scalar_mul(self.key.coefficient(self.keypair.pubkey())?, &secret);
+        let s = scalar_add(
+            &scalar_add(&nonces.0[0], &scalar_mul(&b, &nonces.0[1])),
+            &scalar_mul(&c, &weighted),
+        );
+        memzero(&mut secret);
+        memzero(&mut weighted);
+
+        self.challenge = Some(Challenge {
+            commitments: [ours, theirs.clone()],
+            b,
+            big_r,
+            c,
+            s,
+        });
+        Ok(PartialSignature { s: H256::from(s) })
+    }
+
+    /// Check the other party's partial signature and combine it with ours.
+    pub fn combine(&self, theirs: &PartialSignature) -> Result<Signature, Error> {
+        let challenge = self.challenge.as_ref().ok_or(Error::InvalidSignature)?;
+        if !is_canonical_scalar(&theirs.s.0) {
+            return Err(Error::InvalidSignature);
+        }
+        // s_j * B == R_j1 + b * R_j2 + c * a_j * A_j
+        let commitment = &challenge.commitments[1];
+        let a = self.key.coefficient(&self.other)?;
+        let expected = point_add(
+            &point_add(
+                &commitment.r1.0,
+                &point_mul(&challenge.b, &commitment.r2.0)?,
+            )?,
+            &point_mul(&scalar_mul(&challenge.c, a), &self.other.0)?,
+        )?;
+        if base_mul(&theirs.s.0)? != expected {
+            return Err(Error::InvalidSignature);
+        }
+
+        let mut ret = [0u8; 96];
+        ret[0..32].copy_from_slice(&challenge.big_r);
+        ret[32..64].copy_from_slice(&scalar_add(&challenge.s, &theirs.s.0));
+        ret[64..96].copy_from_slice(&self.key.pubkey.0);
+        Ok(Signature(ret))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cita_crypto_trait::Sign;
+
+    #[test]
+    fn test_cosign() {
+        let client = KeyPair::gen_keypair();
+        let server = KeyPair::gen_keypair();
+        let message = Message::from_low_u64_be(485);
+
+        let (mut a, commit_a) = CosignSession::new(&client, server.pubkey()).unwrap();
+        let (mut b, commit_b) = CosignSession::new(&server, client.pubkey()).unwrap();
+        assert_eq!(a.key(), b.key());
+        let commit_a: CosignCommitment = rlp::decode(&rlp::encode(&commit_a)).unwrap();
+
+        let part_a = a.partial_sign(&commit_b, &message).unwrap();
+        let part_b = b.partial_sign(&commit_a, &message).unwrap();
+        assert!(matches!(
+            a.partial_sign(&commit_b, &message),
+            Err(Error::NonceReused)
+        ));
+
+        let signature = a.combine(&part_b).unwrap();
+        assert_eq!(b.combine(&part_a).unwrap(), signature);
+        assert_eq!(signature.recover(&message).unwrap(), *a.key().pubkey());
+        assert!(signature
+            .verify_public(a.key().pubkey(), &Message::from_low_u64_be(1))
+            .is_err());
+
+        let mut bad = part_b.clone();
+        bad.s.0[0] ^= 1;
+        assert!(a.combine(&bad).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Local timestamping: a node key vouches that a signature existed at a
+//! given time by signing the signature together with that time.
+
+use super::{Error, KeyPair, Message, PubKey, Signature};
+use crate::clock::now;
+use crate::hasher::hash_data;
+use cita_crypto_trait::{CreateKey, Sign};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+
+const COUNTERSIGN_DOMAIN: &[u8] = b"cita-ed25519 countersignature";
+
+/// A timestamp key's signature over an earlier signature and a time in
+/// seconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Countersignature {
+    pub timestamp: u64,
+    pub signature: Signature,
+}
+
+fn countersign_hash(original: &Signature, timestamp: u64) -> Message {
+    let mut s = RlpStream::new_list(3);
+    s.append(&COUNTERSIGN_DOMAIN);
+    s.append(original);
+    s.append(&timestamp);
+    hash_data(&s.out())
+}
+
+/// Countersign `existing_signature` with the current time.
+pub fn countersign(
+    existing_signature: &Signature,
+    timestamp_keypair: &KeyPair,
+) -> Result<Countersignature, Error> {
+    countersign_at(existing_signature, timestamp_keypair, now())
+}
+
+pub fn countersign_at(
+    existing_signature: &Signature,
+    timestamp_keypair: &KeyPair,
+    timestamp: u64,
+) -> Result<Countersignature, Error> {
+    let message = countersign_hash(existing_signature, timestamp);
+    Ok(Countersignature {
+        timestamp,
+        signature: Signature::sign(timestamp_keypair.privkey(), &message)?,
+    })
+}
+
+impl Countersignature {
+    /// The key that countersigned.
+    pub fn signer(&self) -> PubKey {
+        PubKey::from_slice(self.signature.pk())
+    }
+
+    /// Check that this countersigns `original`.
+    pub fn verify(&self, original: &Signature) -> Result<(), Error> {
+        let message = countersign_hash(original, self.timestamp);
+        self.signature.recover(&message)?;
+        Ok(())
+    }
+}
+
+/// Check `original` over `message`, then that each countersignature in
+/// `chain` countersigns the one before it (the first countersigns `original`)
+/// and that timestamps never go backwards.
+///
+/// Returns the timestamp of the last link. Which timestamp keys to trust is
+/// up to the caller, see [`Countersignature::signer`].
+pub fn verify_countersignature_chain(
+    original: &Signature,
+    message: &Message,
+    chain: &[Countersignature],
+) -> Result<u64, Error> {
+    original.recover(message)?;
+    let mut previous = original;
+    let mut time = 0;
+    for link in chain {
+        if link.timestamp < time {
+            return Err(Error::InvalidSignature);
+        }
+        link.verify(previous)?;
+        previous = &link.signature;
+        time = link.timestamp;
+    }
+    Ok(time)
+}
+
+impl Encodable for Countersignature {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2);
+        s.append(&self.timestamp);
+        s.append(&self.signature);
+    }
+}
+
+impl Decodable for Countersignature {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 2 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(Countersignature {
+            timestamp: rlp.val_at(0)?,
+            signature: rlp.val_at(1)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_countersignature_chain() {
+        let author = KeyPair::gen_keypair();
+        let (tsa1, tsa2) = (KeyPair::gen_keypair(), KeyPair::gen_keypair());
+        let msg = Message::from_low_u64_be(11);
+        let original = Signature::sign(author.privkey(), &msg).unwrap();
+
+        let first = countersign(&original, &tsa1).unwrap();
+        assert_eq!(first.signer(), *tsa1.pubkey());
+        let second = countersign_at(&first.signature, &tsa2, first.timestamp + 5).unwrap();
+        let second: Countersignature = rlp::decode(&rlp::encode(&second)).unwrap();
+        let chain = vec![first.clone(), second];
+        assert_eq!(
+            verify_countersignature_chain(&original, &msg, &chain).unwrap(),
+            first.timestamp + 5
+        );
+
+        let reordered = vec![chain[1].clone(), chain[0].clone()];
+        assert!(verify_countersignature_chain(&original, &msg, &reordered).is_err());
+        let mut backdated = first;
+        backdated.timestamp -= 1;
+        assert!(backdated.verify(&original).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hex and base64 decoding without table lookups or branches on the decoded
+//! characters, for parsing secrets.
+//!
+//! Only the input length and overall validity are revealed by timing.
+
+use super::{Error, PrivKey, PRIVKEY_BYTES_LEN};
+use sodiumoxide::utils::memzero;
+
+// 0xff when a < b, else 0.
+fn lt_mask(a: u8, b: u8) -> u8 {
+    ((a as u16).wrapping_sub(b as u16) >> 8) as u8
+}
+
+// 0xff when lo <= c < lo + len, else 0.
+fn range_mask(c: u8, lo: u8, len: u8) -> u8 {
+    lt_mask(c.wrapping_sub(lo), len) & !lt_mask(c, lo)
+}
+
+// Value of a hex digit and a mask that is 0xff when it is one.
+fn hex_digit(c: u8) -> (u8, u8) {
+    let digit = range_mask(c, b'0', 10);
+    let lower = range_mask(c, b'a', 6);
+    let upper = range_mask(c, b'A', 6);
+    let value = (digit & c.wrapping_sub(b'0'))
+        | (lower & c.wrapping_sub(b'a' - 10))
+        | (upper & c.wrapping_sub(b'A' - 10));
+    (value, digit | lower | upper)
+}
+
+fn base64_digit(c: u8) -> (u8, u8) {
+    let upper = range_mask(c, b'A', 26);
+    let lower = range_mask(c, b'a', 26);
+    let digit = range_mask(c, b'0', 10);
+    let plus = range_mask(c, b'+', 1);
+    let slash = range_mask(c, b'/', 1);
+    let value = (upper & c.wrapping_sub(b'A'))
+        | (lower & c.wrapping_sub(b'a' - 26))
+        | (digit & c.wrapping_add(52 - b'0'))
+        | (plus & 62)
+        | (slash & 63);
+    (value, upper | lower | digit | plus | slash)
+}
+
+/// Decode hex, with an optional `0x` prefix.
+pub fn decode_hex_ct(input: &str) -> Result<Vec<u8>, Error> {
+    let input = input.strip_prefix("0x").unwrap_or(input).as_bytes();
+    if input.len() % 2 != 0 {
+        return Err(Error::InvalidEncoding);
+    }
+    let mut out = vec![0u8; input.len() / 2];
+    let mut valid = 0xffu8;
+    for (byte, pair) in out.iter_mut().zip(input.chunks(2)) {
+        let (hi, hi_ok) = hex_digit(pair[0]);
+        let (lo, lo_ok) = hex_digit(pair[1]);
+        *byte = (hi << 4) | lo;
+        valid &= hi_ok & lo_ok;
+    }
+    if valid != 0xff {
+        memzero(&mut out);
+        return Err(Error::InvalidEncoding);
+    }
+    Ok(out)
+}
+
+/// Decode standard base64, with or without `=` padding.
+pub fn decode_base64_ct(input: &str) -> Result<Vec<u8>, Error> {
+    let mut input = input.as_bytes();
+    if input.len() % 4 == 0 {
+        for _ in 0..2 {
+            if let Some(stripped) = input.strip_suffix(b"=") {
+                input = stripped;
+            }
+        }
+    }
+    if input.len() % 4 == 1 {
+        return Err(Error::InvalidEncoding);
+    }
+
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut valid = 0xffu8;
+    for chunk in input.chunks(4) {
+        let mut acc = 0u32;
+        for c in chunk {
+            let (value, ok) = base64_digit(*c);
+            acc = (acc << 6) | u32::from(value);
+            valid &= ok;
+        }
+        acc <<= 6 * (4 - chunk.len()) as u32;
+        let bytes = acc.to_be_bytes();
+        out.extend_from_slice(&bytes[1..chunk.len()]);
+    }
+    if valid != 0xff {
+        memzero(&mut out);
+        return Err(Error::InvalidEncoding);
+    }
+    Ok(out)
+}
+
+/// Parse a 64-byte private key from hex in constant time.
+pub fn privkey_from_hex(input: &str) -> Result<PrivKey, Error> {
+    let mut bytes = decode_hex_ct(input).map_err(|_| Error::InvalidPrivKey)?;
+    let privkey = if bytes.len() == PRIVKEY_BYTES_LEN {
+        Ok(PrivKey::from_slice(&bytes))
+    } else {
+        Err(Error::InvalidPrivKey)
+    };
+    memzero(&mut bytes);
+    privkey
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_serialize::base64::{ToBase64, STANDARD};
+    use rustc_serialize::hex::ToHex;
+
+    #[test]
+    fn test_decode_matches_reference() {
+        let bytes: Vec<u8> = (0..=255u8).collect();
+        assert_eq!(decode_hex_ct(&bytes.to_hex()).unwrap(), bytes);
+        assert_eq!(
+            decode_hex_ct(&bytes.to_hex().to_uppercase()).unwrap(),
+            bytes
+        );
+        for len in 0..8 {
+            let encoded = bytes[..len].to_base64(STANDARD);
+            assert_eq!(decode_base64_ct(&encoded).unwrap(), &bytes[..len]);
+            let unpadded = encoded.trim_end_matches('=');
+            assert_eq!(decode_base64_ct(unpadded).unwrap(), &bytes[..len]);
+        }
+        assert!(decode_hex_ct("0g").is_err());
+        assert!(decode_hex_ct("abc").is_err());
+        assert!(decode_base64_ct("ab-d").is_err());
+    }
+
+    #[test]
+    fn test_privkey_from_hex() {
+        let privkey = PrivKey::from_low_u64_be(0xdead_beef);
+        assert_eq!(privkey_from_hex(&privkey.0.to_hex()).unwrap(), privkey);
+        assert!(privkey_from_hex("00").is_err());
+    }
+}
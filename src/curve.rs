@@ -0,0 +1,167 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Thin safe wrappers over libsodium's Ed25519 scalar and point arithmetic.
+//!
+//! Scalars are 32-byte little-endian values reduced modulo the group order L;
+//! points are 32-byte compressed Edwards y-coordinates.
+
+use super::Error;
+use libsodium_sys as ffi;
+use sodiumoxide::crypto::hash::sha512;
+use sodiumoxide::utils::memzero;
+
+pub(crate) type Scalar = [u8; 32];
+pub(crate) type Point = [u8; 32];
+
+/// The group order L, little-endian.
+pub(crate) const GROUP_ORDER: Scalar = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+];
+
+/// Whether `s` is below L, i.e. the only encoding of its residue.
+pub(crate) fn is_canonical_scalar(s: &[u8; 32]) -> bool {
+    for i in (0..32).rev() {
+        if s[i] != GROUP_ORDER[i] {
+            return s[i] < GROUP_ORDER[i];
+        }
+    }
+    false
+}
+
+/// The canonical encoding of a compressed point.
+///
+/// A y-coordinate in `[p, 2^255)` is replaced by `y - p`, and the sign bit is
+/// cleared when x is zero (y = 1 or y = p - 1), where it carries no meaning.
+/// Whether the result lies on the curve is not checked.
+pub(crate) fn canonical_point(point: &[u8; 32]) -> Point {
+    let mut out = *point;
+    let sign = out[31] & 0x80;
+    out[31] &= 0x7f;
+    // y >= p = 2^255 - 19 only when every limb is saturated.
+    if out[0] >= 0xed && out[1..31].iter().all(|b| *b == 0xff) && out[31] == 0x7f {
+        out[0] -= 0xed;
+        for b in &mut out[1..] {
+            *b = 0;
+        }
+    }
+    let y_is_one = out[0] == 1 && out[1..].iter().all(|b| *b == 0);
+    let y_is_minus_one = out[0] == 0xec && out[1..31].iter().all(|b| *b == 0xff) && out[31] == 0x7f;
+    if !(y_is_one || y_is_minus_one) {
+        out[31] |= sign;
+    }
+    out
+}
+
+/// Reduce a 64-byte little-endian value modulo L.
+pub(crate) fn reduce(wide: &[u8; 64]) -> Scalar {
+    let mut out = [0u8; 32];
+    unsafe { ffi::crypto_core_ed25519_scalar_reduce(out.as_mut_ptr(), wide.as_ptr()) };
+    out
+}
+
+/// Reduce a 32-byte little-endian value modulo L.
+pub(crate) fn reduce32(s: &[u8; 32]) -> Scalar {
+    let mut wide = [0u8; 64];
+    wide[..32].copy_from_slice(s);
+    let out = reduce(&wide);
+    memzero(&mut wide);
+    out
+}
+
+pub(crate) fn scalar_add(x: &Scalar, y: &Scalar) -> Scalar {
+    let mut out = [0u8; 32];
+    unsafe { ffi::crypto_core_ed25519_scalar_add(out.as_mut_ptr(), x.as_ptr(), y.as_ptr()) };
+    out
+}
+
+pub(crate) fn scalar_mul(x: &Scalar, y: &Scalar) -> Scalar {
+    let mut out = [0u8; 32];
+    unsafe { ffi::crypto_core_ed25519_scalar_mul(out.as_mut_ptr(), x.as_ptr(), y.as_ptr()) };
+    out
+}
+
+/// `s * B` for a reduced scalar `s`; fails for the identity.
+pub(crate) fn base_mul(s: &Scalar) -> Result<Point, Error> {
+    let mut out = [0u8; 32];
+    let ret = unsafe { ffi::crypto_scalarmult_ed25519_base_noclamp(out.as_mut_ptr(), s.as_ptr()) };
+    if ret != 0 {
+        return Err(Error::InvalidPrivKey);
+    }
+    Ok(out)
+}
+
+/// `s * p` for a reduced scalar `s`; fails for invalid or small-order
+/// points and for an identity result.
+pub(crate) fn point_mul(s: &Scalar, p: &Point) -> Result<Point, Error> {
+    let mut out = [0u8; 32];
+    let ret =
+        unsafe { ffi::crypto_scalarmult_ed25519_noclamp(out.as_mut_ptr(), s.as_ptr(), p.as_ptr()) };
+    if ret != 0 {
+        return Err(Error::InvalidSignature);
+    }
+    Ok(out)
+}
+
+/// `p + q`; fails unless both are valid points.
+pub(crate) fn point_add(p: &Point, q: &Point) -> Result<Point, Error> {
+    let mut out = [0u8; 32];
+    let ret = unsafe { ffi::crypto_core_ed25519_add(out.as_mut_ptr(), p.as_ptr(), q.as_ptr()) };
+    if ret != 0 {
+        return Err(Error::InvalidPubKey);
+    }
+    Ok(out)
+}
+
+/// SHA-512 of the concatenated `parts`, reduced modulo L.
+pub(crate) fn hash_to_scalar(parts: &[&[u8]]) -> Scalar {
+    let mut state = sha512::State::new();
+    for part in parts {
+        state.update(part);
+    }
+    reduce(&state.finalize().0)
+}
+
+/// The secret scalar and nonce prefix RFC 8032 derives from a 32-byte seed.
+pub(crate) struct ExpandedSecret {
+    pub scalar: Scalar,
+    pub prefix: [u8; 32],
+}
+
+impl ExpandedSecret {
+    pub fn from_seed(seed: &[u8]) -> Self {
+        let mut h = sha512::hash(seed).0;
+        h[0] &= 248;
+        h[31] &= 127;
+        h[31] |= 64;
+        let mut clamped = [0u8; 32];
+        clamped.copy_from_slice(&h[..32]);
+        let mut secret = ExpandedSecret {
+            scalar: reduce32(&clamped),
+            prefix: [0u8; 32],
+        };
+        secret.prefix.copy_from_slice(&h[32..]);
+        memzero(&mut clamped);
+        memzero(&mut h);
+        secret
+    }
+}
+
+impl Drop for ExpandedSecret {
+    fn drop(&mut self) {
+        memzero(&mut self.scalar);
+        memzero(&mut self.prefix);
+    }
+}
@@ -0,0 +1,365 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deterministic child keys.
+//!
+//! Ed25519 only supports hardened derivation under SLIP-0010, so
+//! [`KeyPair::derive_for`] and [`KeyPair::derive_child`] give keys that can
+//! never be derived from a public key alone. [`ExtendedPubKey`] adds a
+//! separate, non-hardened scheme for watch-only address generation.
+
+use super::{pubkey_to_address, Address, Error, KeyPair, Message, PubKey, Signature};
+use crate::curve::{base_mul, hash_to_scalar, point_add, scalar_add, ExpandedSecret};
+use cita_crypto_trait::CreateKey;
+use cita_types::H256;
+use serde::{Deserialize, Serialize};
+use sodiumoxide::crypto::auth::hmacsha512;
+use sodiumoxide::crypto::hash::sha512;
+use sodiumoxide::utils::memzero;
+
+const HARDENED: u32 = 0x8000_0000;
+const LABEL_SALT: &[u8] = b"cita-ed25519 derive_child";
+const MIN_IKM_LEN: usize = 16;
+const SOFT_CHAIN_KEY: &[u8] = b"cita-ed25519 soft chain";
+const SOFT_TWEAK_TAG: &[u8] = b"cita-ed25519 soft child";
+
+/// First component of every path built by [`KeyPair::derive_for`].
+pub const DERIVATION_PURPOSE: u32 = 44;
+
+/// What a derived key is used for; the last component of its path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyUsage {
+    Consensus,
+    Network,
+    Transaction,
+    Other(u32),
+}
+
+impl KeyUsage {
+    pub fn index(self) -> u32 {
+        match self {
+            KeyUsage::Consensus => 0,
+            KeyUsage::Network => 1,
+            KeyUsage::Transaction => 2,
+            KeyUsage::Other(index) => index,
+        }
+    }
+}
+
+struct ExtendedKey {
+    key: [u8; 32],
+    chain_code: [u8; 32],
+}
+
+impl ExtendedKey {
+    fn from_hmac(key: &[u8], parts: &[&[u8]]) -> Self {
+        let mut state = hmacsha512::State::init(key);
+        for part in parts {
+            state.update(part);
+        }
+        let mut tag = state.finalize();
+        let mut extended = ExtendedKey {
+            key: [0u8; 32],
+            chain_code: [0u8; 32],
+        };
+        extended.key.copy_from_slice(&tag.0[..32]);
+        extended.chain_code.copy_from_slice(&tag.0[32..]);
+        memzero(&mut tag.0);
+        extended
+    }
+
+    fn child(&self, index: u32) -> Self {
+        let index = (index | HARDENED).to_be_bytes();
+        ExtendedKey::from_hmac(&self.chain_code, &[&[0u8], &self.key, &index])
+    }
+}
+
+impl Drop for ExtendedKey {
+    fn drop(&mut self) {
+        memzero(&mut self.key);
+        memzero(&mut self.chain_code);
+    }
+}
+
+/// SLIP-0010 ed25519 derivation of `path` (all hardened) from `seed`,
+/// returning the child's 32-byte key.
+pub(crate) fn slip10_derive(seed: &[u8], path: &[u32]) -> Result<[u8; 32], Error> {
+    if path.iter().any(|index| index & HARDENED != 0) {
+        return Err(Error::InvalidPath);
+    }
+    let mut extended = ExtendedKey::from_hmac(b"ed25519 seed", &[seed]);
+    for index in path {
+        extended = extended.child(*index);
+    }
+    Ok(extended.key)
+}
+
+impl KeyPair {
+    pub(crate) fn seed(&self) -> [u8; 32] {
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&self.privkey().0[..32]);
+        seed
+    }
+
+    /// Derive the key at `m/44'/<chain_id>'/<account_index>'/<usage>'`, using
+    /// this key pair's seed as the SLIP-0010 master seed.
+    ///
+    /// Every index must be below 2^31.
+    pub fn derive_for(
+        &self,
+        chain_id: u32,
+        account_index: u32,
+        key_usage: KeyUsage,
+    ) -> Result<KeyPair, Error> {
+        let mut seed = self.seed();
+        let path = [
+            DERIVATION_PURPOSE,
+            chain_id,
+            account_index,
+            key_usage.index(),
+        ];
+        let child = slip10_derive(&seed, &path);
+        memzero(&mut seed);
+        let mut child = child?;
+        let keypair = KeyPair::from_seed(&child);
+        memzero(&mut child);
+        Ok(keypair)
+    }
+
+    /// Derive the child key for `label`, e.g. `"consensus"` or `"network"`,
+    /// with HKDF-SHA512 over this key pair's seed.
+    ///
+    /// Different labels give unrelated keys, and none of them can be derived
+    /// without the master seed.
+    pub fn derive_child(&self, label: &str) -> KeyPair {
+        let mut seed = self.seed();
+        let keypair = hkdf_keypair(LABEL_SALT, &seed, label.as_bytes());
+        memzero(&mut seed);
+        keypair
+    }
+
+    /// Derive a key pair from external key material, such as a corporate
+    /// secret or a KMS data key, with HKDF-SHA512 (RFC 5869).
+    ///
+    /// The same `ikm`, `salt` and `info` always give the same key pair; use
+    /// `info` to separate keys derived from one secret. `ikm` must hold at
+    /// least 16 bytes.
+    pub fn from_ikm(ikm: &[u8], salt: &[u8], info: &[u8]) -> Result<KeyPair, Error> {
+        if ikm.len() < MIN_IKM_LEN {
+            return Err(Error::InvalidPrivKey);
+        }
+        Ok(hkdf_keypair(salt, ikm, info))
+    }
+}
+
+// The key pair for the first 32 bytes of HKDF-SHA512 output.
+fn hkdf_keypair(salt: &[u8], ikm: &[u8], info: &[u8]) -> KeyPair {
+    let mut prk = hmacsha512::State::init(salt);
+    prk.update(ikm);
+    let mut prk = prk.finalize();
+
+    let mut okm = hmacsha512::State::init(&prk.0);
+    okm.update(info);
+    okm.update(&[1u8]);
+    let mut okm = okm.finalize();
+    memzero(&mut prk.0);
+
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(&okm.0[..32]);
+    memzero(&mut okm.0);
+    let keypair = KeyPair::from_seed(&seed);
+    memzero(&mut seed);
+    keypair
+}
+
+/// Public half of the non-hardened scheme: the account public key and its
+/// chain code.
+///
+/// Holders can generate every child public key and address but sign nothing.
+/// Like a BIP-32 xpub it must still be kept private from anyone who may learn
+/// a child private key, since the two together reveal the account secret.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtendedPubKey {
+    pub pubkey: PubKey,
+    pub chain_code: H256,
+}
+
+impl ExtendedPubKey {
+    fn tweak(&self, index: u32) -> [u8; 32] {
+        hash_to_scalar(&[
+            SOFT_TWEAK_TAG,
+            self.chain_code.as_ref(),
+            self.pubkey.as_ref(),
+            &index.to_be_bytes(),
+        ])
+    }
+
+    /// Public key of child `index`: `A + t * B` with `t = H(c, A, index)`.
+    pub fn derive_pubkey(&self, index: u32) -> Result<PubKey, Error> {
+        let tweak_point = base_mul(&self.tweak(index))?;
+        let child = point_add(&self.pubkey.0, &tweak_point)?;
+        Ok(PubKey::from(child))
+    }
+
+    pub fn derive_address(&self, index: u32) -> Result<Address, Error> {
+        self.derive_pubkey(index)
+            .map(|pubkey| pubkey_to_address(&pubkey))
+    }
+}
+
+/// Private key of a non-hardened child, matching
+/// [`ExtendedPubKey::derive_pubkey`].
+///
+/// The child has no 32-byte seed, so it cannot become a [`KeyPair`]; it signs
+/// with its expanded scalar directly and produces ordinary signatures.
+pub struct SoftChildKey {
+    secret: ExpandedSecret,
+    pubkey: PubKey,
+}
+
+impl SoftChildKey {
+    pub fn pubkey(&self) -> &PubKey {
+        &self.pubkey
+    }
+
+    pub fn address(&self) -> Address {
+        pubkey_to_address(&self.pubkey)
+    }
+
+    /// Deterministic signature over `message`.
+    pub fn sign(&self, message: &Message) -> Result<Signature, Error> {
+        Signature::sign_expanded(&self.secret, &[&self.secret.prefix], message)
+    }
+}
+
+impl KeyPair {
+    /// The watch-only export of this key pair's non-hardened account.
+    pub fn extended_pubkey(&self) -> ExtendedPubKey {
+        let mut seed = self.seed();
+        let mut chain = hmacsha512::State::init(SOFT_CHAIN_KEY);
+        chain.update(&seed);
+        let mut tag = chain.finalize();
+        memzero(&mut seed);
+        let chain_code = H256::from_slice(&tag.0[..32]);
+        memzero(&mut tag.0);
+        ExtendedPubKey {
+            pubkey: *self.pubkey(),
+            chain_code,
+        }
+    }
+
+    /// The private key for [`ExtendedPubKey::derive_pubkey`] at `index`.
+    pub fn soft_child(&self, index: u32) -> Result<SoftChildKey, Error> {
+        let master = ExpandedSecret::from_seed(&self.privkey().0[..32]);
+        let mut tweak = self.extended_pubkey().tweak(index);
+        let mut prefix = sha512::State::new();
+        prefix.update(&master.prefix);
+        prefix.update(&tweak);
+        let mut prefix = prefix.finalize().0;
+        let mut secret = ExpandedSecret {
+            scalar: scalar_add(&master.scalar, &tweak),
+            prefix: [0u8; 32],
+        };
+        secret.prefix.copy_from_slice(&prefix[..32]);
+        memzero(&mut prefix);
+        memzero(&mut tweak);
+        let pubkey = PubKey::from(base_mul(&secret.scalar)?);
+        Ok(SoftChildKey { secret, pubkey })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cita_crypto_trait::Sign;
+    use rustc_serialize::hex::{FromHex, ToHex};
+
+    #[test]
+    fn test_slip10_vector() {
+        // SLIP-0010 test vector 1 for ed25519.
+        let seed = "000102030405060708090a0b0c0d0e0f".from_hex().unwrap();
+        assert_eq!(
+            slip10_derive(&seed, &[]).unwrap().to_hex(),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+        assert_eq!(
+            slip10_derive(&seed, &[0]).unwrap().to_hex(),
+            "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3"
+        );
+        assert!(slip10_derive(&seed, &[HARDENED]).is_err());
+    }
+
+    #[test]
+    fn test_derive_for() {
+        let master = KeyPair::gen_keypair();
+        let a = master.derive_for(1, 0, KeyUsage::Consensus).unwrap();
+        let b = master.derive_for(1, 0, KeyUsage::Consensus).unwrap();
+        let c = master.derive_for(2, 0, KeyUsage::Consensus).unwrap();
+        let d = master.derive_for(1, 0, KeyUsage::Network).unwrap();
+        assert_eq!(a.pubkey(), b.pubkey());
+        assert_ne!(a.pubkey(), c.pubkey());
+        assert_ne!(a.pubkey(), d.pubkey());
+        assert!(master.derive_for(HARDENED, 0, KeyUsage::Consensus).is_err());
+    }
+
+    #[test]
+    fn test_derive_child() {
+        let master = KeyPair::gen_keypair();
+        let consensus = master.derive_child("consensus");
+        assert_eq!(
+            consensus.pubkey(),
+            master.derive_child("consensus").pubkey()
+        );
+        assert_ne!(consensus.pubkey(), master.derive_child("network").pubkey());
+        assert_ne!(consensus.pubkey(), master.pubkey());
+    }
+
+    #[test]
+    fn test_from_ikm() {
+        // RFC 5869 test case 1 inputs, with SHA-512.
+        let ikm = [0x0bu8; 22];
+        let salt: Vec<u8> = (0x00..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+        let seed = "832390086cda71fb47625bb5ceb168e4c8e26a1a16ed34d9fc7fe92c14815793"
+            .from_hex()
+            .unwrap();
+        let mut expected = [0u8; 32];
+        expected.copy_from_slice(&seed);
+
+        let keypair = KeyPair::from_ikm(&ikm, &salt, &info).unwrap();
+        assert_eq!(keypair.pubkey(), KeyPair::from_seed(&expected).pubkey());
+        assert_ne!(
+            keypair.pubkey(),
+            KeyPair::from_ikm(&ikm, &salt, b"other").unwrap().pubkey()
+        );
+        assert!(KeyPair::from_ikm(&ikm[..15], &salt, &info).is_err());
+    }
+
+    #[test]
+    fn test_watch_only_derivation() {
+        let master = KeyPair::gen_keypair();
+        let xpub = master.extended_pubkey();
+        let encoded = bincode::serialize(&xpub).unwrap();
+        let xpub: ExtendedPubKey = bincode::deserialize(&encoded).unwrap();
+
+        let child = master.soft_child(7).unwrap();
+        assert_eq!(&xpub.derive_pubkey(7).unwrap(), child.pubkey());
+        assert_eq!(xpub.derive_address(7).unwrap(), child.address());
+        assert_ne!(xpub.derive_pubkey(8).unwrap(), *child.pubkey());
+
+        let message = Message::from([3u8; 32]);
+        let sig = child.sign(&message).unwrap();
+        assert!(sig.verify_public(child.pubkey(), &message).unwrap());
+    }
+}
@@ -12,7 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::limits::LimitExceeded;
+use rlp::DecoderError;
 use std::fmt;
+use std::io;
 
 #[derive(Debug)]
 pub enum Error {
@@ -20,16 +23,117 @@ pub enum Error {
     InvalidPubKey,
     InvalidMessage,
     InvalidSignature,
+    AgentUnavailable,
+    AgentFailure,
+    DecryptionFailed,
+    InvalidShares,
+    InvalidPrefix,
+    Cancelled,
+    InvalidEpoch,
+    SigningLimitReached,
+    InvalidPath,
+    UnsupportedSuite,
+    InvalidThreshold,
+    InvalidEncoding,
+    QueueFull,
+    EntropyFailure,
+    IntegrityCheckFailed,
+    InvalidEvidence,
+    Expired,
+    Replayed,
+    WatermarkViolation,
+    InvalidAddressScheme,
+    NonceReused,
+    InvalidLength,
+    InvalidHasher,
+    InvalidKdfParams,
+    UnsupportedVersion,
+    UnsupportedCipher,
+    KeyDerivationFailed,
+    InitFailed,
+    LimitExceeded(LimitExceeded),
+    Decoder(DecoderError),
+    Io(io::Error),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let message = match *self {
+            Error::Decoder(ref e) => return write!(f, "Crypto error: RLP Decoding Failed: {}", e),
+            Error::Io(ref e) => return write!(f, "Crypto error: IO Failed: {}", e),
+            Error::LimitExceeded(ref e) => return write!(f, "Crypto error: {}", e),
             Error::InvalidPrivKey => "Invalid Private Key",
             Error::InvalidPubKey => "Invalid Public Key",
             Error::InvalidMessage => "Invalid Message",
             Error::InvalidSignature => "Invalid Signature",
+            Error::AgentUnavailable => "SSH Agent Unavailable",
+            Error::AgentFailure => "SSH Agent Failure",
+            Error::DecryptionFailed => "Decryption Failed",
+            Error::InvalidShares => "Invalid Shares",
+            Error::InvalidPrefix => "Invalid Prefix",
+            Error::Cancelled => "Cancelled",
+            Error::InvalidEpoch => "Invalid Epoch",
+            Error::SigningLimitReached => "Signing Limit Reached",
+            Error::InvalidPath => "Invalid Derivation Path",
+            Error::UnsupportedSuite => "Unsupported Signature Suite",
+            Error::InvalidThreshold => "Invalid Threshold",
+            Error::InvalidEncoding => "Invalid Encoding",
+            Error::QueueFull => "Queue Full",
+            Error::EntropyFailure => "Entropy Health Test Failed",
+            Error::IntegrityCheckFailed => "Integrity Check Failed",
+            Error::InvalidEvidence => "Invalid Evidence",
+            Error::Expired => "Expired",
+            Error::Replayed => "Replayed",
+            Error::WatermarkViolation => "Vote Would Double Sign",
+            Error::InvalidAddressScheme => "Invalid Address Scheme",
+            Error::NonceReused => "Signing Nonce Already Used",
+            Error::InvalidLength => "Invalid Length",
+            Error::InvalidHasher => "Invalid Hasher",
+            Error::InvalidKdfParams => "Invalid Key Derivation Parameters",
+            Error::UnsupportedVersion => "Unsupported Version",
+            Error::UnsupportedCipher => "Unsupported Cipher",
+            Error::KeyDerivationFailed => "Key Derivation Failed",
+            Error::InitFailed => "Library Initialization Failed",
         };
         f.write_fmt(format_args!("Crypto error: {}", message))
     }
 }
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Decoder(ref e) => Some(e),
+            Error::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecoderError> for Error {
+    fn from(e: DecoderError) -> Self {
+        Error::Decoder(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Signature;
+
+    fn decode_signature(bytes: &[u8]) -> Result<Signature, Error> {
+        Ok(rlp::decode(bytes)?)
+    }
+
+    #[test]
+    fn test_error_conversions() {
+        assert!(matches!(decode_signature(&[0xc0]), Err(Error::Decoder(_))));
+        let e: Error = io::Error::new(io::ErrorKind::UnexpectedEof, "eof").into();
+        assert!(e.to_string().contains("eof"));
+    }
+}
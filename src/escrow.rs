@@ -0,0 +1,150 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{open_sealed, seal, Address, Error, KeyPair, PrivKey, PubKey, HASH_BYTES_LEN};
+use crate::clock::now;
+use crate::hasher::hash_data;
+use cita_crypto_trait::CreateKey;
+use cita_types::H256;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+use sodiumoxide::utils::{memcmp, memzero};
+
+pub const ESCROW_VERSION: u8 = 1;
+
+/// A private key encrypted to a designated recovery key.
+///
+/// Everything except `ciphertext` is readable without the recovery key so the
+/// blob can be audited. Those fields are hashed into the sealed plaintext, so
+/// they cannot be altered without recovery failing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EscrowBlob {
+    pub version: u8,
+    /// The key the blob is sealed to.
+    pub recovery_pubkey: PubKey,
+    /// Address of the escrowed key.
+    pub address: Address,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+    #[serde(with = "crate::serde_hex")]
+    pub ciphertext: Vec<u8>,
+}
+
+impl EscrowBlob {
+    fn metadata_hash(&self) -> H256 {
+        let mut s = RlpStream::new_list(4);
+        s.append(&self.version);
+        s.append(&self.recovery_pubkey);
+        s.append(&self.address);
+        s.append(&self.created_at);
+        hash_data(&s.out())
+    }
+}
+
+impl Encodable for EscrowBlob {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(5);
+        s.append(&self.version);
+        s.append(&self.recovery_pubkey);
+        s.append(&self.address);
+        s.append(&self.created_at);
+        s.append(&self.ciphertext);
+    }
+}
+
+impl Decodable for EscrowBlob {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 5 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(EscrowBlob {
+            version: rlp.val_at(0)?,
+            recovery_pubkey: rlp.val_at(1)?,
+            address: rlp.val_at(2)?,
+            created_at: rlp.val_at(3)?,
+            ciphertext: rlp.val_at(4)?,
+        })
+    }
+}
+
+/// Encrypt `privkey` so that only the holder of `recovery_pubkey` can restore it.
+pub fn escrow(privkey: &PrivKey, recovery_pubkey: &PubKey) -> Result<EscrowBlob, Error> {
+    let keypair = KeyPair::from_privkey(*privkey)?;
+    let created_at = now();
+    let mut blob = EscrowBlob {
+        version: ESCROW_VERSION,
+        recovery_pubkey: *recovery_pubkey,
+        address: keypair.address(),
+        created_at,
+        ciphertext: Vec::new(),
+    };
+
+    let mut plaintext = blob.metadata_hash().0.to_vec();
+    plaintext.extend_from_slice(privkey.as_ref());
+    let sealed = seal(recovery_pubkey, &plaintext);
+    memzero(&mut plaintext);
+    blob.ciphertext = sealed?;
+    Ok(blob)
+}
+
+/// Restore the key held in `blob` using the recovery key pair.
+pub fn recover_from_escrow(
+    recovery_keypair: &KeyPair,
+    blob: &EscrowBlob,
+) -> Result<KeyPair, Error> {
+    if blob.version != ESCROW_VERSION || &blob.recovery_pubkey != recovery_keypair.pubkey() {
+        return Err(Error::InvalidPubKey);
+    }
+    let mut plaintext = open_sealed(recovery_keypair, &blob.ciphertext)?;
+    let result = if plaintext.len() != HASH_BYTES_LEN + 64
+        || !memcmp(&plaintext[..HASH_BYTES_LEN], &blob.metadata_hash().0)
+    {
+        Err(Error::DecryptionFailed)
+    } else {
+        KeyPair::from_privkey(PrivKey::from_slice(&plaintext[HASH_BYTES_LEN..]))
+    };
+    memzero(&mut plaintext);
+
+    let keypair = result?;
+    if keypair.address() != blob.address {
+        return Err(Error::DecryptionFailed);
+    }
+    Ok(keypair)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escrow_recover() {
+        let keypair = KeyPair::gen_keypair();
+        let recovery = KeyPair::gen_keypair();
+        let blob = escrow(keypair.privkey(), recovery.pubkey()).unwrap();
+        assert_eq!(blob.address, keypair.address());
+
+        let recovered = recover_from_escrow(&recovery, &blob).unwrap();
+        assert_eq!(recovered.privkey(), keypair.privkey());
+        assert!(recover_from_escrow(&keypair, &blob).is_err());
+    }
+
+    #[test]
+    fn test_escrow_metadata_is_authenticated() {
+        let keypair = KeyPair::gen_keypair();
+        let recovery = KeyPair::gen_keypair();
+        let mut blob = escrow(keypair.privkey(), recovery.pubkey()).unwrap();
+        blob.created_at += 1;
+        assert!(recover_from_escrow(&recovery, &blob).is_err());
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Proof that a validator signed two different proposals at the same height
+//! and round, in a form a slashing module can check on its own.
+
+use super::{pubkey_to_address, Address, Error, Message, PubKey, Signature};
+use crate::hasher::hash_data;
+use cita_crypto_trait::Sign;
+use cita_types::{H256, H512};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+
+const VOTE_DOMAIN: &[u8] = b"cita-ed25519 vote";
+
+/// The message a validator signs to vote for `proposal` at `height` and
+/// `round`. Evidence can only be checked for votes signed this way.
+pub fn vote_message(height: u64, round: u64, proposal: &H256) -> Message {
+    let mut s = RlpStream::new_list(4);
+    s.append(&VOTE_DOMAIN);
+    s.append(&height);
+    s.append(&round);
+    s.append(proposal);
+    hash_data(&s.out())
+}
+
+/// One side of a double sign: the proposal voted for and the 64-byte
+/// signature, without the public key that both sides share.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConflictingVote {
+    pub proposal: H256,
+    pub signature: H512,
+}
+
+impl ConflictingVote {
+    fn signature_for(&self, pubkey: &PubKey) -> Signature {
+        let mut bytes = [0u8; 96];
+        bytes[..64].copy_from_slice(&self.signature.0);
+        bytes[64..].copy_from_slice(&pubkey.0);
+        Signature::from(bytes)
+    }
+}
+
+/// Two valid votes by `pubkey` for different proposals in the same round.
+///
+/// The votes are stored ordered by proposal, so the same offence always
+/// yields the same evidence bytes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DoubleSignEvidence {
+    pub pubkey: PubKey,
+    pub height: u64,
+    pub round: u64,
+    pub first: ConflictingVote,
+    pub second: ConflictingVote,
+}
+
+impl DoubleSignEvidence {
+    /// Build evidence from two observed votes, checking both first.
+    pub fn new(
+        height: u64,
+        round: u64,
+        a: (H256, &Signature),
+        b: (H256, &Signature),
+    ) -> Result<Self, Error> {
+        if a.0 == b.0 || a.1.pk() != b.1.pk() {
+            return Err(Error::InvalidEvidence);
+        }
+        let (a, b) = if a.0 < b.0 { (a, b) } else { (b, a) };
+        let vote = |(proposal, signature): (H256, &Signature)| ConflictingVote {
+            proposal,
+            signature: H512::from_slice(signature.sig()),
+        };
+        let evidence = DoubleSignEvidence {
+            pubkey: PubKey::from_slice(a.1.pk()),
+            height,
+            round,
+            first: vote(a),
+            second: vote(b),
+        };
+        verify_evidence(&evidence)?;
+        Ok(evidence)
+    }
+
+    /// Address of the validator that double signed.
+    pub fn offender(&self) -> Address {
+        pubkey_to_address(&self.pubkey)
+    }
+}
+
+/// Check that `evidence` holds two valid, conflicting votes by its key.
+pub fn verify_evidence(evidence: &DoubleSignEvidence) -> Result<(), Error> {
+    if evidence.first.proposal >= evidence.second.proposal {
+        return Err(Error::InvalidEvidence);
+    }
+    for vote in &[&evidence.first, &evidence.second] {
+        let message = vote_message(evidence.height, evidence.round, &vote.proposal);
+        vote.signature_for(&evidence.pubkey)
+            .verify_public(&evidence.pubkey, &message)?;
+    }
+    Ok(())
+}
+
+impl Encodable for ConflictingVote {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2);
+        s.append(&self.proposal);
+        s.append(&self.signature);
+    }
+}
+
+impl Decodable for ConflictingVote {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 2 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(ConflictingVote {
+            proposal: rlp.val_at(0)?,
+            signature: rlp.val_at(1)?,
+        })
+    }
+}
+
+impl Encodable for DoubleSignEvidence {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(5);
+        s.append(&self.pubkey);
+        s.append(&self.height);
+        s.append(&self.round);
+        s.append(&self.first);
+        s.append(&self.second);
+    }
+}
+
+impl Decodable for DoubleSignEvidence {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 5 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(DoubleSignEvidence {
+            pubkey: rlp.val_at(0)?,
+            height: rlp.val_at(1)?,
+            round: rlp.val_at(2)?,
+            first: rlp.val_at(3)?,
+            second: rlp.val_at(4)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+    use cita_crypto_trait::CreateKey;
+
+    fn vote(keypair: &KeyPair, height: u64, round: u64, proposal: &H256) -> Signature {
+        let message = vote_message(height, round, proposal);
+        Signature::sign(keypair.privkey(), &message).unwrap()
+    }
+
+    #[test]
+    fn test_double_sign_evidence() {
+        let keypair = KeyPair::gen_keypair();
+        let (p1, p2) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+        let s1 = vote(&keypair, 10, 0, &p1);
+        let s2 = vote(&keypair, 10, 0, &p2);
+
+        let evidence = DoubleSignEvidence::new(10, 0, (p2, &s2), (p1, &s1)).unwrap();
+        assert_eq!(evidence.first.proposal, p1);
+        assert_eq!(evidence.offender(), keypair.address());
+        let decoded: DoubleSignEvidence = rlp::decode(&rlp::encode(&evidence)).unwrap();
+        assert_eq!(decoded, evidence);
+        verify_evidence(&decoded).unwrap();
+
+        let mut wrong_round = evidence.clone();
+        wrong_round.round = 1;
+        assert!(verify_evidence(&wrong_round).is_err());
+    }
+
+    #[test]
+    fn test_rejects_non_conflicting_votes() {
+        let keypair = KeyPair::gen_keypair();
+        let other = KeyPair::gen_keypair();
+        let (p1, p2) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+        let s1 = vote(&keypair, 10, 0, &p1);
+        assert!(DoubleSignEvidence::new(10, 0, (p1, &s1), (p1, &s1)).is_err());
+        let s2 = vote(&other, 10, 0, &p2);
+        assert!(DoubleSignEvidence::new(10, 0, (p1, &s1), (p2, &s2)).is_err());
+        // A vote from another round is not a conflict.
+        let s2 = vote(&keypair, 10, 1, &p2);
+        assert!(DoubleSignEvidence::new(10, 0, (p1, &s1), (p2, &s2)).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `arbitrary` support for fuzzing code that consumes this crate's types.
+//!
+//! Generated keys and signatures are a mix of well-formed values and the
+//! edge cases verifiers trip on: all-zero bytes, the identity and other
+//! small-order points, and non-canonical scalars. `PubKey` and `Address` come
+//! from `cita-types`, so derive users pick them up through
+//! `#[arbitrary(with = cita_ed25519::arbitrary_pubkey)]` and friends.
+
+use super::{
+    Address, ConflictingVote, Countersignature, DoubleSignEvidence, KeyPair, Message, PubKey,
+    SessionCertificate, Signature, SignedRequest,
+};
+use crate::curve::GROUP_ORDER;
+use arbitrary::{Arbitrary, Result, Unstructured};
+use cita_crypto_trait::{CreateKey, Sign};
+use cita_types::{H256, H512};
+
+// Encodings of the identity and of a point of order 2 (y = -1).
+const IDENTITY: [u8; 32] = {
+    let mut p = [0u8; 32];
+    p[0] = 1;
+    p
+};
+const ORDER_TWO: [u8; 32] = {
+    let mut p = [0xffu8; 32];
+    p[0] = 0xec;
+    p[31] = 0x7f;
+    p
+};
+
+fn arbitrary_keypair(u: &mut Unstructured) -> Result<KeyPair> {
+    Ok(KeyPair::from_seed(&u.arbitrary()?))
+}
+
+pub fn arbitrary_pubkey(u: &mut Unstructured) -> Result<PubKey> {
+    Ok(match u.int_in_range(0u8..=4)? {
+        0 => *arbitrary_keypair(u)?.pubkey(),
+        1 => PubKey::zero(),
+        2 => PubKey::from(IDENTITY),
+        3 => PubKey::from(ORDER_TWO),
+        _ => PubKey::from(<[u8; 32]>::arbitrary(u)?),
+    })
+}
+
+pub fn arbitrary_address(u: &mut Unstructured) -> Result<Address> {
+    Ok(Address::from(<[u8; 20]>::arbitrary(u)?))
+}
+
+pub fn arbitrary_message(u: &mut Unstructured) -> Result<Message> {
+    Ok(Message::from(<[u8; 32]>::arbitrary(u)?))
+}
+
+impl<'a> Arbitrary<'a> for Signature {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut bytes = [0u8; 96];
+        match u.int_in_range(0u8..=4)? {
+            // A valid signature, by a key and over a message drawn from `u`.
+            0 => {
+                let keypair = arbitrary_keypair(u)?;
+                let message = arbitrary_message(u)?;
+                return Ok(Signature::sign(keypair.privkey(), &message).unwrap());
+            }
+            1 => {}
+            // s = L, which strict verifiers must reject.
+            2 => {
+                u.fill_buffer(&mut bytes[..32])?;
+                bytes[32..64].copy_from_slice(&GROUP_ORDER);
+                bytes[64..].copy_from_slice(&arbitrary_pubkey(u)?.0);
+            }
+            // A small-order R.
+            3 => {
+                bytes[..32].copy_from_slice(&ORDER_TWO);
+                u.fill_buffer(&mut bytes[32..64])?;
+                bytes[64..].copy_from_slice(&arbitrary_pubkey(u)?.0);
+            }
+            _ => u.fill_buffer(&mut bytes)?,
+        }
+        Ok(Signature(bytes))
+    }
+
+    fn size_hint(_: usize) -> (usize, Option<usize>) {
+        (1, Some(1 + 96))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Countersignature {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Countersignature {
+            timestamp: u.arbitrary()?,
+            signature: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for ConflictingVote {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(ConflictingVote {
+            proposal: H256::from(<[u8; 32]>::arbitrary(u)?),
+            signature: H512::from(<[u8; 64]>::arbitrary(u)?),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for DoubleSignEvidence {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(DoubleSignEvidence {
+            pubkey: arbitrary_pubkey(u)?,
+            height: u.arbitrary()?,
+            round: u.arbitrary()?,
+            first: u.arbitrary()?,
+            second: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for SessionCertificate {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(SessionCertificate {
+            identity: arbitrary_pubkey(u)?,
+            session_pubkey: arbitrary_pubkey(u)?,
+            not_before: u.arbitrary()?,
+            not_after: u.arbitrary()?,
+            signature: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for SignedRequest {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(SignedRequest {
+            payload: u.arbitrary()?,
+            nonce: u.arbitrary()?,
+            expires_at: u.arbitrary()?,
+            signature: u.arbitrary()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Arbitrary, Debug)]
+    struct Transfer {
+        #[arbitrary(with = arbitrary_pubkey)]
+        from: PubKey,
+        signature: Signature,
+    }
+
+    #[test]
+    fn test_arbitrary_signatures() {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let data: Vec<u8> = (0..16384)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        let mut u = Unstructured::new(&data);
+        let (mut zero, mut unreduced, mut identity, mut transfers) = (0, 0, 0, 0);
+        while let Ok(transfer) = u.arbitrary::<Transfer>() {
+            let sig = &transfer.signature;
+            zero += sig.0.iter().all(|b| *b == 0) as usize;
+            unreduced += (sig.0[32..64] == GROUP_ORDER) as usize;
+            identity += (transfer.from.0 == IDENTITY) as usize;
+            transfers += 1;
+            if u.is_empty() {
+                break;
+            }
+        }
+        assert!(transfers > 10 && zero > 0 && unreduced > 0 && identity > 0);
+
+        let evidence: DoubleSignEvidence = Unstructured::new(&data).arbitrary().unwrap();
+        assert!(crate::verify_evidence(&evidence).is_err());
+    }
+}
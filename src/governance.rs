@@ -0,0 +1,234 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Multi-signer approval of JSON documents such as chain upgrade proposals.
+//!
+//! Signers sign a canonical form of the document (object keys sorted, no
+//! insignificant whitespace) together with the signer list and threshold, so
+//! reformatting the JSON does not invalidate signatures but changing any of
+//! them does.
+
+use super::{pubkey_to_address, Address, Error, KeyPair, Message, PubKey, Signature};
+use crate::hasher::hash_data;
+use cita_crypto_trait::{CreateKey, Sign};
+use rlp::RlpStream;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const GOVERNANCE_DOMAIN: &[u8] = b"cita-ed25519 governance";
+
+/// The canonical bytes of `document`: object keys sorted by their UTF-8
+/// bytes at every level, no whitespace, strings escaped as serde_json does.
+///
+/// Keys are sorted here rather than relying on serde_json's map type, which
+/// keeps insertion order when its `preserve_order` feature is enabled
+/// anywhere in the build. Numbers other than integers have no single
+/// textual form and fail with `InvalidEncoding`.
+pub fn canonical_json(document: &Value) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::new();
+    write_canonical(document, &mut out)?;
+    Ok(out)
+}
+
+fn write_canonical(value: &Value, out: &mut Vec<u8>) -> Result<(), Error> {
+    match value {
+        Value::Number(n) if !(n.is_i64() || n.is_u64()) => return Err(Error::InvalidEncoding),
+        Value::Array(items) => {
+            out.push(b'[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_canonical(item, out)?;
+            }
+            out.push(b']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_unstable_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+            out.push(b'{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                serde_json::to_writer(&mut *out, key).map_err(|_| Error::InvalidEncoding)?;
+                out.push(b':');
+                write_canonical(item, out)?;
+            }
+            out.push(b'}');
+        }
+        _ => serde_json::to_writer(&mut *out, value).map_err(|_| Error::InvalidEncoding)?,
+    }
+    Ok(())
+}
+
+/// A document with the signatures collected for it so far.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GovernanceProposal {
+    pub document: Value,
+    pub signers: Vec<Address>,
+    pub threshold: usize,
+    pub signatures: Vec<Signature>,
+}
+
+impl GovernanceProposal {
+    /// Start collecting signatures from `signers`, `threshold` of which must
+    /// sign for the proposal to pass.
+    pub fn new(document: Value, signers: &[Address], threshold: usize) -> Result<Self, Error> {
+        let mut signers = signers.to_vec();
+        signers.sort_unstable();
+        signers.dedup();
+        if threshold == 0 || threshold > signers.len() {
+            return Err(Error::InvalidThreshold);
+        }
+        Ok(GovernanceProposal {
+            document,
+            signers,
+            threshold,
+            signatures: Vec::new(),
+        })
+    }
+
+    /// What every signer signs. Fails if the document holds a non-integer
+    /// number.
+    pub fn signing_hash(&self) -> Result<Message, Error> {
+        let mut s = RlpStream::new_list(4);
+        s.append(&GOVERNANCE_DOMAIN);
+        s.append(&canonical_json(&self.document)?);
+        s.append_list(&self.signers);
+        s.append(&(self.threshold as u64));
+        Ok(hash_data(&s.out()))
+    }
+
+    pub fn sign(&mut self, keypair: &KeyPair) -> Result<(), Error> {
+        let signature = Signature::sign(keypair.privkey(), &self.signing_hash()?)?;
+        self.add_signature(signature)
+    }
+
+    /// Add a signature from one of the signers, replacing any earlier one
+    /// from the same signer.
+    pub fn add_signature(&mut self, signature: Signature) -> Result<(), Error> {
+        let address = pubkey_to_address(&PubKey::from_slice(signature.pk()));
+        if self.signers.binary_search(&address).is_err() {
+            return Err(Error::InvalidPubKey);
+        }
+        if !signature.verify_address(&address, &self.signing_hash()?)? {
+            return Err(Error::InvalidSignature);
+        }
+        self.signatures.retain(|s| s.pk() != signature.pk());
+        self.signatures.push(signature);
+        self.signatures.sort_by(|a, b| a.pk().cmp(b.pk()));
+        Ok(())
+    }
+
+    /// Take over the signatures collected in `other` for the same proposal.
+    pub fn merge(&mut self, other: &GovernanceProposal) -> Result<(), Error> {
+        if other.signing_hash()? != self.signing_hash()? {
+            return Err(Error::InvalidMessage);
+        }
+        for signature in &other.signatures {
+            self.add_signature(signature.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Signers with a signature on the proposal, sorted.
+    pub fn signed_by(&self) -> Vec<Address> {
+        let mut signed: Vec<Address> = self
+            .signatures
+            .iter()
+            .map(|s| pubkey_to_address(&PubKey::from_slice(s.pk())))
+            .collect();
+        signed.sort_unstable();
+        signed
+    }
+
+    /// Signers that have not signed yet.
+    pub fn missing(&self) -> Vec<Address> {
+        let signed = self.signed_by();
+        self.signers
+            .iter()
+            .filter(|a| signed.binary_search(a).is_err())
+            .copied()
+            .collect()
+    }
+
+    /// Re-check every signature and whether enough signers have signed.
+    ///
+    /// A proposal decoded from untrusted JSON must be checked this way;
+    /// `signatures` is only validated on the way in through this type.
+    pub fn is_approved(&self) -> bool {
+        let message = match self.signing_hash() {
+            Ok(message) => message,
+            Err(_) => return false,
+        };
+        let mut signed = self.signed_by();
+        signed.dedup();
+        signed.len() == self.signatures.len()
+            && signed.len() >= self.threshold
+            && signed.iter().all(|a| self.signers.binary_search(a).is_ok())
+            && self.signatures.iter().all(|s| s.recover(&message).is_ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_governance_proposal() {
+        let keys: Vec<KeyPair> = (0..3).map(|_| KeyPair::gen_keypair()).collect();
+        let signers: Vec<Address> = keys.iter().map(|k| k.address()).collect();
+        let document = json!({"upgrade": "v2", "height": 1000});
+        let mut proposal = GovernanceProposal::new(document, &signers, 2).unwrap();
+        let mut remote = proposal.clone();
+
+        proposal.sign(&keys[0]).unwrap();
+        assert!(!proposal.is_approved());
+        assert!(proposal.sign(&KeyPair::gen_keypair()).is_err());
+
+        // A copy with the keys in another order signs the same thing.
+        remote.document = serde_json::from_str(r#"{ "height": 1000, "upgrade": "v2" }"#).unwrap();
+        remote.sign(&keys[2]).unwrap();
+        proposal.merge(&remote).unwrap();
+        assert!(proposal.is_approved());
+        assert_eq!(proposal.missing(), vec![keys[1].address()]);
+
+        let json = serde_json::to_string(&proposal).unwrap();
+        let mut decoded: GovernanceProposal = serde_json::from_str(&json).unwrap();
+        assert!(decoded.is_approved());
+        decoded.threshold = 3;
+        assert!(!decoded.is_approved());
+    }
+
+    #[test]
+    fn test_canonical_json_golden() {
+        let document: Value = serde_json::from_str(
+            r#"{"z": [1, -2, {"b": null, "a": true}], "a": "\u00e9\n", "B": {}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            canonical_json(&document).unwrap(),
+            r#"{"B":{},"a":"é\n","z":[1,-2,{"a":true,"b":null}]}"#.as_bytes()
+        );
+        assert!(matches!(
+            canonical_json(&json!({"ratio": 0.5})),
+            Err(Error::InvalidEncoding)
+        ));
+        let proposal = GovernanceProposal::new(json!([1.5]), &[Address::zero()], 1).unwrap();
+        assert!(proposal.signing_hash().is_err());
+        assert!(!proposal.is_approved());
+    }
+}
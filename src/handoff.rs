@@ -0,0 +1,308 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Moving a validator key to a new host in two messages.
+//!
+//! The new host sends a [`HandoffRequest`] carrying a fresh X25519 key,
+//! signed with its identity key. The old host checks it and replies with a
+//! [`HandoffResponse`], signed with its own identity key. The response holds
+//! the validator seed sealed to that X25519 key. Each request is accepted
+//! once and only until it expires. The X25519 secret never leaves the
+//! [`HandoffReceiver`] and is erased when it is dropped.
+
+use super::{pubkey_to_address, Error, KeyPair, Message, NonceStore, PubKey, Signature};
+use crate::clock::now;
+use crate::hasher::hash_data;
+use cita_crypto_trait::{CreateKey, Sign};
+use cita_types::H256;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+use sodiumoxide::crypto::{box_, sealedbox};
+use sodiumoxide::randombytes::randombytes_into;
+use sodiumoxide::utils::memzero;
+use std::time::Duration;
+
+const REQUEST_DOMAIN: &[u8] = b"cita-ed25519 key handoff request";
+const RESPONSE_DOMAIN: &[u8] = b"cita-ed25519 key handoff response";
+
+/// First message, from the new host.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandoffRequest {
+    pub receiver: PubKey,
+    pub ephemeral: H256,
+    pub nonce: u128,
+    pub expires_at: u64,
+    pub signature: Signature,
+}
+
+impl HandoffRequest {
+    pub fn signing_hash(&self) -> Message {
+        let mut s = RlpStream::new_list(5);
+        s.append(&REQUEST_DOMAIN);
+        s.append(&self.receiver);
+        s.append(&self.ephemeral);
+        s.append(&&self.nonce.to_be_bytes()[..]);
+        s.append(&self.expires_at);
+        hash_data(&s.out())
+    }
+}
+
+impl Encodable for HandoffRequest {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(5);
+        s.append(&self.receiver);
+        s.append(&self.ephemeral);
+        s.append(&&self.nonce.to_be_bytes()[..]);
+        s.append(&self.expires_at);
+        s.append(&self.signature);
+    }
+}
+
+impl Decodable for HandoffRequest {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 5 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        let nonce = rlp.at(2)?.decoder().decode_value(|bytes| {
+            let mut nonce = [0u8; 16];
+            if bytes.len() != nonce.len() {
+                return Err(DecoderError::RlpInvalidLength);
+            }
+            nonce.copy_from_slice(bytes);
+            Ok(u128::from_be_bytes(nonce))
+        })?;
+        Ok(HandoffRequest {
+            receiver: rlp.val_at(0)?,
+            ephemeral: rlp.val_at(1)?,
+            nonce,
+            expires_at: rlp.val_at(3)?,
+            signature: rlp.val_at(4)?,
+        })
+    }
+}
+
+/// Second message, from the old host.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandoffResponse {
+    pub sender: PubKey,
+    pub request_hash: H256,
+    #[serde(with = "crate::serde_hex")]
+    pub ciphertext: Vec<u8>,
+    pub signature: Signature,
+}
+
+impl HandoffResponse {
+    pub fn signing_hash(&self) -> Message {
+        let mut s = RlpStream::new_list(4);
+        s.append(&RESPONSE_DOMAIN);
+        s.append(&self.sender);
+        s.append(&self.request_hash);
+        s.append(&self.ciphertext);
+        hash_data(&s.out())
+    }
+}
+
+impl Encodable for HandoffResponse {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(4);
+        s.append(&self.sender);
+        s.append(&self.request_hash);
+        s.append(&self.ciphertext);
+        s.append(&self.signature);
+    }
+}
+
+impl Decodable for HandoffResponse {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 4 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(HandoffResponse {
+            sender: rlp.val_at(0)?,
+            request_hash: rlp.val_at(1)?,
+            ciphertext: rlp.val_at(2)?,
+            signature: rlp.val_at(3)?,
+        })
+    }
+}
+
+/// The new host's side of one handoff.
+pub struct HandoffReceiver {
+    request: HandoffRequest,
+    ephemeral: box_::SecretKey,
+}
+
+impl HandoffReceiver {
+    /// Start a handoff as `identity`, valid for `ttl`.
+    pub fn new(identity: &KeyPair, ttl: Duration) -> Result<(Self, HandoffRequest), Error> {
+        let (pk, sk) = box_::gen_keypair();
+        let mut nonce = [0u8; 16];
+        randombytes_into(&mut nonce);
+        let mut request = HandoffRequest {
+            receiver: *identity.pubkey(),
+            ephemeral: H256::from(pk.0),
+            nonce: u128::from_be_bytes(nonce),
+            expires_at: now().saturating_add(ttl.as_secs()),
+            signature: Signature([0u8; 96]),
+        };
+        request.signature = Signature::sign(identity.privkey(), &request.signing_hash())?;
+        let receiver = HandoffReceiver {
+            request: request.clone(),
+            ephemeral: sk,
+        };
+        Ok((receiver, request))
+    }
+
+    /// Check that `sender` answered our request and unwrap the key.
+    pub fn finish(&self, response: &HandoffResponse, sender: &PubKey) -> Result<KeyPair, Error> {
+        self.finish_at(response, sender, now())
+    }
+
+    /// [`HandoffReceiver::finish`] at `time` seconds since the Unix epoch.
+    pub fn finish_at(
+        &self,
+        response: &HandoffResponse,
+        sender: &PubKey,
+        time: u64,
+    ) -> Result<KeyPair, Error> {
+        if time > self.request.expires_at {
+            return Err(Error::Expired);
+        }
+        if response.sender != *sender || response.request_hash != self.request.signing_hash() {
+            return Err(Error::InvalidMessage);
+        }
+        response
+            .signature
+            .verify_public(sender, &response.signing_hash())?;
+        let pk = box_::PublicKey(self.request.ephemeral.0);
+        let mut seed = sealedbox::open(&response.ciphertext, &pk, &self.ephemeral)
+            .map_err(|_| Error::DecryptionFailed)?;
+        if seed.len() != 32 {
+            memzero(&mut seed);
+            return Err(Error::DecryptionFailed);
+        }
+        let mut fixed = [0u8; 32];
+        fixed.copy_from_slice(&seed);
+        let keypair = KeyPair::from_seed(&fixed);
+        memzero(&mut fixed);
+        memzero(&mut seed);
+        Ok(keypair)
+    }
+}
+
+/// The old host's side: hand `key` to the holder of `receiver`, signing as
+/// `identity`. `store` remembers accepted requests.
+pub fn answer_handoff<S: NonceStore + ?Sized>(
+    key: &KeyPair,
+    identity: &KeyPair,
+    request: &HandoffRequest,
+    receiver: &PubKey,
+    store: &S,
+) -> Result<HandoffResponse, Error> {
+    answer_handoff_at(key, identity, request, receiver, store, now())
+}
+
+/// [`answer_handoff`] at `time` seconds since the Unix epoch.
+pub fn answer_handoff_at<S: NonceStore + ?Sized>(
+    key: &KeyPair,
+    identity: &KeyPair,
+    request: &HandoffRequest,
+    receiver: &PubKey,
+    store: &S,
+    time: u64,
+) -> Result<HandoffResponse, Error> {
+    if time > request.expires_at {
+        return Err(Error::Expired);
+    }
+    if request.receiver != *receiver {
+        return Err(Error::InvalidPubKey);
+    }
+    let request_hash = request.signing_hash();
+    request.signature.verify_public(receiver, &request_hash)?;
+    if !store.check_and_insert(
+        &pubkey_to_address(receiver),
+        request.nonce,
+        request.expires_at,
+    ) {
+        return Err(Error::Replayed);
+    }
+
+    let pk = box_::PublicKey(request.ephemeral.0);
+    let mut response = HandoffResponse {
+        sender: *identity.pubkey(),
+        request_hash,
+        ciphertext: sealedbox::seal(&key.privkey().0[..32], &pk),
+        signature: Signature([0u8; 96]),
+    };
+    response.signature = Signature::sign(identity.privkey(), &response.signing_hash())?;
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryNonceStore;
+
+    #[test]
+    fn test_key_handoff() {
+        let validator = KeyPair::gen_keypair();
+        let old_host = KeyPair::gen_keypair();
+        let new_host = KeyPair::gen_keypair();
+        let store = MemoryNonceStore::new();
+
+        let (receiver, request) = HandoffReceiver::new(&new_host, Duration::from_secs(60)).unwrap();
+        let request: HandoffRequest = rlp::decode(&rlp::encode(&request)).unwrap();
+        let stranger = KeyPair::gen_keypair();
+        assert!(
+            answer_handoff(&validator, &old_host, &request, stranger.pubkey(), &store).is_err()
+        );
+
+        let response =
+            answer_handoff(&validator, &old_host, &request, new_host.pubkey(), &store).unwrap();
+        assert!(matches!(
+            answer_handoff(&validator, &old_host, &request, new_host.pubkey(), &store),
+            Err(Error::Replayed)
+        ));
+        let json = serde_json::to_string(&response).unwrap();
+        let response: HandoffResponse = serde_json::from_str(&json).unwrap();
+
+        let mut forged = response.clone();
+        forged.sender = *stranger.pubkey();
+        forged.signature = Signature::sign(stranger.privkey(), &forged.signing_hash()).unwrap();
+        assert!(receiver.finish(&forged, old_host.pubkey()).is_err());
+        let (other, _) = HandoffReceiver::new(&new_host, Duration::from_secs(60)).unwrap();
+        assert!(other.finish(&response, old_host.pubkey()).is_err());
+
+        let received = receiver.finish(&response, old_host.pubkey()).unwrap();
+        assert_eq!(received.pubkey(), validator.pubkey());
+        assert_eq!(received.privkey(), validator.privkey());
+    }
+
+    #[test]
+    fn test_expired_handoff() {
+        let host = KeyPair::gen_keypair();
+        let (receiver, request) = HandoffReceiver::new(&host, Duration::from_secs(5)).unwrap();
+        let late = request.expires_at + 1;
+        let store = MemoryNonceStore::new();
+        assert!(matches!(
+            answer_handoff_at(&host, &host, &request, host.pubkey(), &store, late),
+            Err(Error::Expired)
+        ));
+        let response = answer_handoff(&host, &host, &request, host.pubkey(), &store).unwrap();
+        assert!(matches!(
+            receiver.finish_at(&response, host.pubkey(), late),
+            Err(Error::Expired)
+        ));
+    }
+}
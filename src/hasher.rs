@@ -0,0 +1,219 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The hash behind addresses and signing digests, swappable at runtime.
+//!
+//! Addresses, [`SigningHasher`](crate::SigningHasher) and the domain-tagged
+//! `signing_hash` of each signed record type go through [`hash_data`]. By
+//! default that is the hash selected at build time by the `sha3hash`,
+//! `blake2bhash` or `sm3hash` feature. A process can register another
+//! implementation of the same function, such as a hardware-accelerated one,
+//! and switch to it once at startup. The replacement must produce identical
+//! output, or every address and signed digest changes with it, so
+//! [`register_hasher`] checks it against the built-in hash on known inputs
+//! first.
+//!
+//! Digests whose algorithm is fixed by a format do not go through
+//! [`hash_data`]: SHA-512 inside Ed25519 and key derivation, and SHA-256
+//! for streamed-message signatures, timelock puzzles, testnet and
+//! test-vector seeds, watermark and usage-count file checksums and the
+//! SHA-256 address scheme.
+
+use super::Error;
+use cita_types::H256;
+use hashable::Hashable;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+#[cfg(feature = "sha3hash")]
+const BUILTIN_NAME: &str = "sha3";
+#[cfg(feature = "blake2bhash")]
+const BUILTIN_NAME: &str = "blake2b";
+#[cfg(feature = "sm3hash")]
+const BUILTIN_NAME: &str = "sm3";
+
+/// A 256-bit hash function.
+pub trait Hasher: Send + Sync {
+    fn name(&self) -> &str;
+    fn hash(&self, data: &[u8]) -> H256;
+
+    /// Hash several inputs at once. Multi-buffer SIMD implementations
+    /// override this to hash independent inputs in parallel lanes.
+    fn hash_many(&self, inputs: &[&[u8]]) -> Vec<H256> {
+        inputs.iter().map(|data| self.hash(data)).collect()
+    }
+}
+
+/// The hash compiled into this build.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BuiltinHasher;
+
+impl Hasher for BuiltinHasher {
+    fn name(&self) -> &str {
+        BUILTIN_NAME
+    }
+
+    fn hash(&self, data: &[u8]) -> H256 {
+        data.crypt_hash()
+    }
+}
+
+static REGISTRY: RwLock<Vec<Arc<dyn Hasher>>> = RwLock::new(Vec::new());
+static ACTIVE: RwLock<Option<Arc<dyn Hasher>>> = RwLock::new(None);
+// Lets the default path skip the lock.
+static REPLACED: AtomicBool = AtomicBool::new(false);
+
+// A panic while holding a lock leaves the data intact, since every write
+// is a single assignment or push.
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+// Empty, short, and longer than a block of any of the built-in hashes.
+fn matches_builtin(hasher: &dyn Hasher) -> bool {
+    let long = [0xa5u8; 200];
+    let inputs: [&[u8]; 3] = [b"", b"abc", &long];
+    let expected = BuiltinHasher.hash_many(&inputs);
+    inputs
+        .iter()
+        .zip(&expected)
+        .all(|(data, digest)| hasher.hash(data) == *digest)
+        && hasher.hash_many(&inputs) == expected
+}
+
+/// Make `hasher` available to [`use_hasher`] under its name.
+///
+/// Fails if the name is taken or if `hasher` disagrees with the built-in
+/// hash on known inputs.
+pub fn register_hasher(hasher: Arc<dyn Hasher>) -> Result<(), Error> {
+    let mut registry = write(&REGISTRY);
+    if hasher.name() == BUILTIN_NAME
+        || registry.iter().any(|h| h.name() == hasher.name())
+        || !matches_builtin(&*hasher)
+    {
+        return Err(Error::InvalidHasher);
+    }
+    registry.push(hasher);
+    Ok(())
+}
+
+/// Route every digest through the hasher registered as `name`; the name of
+/// the built-in hash switches back to it.
+pub fn use_hasher(name: &str) -> Result<(), Error> {
+    if name == BUILTIN_NAME {
+        REPLACED.store(false, Ordering::Release);
+        *write(&ACTIVE) = None;
+        return Ok(());
+    }
+    let hasher = read(&REGISTRY)
+        .iter()
+        .find(|h| h.name() == name)
+        .cloned()
+        .ok_or(Error::InvalidHasher)?;
+    *write(&ACTIVE) = Some(hasher);
+    REPLACED.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// Name of the hasher in use.
+pub fn active_hasher() -> String {
+    match read(&ACTIVE).as_ref() {
+        Some(hasher) => hasher.name().to_owned(),
+        None => BUILTIN_NAME.to_owned(),
+    }
+}
+
+/// Hash `data` with the hasher in use.
+pub fn hash_data(data: &[u8]) -> H256 {
+    if REPLACED.load(Ordering::Acquire) {
+        if let Some(hasher) = read(&ACTIVE).as_ref() {
+            return hasher.hash(data);
+        }
+    }
+    data.crypt_hash()
+}
+
+/// [`hash_data`] over many inputs, through the active hasher's
+/// [`Hasher::hash_many`].
+pub fn hash_data_many(inputs: &[&[u8]]) -> Vec<H256> {
+    if REPLACED.load(Ordering::Acquire) {
+        if let Some(hasher) = read(&ACTIVE).as_ref() {
+            return hasher.hash_many(inputs);
+        }
+    }
+    BuiltinHasher.hash_many(inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pubkey_to_address, PubKey};
+    use std::sync::atomic::AtomicU64;
+
+    // Same output as the built-in hash, so other tests running meanwhile
+    // are unaffected.
+    struct CountingHasher(AtomicU64);
+
+    impl Hasher for CountingHasher {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn hash(&self, data: &[u8]) -> H256 {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            BuiltinHasher.hash(data)
+        }
+    }
+
+    struct WrongHasher;
+
+    impl Hasher for WrongHasher {
+        fn name(&self) -> &str {
+            "wrong"
+        }
+
+        fn hash(&self, data: &[u8]) -> H256 {
+            let mut digest = BuiltinHasher.hash(data);
+            digest.0[0] ^= 1;
+            digest
+        }
+    }
+
+    #[test]
+    fn test_hasher_registry() {
+        let counting = Arc::new(CountingHasher(AtomicU64::new(0)));
+        register_hasher(counting.clone()).unwrap();
+        assert!(register_hasher(counting.clone()).is_err());
+        assert!(register_hasher(Arc::new(BuiltinHasher)).is_err());
+        assert!(use_hasher("unknown").is_err());
+        assert!(matches!(
+            register_hasher(Arc::new(WrongHasher)),
+            Err(Error::InvalidHasher)
+        ));
+        assert!(use_hasher("wrong").is_err());
+
+        let pubkey = PubKey::from_low_u64_be(7);
+        let expected = pubkey_to_address(&pubkey);
+        use_hasher("counting").unwrap();
+        assert_eq!(active_hasher(), "counting");
+        assert_eq!(pubkey_to_address(&pubkey), expected);
+        use_hasher(BUILTIN_NAME).unwrap();
+        assert_eq!(active_hasher(), BUILTIN_NAME);
+        assert!(counting.0.load(Ordering::SeqCst) >= 1);
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Continuous health tests on the bytes that become key seeds, modelled on
+//! the repetition count and adaptive proportion tests of NIST SP 800-90B
+//! section 4.4.
+//!
+//! The tests watch the output of the system RNG rather than a raw noise
+//! source, so they only catch gross failures such as a stuck or constant
+//! generator. Cutoffs assume a conservative 4 bits of min-entropy per byte
+//! and a false positive rate of 2^-20.
+
+use super::{Error, KeyPair};
+use sodiumoxide::randombytes::randombytes_into;
+use sodiumoxide::utils::memzero;
+use std::sync::Mutex;
+
+/// A byte repeated this many times in a row fails the repetition count test.
+pub const REPETITION_CUTOFF: usize = 6;
+/// Samples per adaptive proportion test window.
+pub const PROPORTION_WINDOW: usize = 512;
+/// A window whose first byte occurs this many times fails the adaptive
+/// proportion test.
+pub const PROPORTION_CUTOFF: usize = 62;
+
+/// Which health test rejected the entropy stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthFailure {
+    RepetitionCount,
+    AdaptiveProportion,
+}
+
+/// State of both tests, carried across calls so that windows span several
+/// key generations.
+#[derive(Debug, Default, Clone)]
+pub struct HealthTests {
+    last: Option<u8>,
+    repetitions: usize,
+    window_sample: u8,
+    window_seen: usize,
+    window_matches: usize,
+}
+
+impl HealthTests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed `samples` through both tests.
+    pub fn check(&mut self, samples: &[u8]) -> Result<(), HealthFailure> {
+        for &sample in samples {
+            if self.last == Some(sample) {
+                self.repetitions += 1;
+                if self.repetitions >= REPETITION_CUTOFF {
+                    return Err(HealthFailure::RepetitionCount);
+                }
+            } else {
+                self.last = Some(sample);
+                self.repetitions = 1;
+            }
+
+            if self.window_seen == 0 {
+                self.window_sample = sample;
+                self.window_matches = 0;
+            }
+            if sample == self.window_sample {
+                self.window_matches += 1;
+                if self.window_matches >= PROPORTION_CUTOFF {
+                    return Err(HealthFailure::AdaptiveProportion);
+                }
+            }
+            self.window_seen = (self.window_seen + 1) % PROPORTION_WINDOW;
+        }
+        Ok(())
+    }
+}
+
+type FailureHook = Box<dyn Fn(HealthFailure) + Send + Sync>;
+
+static HEALTH: Mutex<Option<HealthTests>> = Mutex::new(None);
+static FAILURE_HOOK: Mutex<Option<FailureHook>> = Mutex::new(None);
+
+/// Install a callback run whenever a health test fails, before key
+/// generation reports the failure. Replaces any earlier callback.
+pub fn set_health_failure_hook<F>(hook: F)
+where
+    F: Fn(HealthFailure) + Send + Sync + 'static,
+{
+    *FAILURE_HOOK.lock().unwrap() = Some(Box::new(hook));
+}
+
+/// Fill `seed` from the system RNG, running the bytes through the health
+/// tests first.
+pub(crate) fn checked_seed(seed: &mut [u8; 32]) -> Result<(), Error> {
+    randombytes_into(seed);
+    let result = HEALTH
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HealthTests::new)
+        .check(seed);
+    if let Err(failure) = result {
+        memzero(seed);
+        // The stream is suspect from here on; start the tests afresh.
+        *HEALTH.lock().unwrap() = None;
+        if let Some(hook) = FAILURE_HOOK.lock().unwrap().as_ref() {
+            hook(failure);
+        }
+        return Err(Error::EntropyFailure);
+    }
+    Ok(())
+}
+
+impl KeyPair {
+    /// Generate a key pair from a seed that passed the entropy health tests.
+    pub fn try_gen_keypair() -> Result<KeyPair, Error> {
+        let mut seed = [0u8; 32];
+        checked_seed(&mut seed)?;
+        let keypair = KeyPair::from_seed(&seed);
+        memzero(&mut seed);
+        #[cfg(feature = "tracing")]
+        crate::trace::keygen(cita_crypto_trait::CreateKey::pubkey(&keypair));
+        Ok(keypair)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cita_crypto_trait::CreateKey;
+
+    #[test]
+    fn test_health_tests() {
+        let mut tests = HealthTests::new();
+        let mut bytes = [0u8; 4096];
+        randombytes_into(&mut bytes);
+        // A chance failure here is as likely as the 2^-20 cutoff promises.
+        assert!(tests.check(&bytes).is_ok());
+        assert_eq!(
+            HealthTests::new().check(&[7; 8]),
+            Err(HealthFailure::RepetitionCount)
+        );
+
+        let biased: Vec<u8> = (0..PROPORTION_WINDOW)
+            .map(|i| if i % 2 == 0 { 0xaa } else { i as u8 })
+            .collect();
+        assert_eq!(
+            HealthTests::new().check(&biased),
+            Err(HealthFailure::AdaptiveProportion)
+        );
+    }
+
+    #[test]
+    fn test_try_gen_keypair() {
+        let keypair = KeyPair::try_gen_keypair().unwrap();
+        let other = KeyPair::from_privkey(*keypair.privkey()).unwrap();
+        assert_eq!(keypair.pubkey(), other.pubkey());
+        assert_ne!(keypair.pubkey(), KeyPair::gen_keypair().pubkey());
+    }
+}
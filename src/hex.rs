@@ -0,0 +1,60 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lower-case hex formatting straight into a `fmt::Formatter`.
+
+use std::fmt;
+use std::str;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Formats the wrapped bytes as lower-case hex, in chunks encoded on the
+/// stack, so no `String` is built. `Debug` adds quotes, matching how the hex
+/// strings it replaces used to print.
+pub(crate) struct Hex<'a>(pub &'a [u8]);
+
+impl<'a> fmt::Display for Hex<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut buf = [0u8; 128];
+        for chunk in self.0.chunks(buf.len() / 2) {
+            for (i, byte) in chunk.iter().enumerate() {
+                buf[2 * i] = HEX_DIGITS[(byte >> 4) as usize];
+                buf[2 * i + 1] = HEX_DIGITS[(byte & 0x0f) as usize];
+            }
+            let digits = str::from_utf8(&buf[..2 * chunk.len()]).map_err(|_| fmt::Error)?;
+            f.write_str(digits)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Debug for Hex<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}\"", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_serialize::hex::ToHex;
+
+    #[test]
+    fn test_hex_matches_to_hex() {
+        let bytes: Vec<u8> = (0..=255u8).collect();
+        assert_eq!(Hex(&bytes).to_string(), bytes.to_hex());
+        assert_eq!(format!("{:?}", Hex(&[0xab])), "\"ab\"");
+        assert_eq!(Hex(&[]).to_string(), "");
+    }
+}
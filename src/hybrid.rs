@@ -0,0 +1,186 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Experimental hybrid signatures: an Ed25519 signature and a post-quantum
+//! signature over the same statement, valid only if both verify.
+//!
+//! No post-quantum algorithm ships with this crate. Callers plug one in
+//! through [`PqSigner`] and [`PqVerifier`]; this module fixes the envelope
+//! so that chains can start carrying it before the algorithm choice is final.
+
+use super::{Error, KeyPair, Message, PubKey, Signature};
+use crate::hasher::hash_data;
+use cita_crypto_trait::{CreateKey, Sign};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+
+const HYBRID_DOMAIN: &[u8] = b"cita-ed25519 hybrid signature";
+
+/// Algorithm IDs for the post-quantum half.
+pub const PQ_ML_DSA_44: u16 = 1;
+pub const PQ_ML_DSA_65: u16 = 2;
+pub const PQ_ML_DSA_87: u16 = 3;
+
+/// A post-quantum private key able to sign.
+pub trait PqSigner {
+    fn algorithm(&self) -> u16;
+    fn public_key(&self) -> Vec<u8>;
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error>;
+}
+
+/// Verification for one post-quantum algorithm.
+pub trait PqVerifier {
+    fn algorithm(&self) -> u16;
+    fn verify(&self, public_key: &[u8], message: &[u8], signature: &[u8]) -> bool;
+}
+
+/// Both signatures and the post-quantum public key they need.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HybridSignature {
+    pub ed25519: Signature,
+    pub pq_algorithm: u16,
+    #[serde(with = "crate::serde_hex")]
+    pub pq_pubkey: Vec<u8>,
+    #[serde(with = "crate::serde_hex")]
+    pub pq_signature: Vec<u8>,
+}
+
+// Both halves sign the message bound to both public keys, so neither
+// signature can be lifted into an envelope with a different partner key.
+fn hybrid_hash(message: &Message, ed25519: &PubKey, algorithm: u16, pq_pubkey: &[u8]) -> Message {
+    let mut s = RlpStream::new_list(5);
+    s.append(&HYBRID_DOMAIN);
+    s.append(message);
+    s.append(ed25519);
+    s.append(&algorithm);
+    s.append(&pq_pubkey);
+    hash_data(&s.out())
+}
+
+impl HybridSignature {
+    pub fn sign<P: PqSigner + ?Sized>(
+        keypair: &KeyPair,
+        pq_signer: &P,
+        message: &Message,
+    ) -> Result<Self, Error> {
+        let pq_algorithm = pq_signer.algorithm();
+        let pq_pubkey = pq_signer.public_key();
+        let statement = hybrid_hash(message, keypair.pubkey(), pq_algorithm, &pq_pubkey);
+        Ok(HybridSignature {
+            ed25519: Signature::sign(keypair.privkey(), &statement)?,
+            pq_signature: pq_signer.sign(statement.as_ref())?,
+            pq_algorithm,
+            pq_pubkey,
+        })
+    }
+
+    /// Check both halves; returns the Ed25519 public key on success.
+    pub fn verify<V: PqVerifier + ?Sized>(
+        &self,
+        message: &Message,
+        pq_verifier: &V,
+    ) -> Result<PubKey, Error> {
+        if pq_verifier.algorithm() != self.pq_algorithm {
+            return Err(Error::UnsupportedSuite);
+        }
+        let ed25519 = PubKey::from_slice(self.ed25519.pk());
+        let statement = hybrid_hash(message, &ed25519, self.pq_algorithm, &self.pq_pubkey);
+        self.ed25519.verify_public(&ed25519, &statement)?;
+        if !pq_verifier.verify(&self.pq_pubkey, statement.as_ref(), &self.pq_signature) {
+            return Err(Error::InvalidSignature);
+        }
+        Ok(ed25519)
+    }
+}
+
+impl Encodable for HybridSignature {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(4);
+        s.append(&self.ed25519);
+        s.append(&self.pq_algorithm);
+        s.append(&self.pq_pubkey);
+        s.append(&self.pq_signature);
+    }
+}
+
+impl Decodable for HybridSignature {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 4 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(HybridSignature {
+            ed25519: rlp.val_at(0)?,
+            pq_algorithm: rlp.val_at(1)?,
+            pq_pubkey: rlp.val_at(2)?,
+            pq_signature: rlp.val_at(3)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sodiumoxide::crypto::sign;
+
+    // Stands in for a real post-quantum scheme in tests.
+    struct MockPq(KeyPair);
+
+    impl PqSigner for MockPq {
+        fn algorithm(&self) -> u16 {
+            0xffff
+        }
+
+        fn public_key(&self) -> Vec<u8> {
+            self.0.pubkey().0.to_vec()
+        }
+
+        fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+            let sk = sign::SecretKey::from_slice(self.0.privkey().as_ref()).unwrap();
+            Ok(sign::sign_detached(message, &sk).as_ref().to_vec())
+        }
+    }
+
+    impl PqVerifier for MockPq {
+        fn algorithm(&self) -> u16 {
+            0xffff
+        }
+
+        fn verify(&self, public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+            match (
+                sign::PublicKey::from_slice(public_key),
+                sign::Signature::from_bytes(signature),
+            ) {
+                (Some(pk), Ok(sig)) => sign::verify_detached(&sig, message, &pk),
+                _ => false,
+            }
+        }
+    }
+
+    #[test]
+    fn test_hybrid_signature() {
+        let keypair = KeyPair::gen_keypair();
+        let pq = MockPq(KeyPair::gen_keypair());
+        let msg = Message::from_low_u64_be(5);
+        let sig = HybridSignature::sign(&keypair, &pq, &msg).unwrap();
+        let sig: HybridSignature = rlp::decode(&rlp::encode(&sig)).unwrap();
+        assert_eq!(sig.verify(&msg, &pq).unwrap(), *keypair.pubkey());
+
+        let mut broken = sig.clone();
+        broken.pq_signature[0] ^= 1;
+        assert!(broken.verify(&msg, &pq).is_err());
+        let mut swapped = sig;
+        swapped.pq_pubkey = KeyPair::gen_keypair().pubkey().0.to_vec();
+        assert!(swapped.verify(&msg, &pq).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{pubkey_to_address, Address, PubKey, Signature};
+use crate::curve::{canonical_point, is_canonical_scalar};
+use crate::hex::Hex;
+use cita_types::H256;
+use libsodium_sys as ffi;
+use std::fmt;
+
+/// The parts of a signature and what can be said about them without a
+/// message, for debugging verification failures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureReport {
+    pub r: H256,
+    pub s: H256,
+    pub pubkey: PubKey,
+    pub address: Address,
+    /// R is encoded with its y-coordinate reduced modulo p.
+    pub r_canonical: bool,
+    /// S is below the group order.
+    pub s_canonical: bool,
+    /// The public key is encoded with its y-coordinate reduced modulo p.
+    pub pubkey_canonical: bool,
+    /// The public key is a point of the main subgroup and not of small order.
+    pub pubkey_valid: bool,
+}
+
+impl SignatureReport {
+    /// Whether the signature is in the only encoding strict verifiers accept.
+    pub fn is_canonical(&self) -> bool {
+        self.r_canonical && self.s_canonical && self.pubkey_canonical
+    }
+}
+
+fn yes_no(ok: bool, yes: &'static str, no: &'static str) -> &'static str {
+    if ok {
+        yes
+    } else {
+        no
+    }
+}
+
+impl fmt::Display for SignatureReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let r = yes_no(self.r_canonical, "canonical", "non-canonical");
+        let s = yes_no(self.s_canonical, "canonical", "not reduced");
+        let pubkey = yes_no(self.pubkey_valid, "valid", "invalid point");
+        writeln!(f, "R:        {} ({})", Hex(&self.r.0), r)?;
+        writeln!(f, "S:        {} ({})", Hex(&self.s.0), s)?;
+        writeln!(f, "pubkey:   {} ({})", Hex(&self.pubkey.0), pubkey)?;
+        write!(f, "address:  {}", Hex(&self.address.0))
+    }
+}
+
+impl Signature {
+    /// Split the signature into its parts and check each one's encoding.
+    pub fn inspect(&self) -> SignatureReport {
+        let mut r = [0u8; 32];
+        let mut s = [0u8; 32];
+        let mut pk = [0u8; 32];
+        r.copy_from_slice(&self.0[0..32]);
+        s.copy_from_slice(&self.0[32..64]);
+        pk.copy_from_slice(&self.0[64..96]);
+        let pubkey_valid = unsafe { ffi::crypto_core_ed25519_is_valid_point(pk.as_ptr()) } == 1;
+        SignatureReport {
+            r: H256::from(r),
+            s: H256::from(s),
+            pubkey: PubKey::from(pk),
+            address: pubkey_to_address(&PubKey::from(pk)),
+            r_canonical: canonical_point(&r) == r,
+            s_canonical: is_canonical_scalar(&s),
+            pubkey_canonical: canonical_point(&pk) == pk,
+            pubkey_valid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeyPair, Message};
+    use cita_crypto_trait::{CreateKey, Sign};
+
+    #[test]
+    fn test_inspect() {
+        let keypair = KeyPair::gen_keypair();
+        let sig = Signature::sign(keypair.privkey(), &Message::from_low_u64_be(2)).unwrap();
+        let report = sig.inspect();
+        assert!(report.is_canonical() && report.pubkey_valid);
+        assert_eq!(report.address, keypair.address());
+        assert_eq!(report.pubkey, *keypair.pubkey());
+        let shown = report.to_string();
+        assert_eq!(shown.lines().count(), 4);
+        assert!(shown.contains(&Hex(&keypair.address().0).to_string()));
+
+        let mut bad = sig;
+        bad.0[63] |= 0xf0;
+        bad.0[64..].copy_from_slice(&[0u8; 32]);
+        let report = bad.inspect();
+        assert!(!report.s_canonical && !report.pubkey_valid);
+        assert!(report.to_string().contains("not reduced"));
+    }
+}
@@ -12,16 +12,38 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::{Address, PrivKey, PubKey};
+use super::{Address, PrivKey, PubKey, PRIVKEY_BYTES_LEN, PUBKEY_BYTES_LEN};
 use crate::error::Error;
+use crate::hasher::hash_data;
+use crate::hex::Hex;
+use crate::meter::{charge, Operation};
 use cita_crypto_trait::CreateKey;
-use hashable::Hashable;
-use rustc_serialize::hex::ToHex;
+#[cfg(not(feature = "fips-like"))]
 use sodiumoxide::crypto::sign::gen_keypair;
+use sodiumoxide::crypto::sign::{keypair_from_seed, Seed};
+use sodiumoxide::utils::memzero;
 use std::fmt;
+use std::mem;
 
 pub fn pubkey_to_address(pubkey: &PubKey) -> Address {
-    Address::from(pubkey.crypt_hash())
+    charge(Operation::AddressHash, 1);
+    Address::from(hash_data(&pubkey.0))
+}
+
+/// A public key from `bytes`, or `InvalidLength` unless there are exactly 32.
+pub fn pubkey_from_slice(bytes: &[u8]) -> Result<PubKey, Error> {
+    if bytes.len() != PUBKEY_BYTES_LEN {
+        return Err(Error::InvalidLength);
+    }
+    Ok(PubKey::from_slice(bytes))
+}
+
+/// A private key from `bytes`, or `InvalidLength` unless there are exactly 64.
+pub fn privkey_from_slice(bytes: &[u8]) -> Result<PrivKey, Error> {
+    if bytes.len() != PRIVKEY_BYTES_LEN {
+        return Err(Error::InvalidLength);
+    }
+    Ok(PrivKey::from_slice(bytes))
 }
 
 #[derive(Default)]
@@ -30,11 +52,40 @@ pub struct KeyPair {
     pubkey: PubKey,
 }
 
+/// Shows the public key and address only, so a loaded key pair can be logged.
 impl fmt::Display for KeyPair {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        writeln!(f, "privkey:  {}", self.privkey.0.to_hex())?;
-        writeln!(f, "pubkey:  {}", self.pubkey.0.to_hex())?;
-        write!(f, "address:  {}", self.address().0.to_hex())
+        writeln!(f, "pubkey:  {}", Hex(&self.pubkey.0))?;
+        write!(f, "address:  {}", Hex(&self.address().0))
+    }
+}
+
+impl Drop for KeyPair {
+    fn drop(&mut self) {
+        memzero(&mut self.privkey.0);
+    }
+}
+
+impl From<KeyPair> for (PrivKey, PubKey) {
+    fn from(keypair: KeyPair) -> Self {
+        keypair.into_parts()
+    }
+}
+
+impl KeyPair {
+    /// Split the key pair without copying the private key out of a value that
+    /// stays alive; the pair's own copy is zeroed when it is dropped.
+    pub fn into_parts(mut self) -> (PrivKey, PubKey) {
+        (mem::take(&mut self.privkey), self.pubkey)
+    }
+
+    /// Build the key pair for a 32-byte Ed25519 seed.
+    pub fn from_seed(seed: &[u8; 32]) -> Self {
+        let (pk, sk) = keypair_from_seed(&Seed(*seed));
+        KeyPair {
+            privkey: PrivKey::from(sk.0),
+            pubkey: PubKey::from(pk.0),
+        }
     }
 }
 
@@ -49,12 +100,23 @@ impl CreateKey for KeyPair {
         Ok(KeyPair { privkey, pubkey })
     }
 
+    /// With the `fips-like` feature the seed goes through the entropy health
+    /// tests, and a failure panics; use `KeyPair::try_gen_keypair` to handle it.
+    #[cfg(feature = "fips-like")]
+    fn gen_keypair() -> Self {
+        KeyPair::try_gen_keypair().expect("entropy health test failed")
+    }
+
+    #[cfg(not(feature = "fips-like"))]
     fn gen_keypair() -> Self {
         let (pk, sk) = gen_keypair();
-        KeyPair {
+        let keypair = KeyPair {
             privkey: PrivKey::from(sk.0),
             pubkey: PubKey::from(pk.0),
-        }
+        };
+        #[cfg(feature = "tracing")]
+        crate::trace::keygen(&keypair.pubkey);
+        keypair
     }
 
     fn privkey(&self) -> &Self::PrivKey {
@@ -82,4 +144,44 @@ mod tests {
         assert_eq!(keypair1.pubkey, keypair2.pubkey);
         assert_eq!(keypair1.privkey, keypair2.privkey);
     }
+
+    #[test]
+    fn test_keys_from_slice() {
+        let keypair = KeyPair::gen_keypair();
+        let privkey = privkey_from_slice(&keypair.privkey.0).unwrap();
+        assert_eq!(privkey, keypair.privkey);
+        assert_eq!(pubkey_from_slice(&privkey.0[32..]).unwrap(), keypair.pubkey);
+        assert!(matches!(
+            pubkey_from_slice(&privkey.0),
+            Err(Error::InvalidLength)
+        ));
+        assert!(privkey_from_slice(&privkey.0[..32]).is_err());
+    }
+
+    #[test]
+    fn test_from_seed() {
+        let keypair1 = KeyPair::gen_keypair();
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&keypair1.privkey.0[..32]);
+        let keypair2 = KeyPair::from_seed(&seed);
+        assert_eq!(keypair1.pubkey, keypair2.pubkey);
+        assert_eq!(keypair1.privkey, keypair2.privkey);
+    }
+
+    #[test]
+    fn test_into_parts() {
+        let keypair = KeyPair::gen_keypair();
+        let (expected_privkey, expected_pubkey) = (keypair.privkey, keypair.pubkey);
+        let (privkey, pubkey): (PrivKey, PubKey) = keypair.into();
+        assert_eq!(privkey, expected_privkey);
+        assert_eq!(pubkey, expected_pubkey);
+    }
+
+    #[test]
+    fn test_display_hides_privkey() {
+        let keypair = KeyPair::gen_keypair();
+        let shown = keypair.to_string();
+        assert!(shown.contains(&Hex(&keypair.pubkey.0).to_string()));
+        assert!(!shown.contains(&Hex(&keypair.privkey.0[..32]).to_string()));
+    }
 }
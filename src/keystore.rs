@@ -0,0 +1,345 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Password-encrypted key files, so node keys need not sit in config files
+//! as raw hex.
+//!
+//! The key is derived from the password with Argon2id and encrypts the
+//! 32-byte seed with AES-256-GCM, or XChaCha20-Poly1305 on CPUs without
+//! AES-NI. The public key and format version are bound as associated data.
+
+use super::{Address, Error, KeyPair, PubKey};
+use cita_crypto_trait::CreateKey;
+use rlp::RlpStream;
+use serde::{Deserialize, Serialize};
+use sodiumoxide::crypto::aead::{aes256gcm, xchacha20poly1305_ietf};
+use sodiumoxide::crypto::pwhash::argon2id13;
+use sodiumoxide::utils::memzero;
+
+pub const KEYSTORE_VERSION: u8 = 1;
+
+const KEYSTORE_DOMAIN: &[u8] = b"cita-ed25519 keystore";
+const KEY_LEN: usize = 32;
+
+/// Argon2id cost. Files asking for more than the sensitive limits are
+/// refused, so a hostile file cannot make loading exhaust memory, and so are
+/// files asking for less than the interactive limits, so it cannot make the
+/// password cheap to guess either.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeystoreParams {
+    pub opslimit: u64,
+    /// Bytes.
+    pub memlimit: u64,
+}
+
+impl KeystoreParams {
+    /// Cheap enough for interactive use; prefer the default for node keys.
+    pub fn interactive() -> Self {
+        KeystoreParams {
+            opslimit: argon2id13::OPSLIMIT_INTERACTIVE.0 as u64,
+            memlimit: argon2id13::MEMLIMIT_INTERACTIVE.0 as u64,
+        }
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        let ops =
+            argon2id13::OPSLIMIT_INTERACTIVE.0 as u64..=argon2id13::OPSLIMIT_SENSITIVE.0 as u64;
+        let mem =
+            argon2id13::MEMLIMIT_INTERACTIVE.0 as u64..=argon2id13::MEMLIMIT_SENSITIVE.0 as u64;
+        if !ops.contains(&self.opslimit) || !mem.contains(&self.memlimit) {
+            return Err(Error::InvalidKdfParams);
+        }
+        Ok(())
+    }
+}
+
+impl Default for KeystoreParams {
+    fn default() -> Self {
+        KeystoreParams {
+            opslimit: argon2id13::OPSLIMIT_MODERATE.0 as u64,
+            memlimit: argon2id13::MEMLIMIT_MODERATE.0 as u64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeystoreCipher {
+    #[serde(rename = "aes-256-gcm")]
+    Aes256Gcm,
+    #[serde(rename = "xchacha20-poly1305")]
+    XChaCha20Poly1305,
+}
+
+impl KeystoreCipher {
+    /// AES-256-GCM where the CPU supports it in hardware.
+    pub fn preferred() -> Self {
+        if aes().is_ok() {
+            KeystoreCipher::Aes256Gcm
+        } else {
+            KeystoreCipher::XChaCha20Poly1305
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "algorithm", rename_all = "lowercase")]
+pub enum KeystoreKdf {
+    Argon2id {
+        #[serde(with = "crate::serde_hex")]
+        salt: Vec<u8>,
+        opslimit: u64,
+        memlimit: u64,
+    },
+}
+
+/// A key pair encrypted under a password, serialized as JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeystoreJson {
+    pub version: u8,
+    pub address: Address,
+    pub pubkey: PubKey,
+    pub kdf: KeystoreKdf,
+    pub cipher: KeystoreCipher,
+    #[serde(with = "crate::serde_hex")]
+    pub nonce: Vec<u8>,
+    #[serde(with = "crate::serde_hex")]
+    pub ciphertext: Vec<u8>,
+}
+
+fn associated_data(version: u8, pubkey: &PubKey) -> Vec<u8> {
+    let mut s = RlpStream::new_list(3);
+    s.append(&KEYSTORE_DOMAIN);
+    s.append(&version);
+    s.append(pubkey);
+    s.out().to_vec()
+}
+
+fn derive_key(password: &str, kdf: &KeystoreKdf) -> Result<[u8; KEY_LEN], Error> {
+    let KeystoreKdf::Argon2id {
+        salt,
+        opslimit,
+        memlimit,
+    } = kdf;
+    let params = KeystoreParams {
+        opslimit: *opslimit,
+        memlimit: *memlimit,
+    };
+    params.check()?;
+    let salt = argon2id13::Salt::from_slice(salt).ok_or(Error::InvalidEncoding)?;
+    let mut key = [0u8; KEY_LEN];
+    argon2id13::derive_key(
+        &mut key,
+        password.as_bytes(),
+        &salt,
+        argon2id13::OpsLimit(params.opslimit as usize),
+        argon2id13::MemLimit(params.memlimit as usize),
+    )
+    .map_err(|_| Error::KeyDerivationFailed)?;
+    Ok(key)
+}
+
+fn aes() -> Result<aes256gcm::Aes256Gcm, Error> {
+    sodiumoxide::init().map_err(|_| Error::InitFailed)?;
+    aes256gcm::Aes256Gcm::new().map_err(|_| Error::UnsupportedCipher)
+}
+
+fn encrypt(
+    cipher: KeystoreCipher,
+    key: &[u8; KEY_LEN],
+    ad: &[u8],
+    plaintext: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    match cipher {
+        KeystoreCipher::Aes256Gcm => {
+            let aes = aes()?;
+            let nonce = aes.gen_initial_nonce();
+            let key = aes256gcm::Key(*key);
+            let ciphertext = aes.seal(plaintext, Some(ad), &nonce, &key);
+            Ok((nonce.0.to_vec(), ciphertext))
+        }
+        KeystoreCipher::XChaCha20Poly1305 => {
+            let nonce = xchacha20poly1305_ietf::gen_nonce();
+            let key = xchacha20poly1305_ietf::Key(*key);
+            let ciphertext = xchacha20poly1305_ietf::seal(plaintext, Some(ad), &nonce, &key);
+            Ok((nonce.0.to_vec(), ciphertext))
+        }
+    }
+}
+
+fn decrypt(
+    cipher: KeystoreCipher,
+    key: &[u8; KEY_LEN],
+    ad: &[u8],
+    nonce: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    match cipher {
+        KeystoreCipher::Aes256Gcm => {
+            let aes = aes()?;
+            let nonce = aes256gcm::Nonce::from_slice(nonce).ok_or(Error::InvalidEncoding)?;
+            aes.open(ciphertext, Some(ad), &nonce, &aes256gcm::Key(*key))
+        }
+        KeystoreCipher::XChaCha20Poly1305 => {
+            let nonce =
+                xchacha20poly1305_ietf::Nonce::from_slice(nonce).ok_or(Error::InvalidEncoding)?;
+            let key = xchacha20poly1305_ietf::Key(*key);
+            xchacha20poly1305_ietf::open(ciphertext, Some(ad), &nonce, &key)
+        }
+    }
+    .map_err(|_| Error::DecryptionFailed)
+}
+
+impl KeyPair {
+    /// Encrypt this key pair under `password` with the default Argon2id cost.
+    pub fn to_keystore(&self, password: &str) -> Result<KeystoreJson, Error> {
+        self.to_keystore_with(
+            password,
+            KeystoreParams::default(),
+            KeystoreCipher::preferred(),
+        )
+    }
+
+    pub fn to_keystore_with(
+        &self,
+        password: &str,
+        params: KeystoreParams,
+        cipher: KeystoreCipher,
+    ) -> Result<KeystoreJson, Error> {
+        params.check()?;
+        let kdf = KeystoreKdf::Argon2id {
+            salt: argon2id13::gen_salt().0.to_vec(),
+            opslimit: params.opslimit,
+            memlimit: params.memlimit,
+        };
+        let mut key = derive_key(password, &kdf)?;
+        let ad = associated_data(KEYSTORE_VERSION, self.pubkey());
+        let sealed = encrypt(cipher, &key, &ad, &self.privkey().0[..32]);
+        memzero(&mut key);
+        let (nonce, ciphertext) = sealed?;
+        Ok(KeystoreJson {
+            version: KEYSTORE_VERSION,
+            address: self.address(),
+            pubkey: *self.pubkey(),
+            kdf,
+            cipher,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// Decrypt `keystore` with `password`. A wrong password or any edit to
+    /// the file gives `DecryptionFailed`.
+    pub fn from_keystore(keystore: &KeystoreJson, password: &str) -> Result<KeyPair, Error> {
+        if keystore.version != KEYSTORE_VERSION {
+            return Err(Error::UnsupportedVersion);
+        }
+        let mut key = derive_key(password, &keystore.kdf)?;
+        let ad = associated_data(keystore.version, &keystore.pubkey);
+        let opened = decrypt(
+            keystore.cipher,
+            &key,
+            &ad,
+            &keystore.nonce,
+            &keystore.ciphertext,
+        );
+        memzero(&mut key);
+        let mut seed = opened?;
+        let keypair = if seed.len() == 32 {
+            let mut bytes = [0u8; 32];
+            bytes.copy_from_slice(&seed);
+            let keypair = KeyPair::from_seed(&bytes);
+            memzero(&mut bytes);
+            Ok(keypair)
+        } else {
+            Err(Error::DecryptionFailed)
+        };
+        memzero(&mut seed);
+
+        let keypair = keypair?;
+        if keypair.pubkey() != &keystore.pubkey || keypair.address() != keystore.address {
+            return Err(Error::DecryptionFailed);
+        }
+        Ok(keypair)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keystore_roundtrip() {
+        let keypair = KeyPair::gen_keypair();
+        let params = KeystoreParams::interactive();
+        let cipher = KeystoreCipher::XChaCha20Poly1305;
+        let keystore = keypair.to_keystore_with("hunter2", params, cipher).unwrap();
+        let json = serde_json::to_string(&keystore).unwrap();
+        let keystore: KeystoreJson = serde_json::from_str(&json).unwrap();
+
+        let restored = KeyPair::from_keystore(&keystore, "hunter2").unwrap();
+        assert_eq!(restored.privkey(), keypair.privkey());
+        assert!(matches!(
+            KeyPair::from_keystore(&keystore, "hunter3"),
+            Err(Error::DecryptionFailed)
+        ));
+
+        let mut swapped = keystore.clone();
+        swapped.pubkey = *KeyPair::gen_keypair().pubkey();
+        assert!(KeyPair::from_keystore(&swapped, "hunter2").is_err());
+        let mut costly = keystore.clone();
+        let KeystoreKdf::Argon2id { memlimit, .. } = &mut costly.kdf;
+        *memlimit = u64::MAX;
+        assert!(matches!(
+            KeyPair::from_keystore(&costly, "hunter2"),
+            Err(Error::InvalidKdfParams)
+        ));
+        let mut cheap = keystore.clone();
+        let KeystoreKdf::Argon2id { opslimit, .. } = &mut cheap.kdf;
+        *opslimit = 1;
+        assert!(matches!(
+            KeyPair::from_keystore(&cheap, "hunter2"),
+            Err(Error::InvalidKdfParams)
+        ));
+        let mut cheap = keystore.clone();
+        let KeystoreKdf::Argon2id { memlimit, .. } = &mut cheap.kdf;
+        *memlimit = 8192;
+        assert!(matches!(
+            KeyPair::from_keystore(&cheap, "hunter2"),
+            Err(Error::InvalidKdfParams)
+        ));
+        let mut newer = keystore;
+        newer.version += 1;
+        assert!(matches!(
+            KeyPair::from_keystore(&newer, "hunter2"),
+            Err(Error::UnsupportedVersion)
+        ));
+    }
+
+    #[test]
+    fn test_keystore_aes_gcm() {
+        if KeystoreCipher::preferred() != KeystoreCipher::Aes256Gcm {
+            return;
+        }
+        let keypair = KeyPair::gen_keypair();
+        let params = KeystoreParams::interactive();
+        let keystore = keypair
+            .to_keystore_with("pw", params, KeystoreCipher::Aes256Gcm)
+            .unwrap();
+        assert!(serde_json::to_string(&keystore)
+            .unwrap()
+            .contains("aes-256-gcm"));
+        let restored = KeyPair::from_keystore(&keystore, "pw").unwrap();
+        assert_eq!(restored.pubkey(), keypair.pubkey());
+    }
+}
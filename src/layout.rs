@@ -0,0 +1,144 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Error, PubKey, Signature, PUBKEY_BYTES_LEN, SIGNATURE_BYTES_LEN};
+use std::convert::TryFrom;
+
+const SIG_LEN: usize = SIGNATURE_BYTES_LEN - PUBKEY_BYTES_LEN;
+
+/// Length of a signature sent as a big-endian `u16` validator index
+/// followed by the 64-byte signature.
+pub const INDEXED_SIGNATURE_LEN: usize = 2 + SIG_LEN;
+
+/// Byte order of a signature in a foreign format.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureLayout {
+    /// `sig || pk`, this crate's own layout.
+    #[default]
+    SigPk,
+    /// `pk || sig`.
+    PkSig,
+    /// The 64-byte signature alone; the public key travels separately.
+    SigOnly,
+}
+
+impl SignatureLayout {
+    pub fn encoded_len(self) -> usize {
+        match self {
+            SignatureLayout::SigPk | SignatureLayout::PkSig => SIGNATURE_BYTES_LEN,
+            SignatureLayout::SigOnly => SIG_LEN,
+        }
+    }
+}
+
+impl Signature {
+    /// Parse `bytes` laid out as `layout`. `pubkey` is required for
+    /// [`SignatureLayout::SigOnly`] and ignored otherwise.
+    pub fn from_bytes_with(
+        bytes: &[u8],
+        layout: SignatureLayout,
+        pubkey: Option<&PubKey>,
+    ) -> Result<Signature, Error> {
+        if bytes.len() != layout.encoded_len() {
+            return Err(Error::InvalidSignature);
+        }
+        let mut ret = [0u8; SIGNATURE_BYTES_LEN];
+        match layout {
+            SignatureLayout::SigPk => ret.copy_from_slice(bytes),
+            SignatureLayout::PkSig => {
+                ret[..SIG_LEN].copy_from_slice(&bytes[PUBKEY_BYTES_LEN..]);
+                ret[SIG_LEN..].copy_from_slice(&bytes[..PUBKEY_BYTES_LEN]);
+            }
+            SignatureLayout::SigOnly => {
+                let pubkey = pubkey.ok_or(Error::InvalidPubKey)?;
+                ret[..SIG_LEN].copy_from_slice(bytes);
+                ret[SIG_LEN..].copy_from_slice(pubkey.as_ref());
+            }
+        }
+        Ok(Signature(ret))
+    }
+
+    /// Replace the embedded public key with its position in `validators`,
+    /// an ordering both sides have agreed on.
+    pub fn to_indexed(&self, validators: &[PubKey]) -> Result<[u8; INDEXED_SIGNATURE_LEN], Error> {
+        let index = validators
+            .iter()
+            .position(|pubkey| pubkey.as_ref() == self.pk())
+            .and_then(|index| u16::try_from(index).ok())
+            .ok_or(Error::InvalidPubKey)?;
+        let mut ret = [0u8; INDEXED_SIGNATURE_LEN];
+        ret[..2].copy_from_slice(&index.to_be_bytes());
+        ret[2..].copy_from_slice(self.sig());
+        Ok(ret)
+    }
+
+    /// Rebuild the 96-byte form from [`Signature::to_indexed`] output.
+    pub fn from_indexed(bytes: &[u8], validators: &[PubKey]) -> Result<Signature, Error> {
+        if bytes.len() != INDEXED_SIGNATURE_LEN {
+            return Err(Error::InvalidSignature);
+        }
+        let index = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+        let pubkey = validators.get(index).ok_or(Error::InvalidPubKey)?;
+        Signature::from_bytes_with(&bytes[2..], SignatureLayout::SigOnly, Some(pubkey))
+    }
+
+    pub fn to_bytes_with(&self, layout: SignatureLayout) -> Vec<u8> {
+        match layout {
+            SignatureLayout::SigPk => self.0.to_vec(),
+            SignatureLayout::PkSig => [self.pk(), self.sig()].concat(),
+            SignatureLayout::SigOnly => self.sig().to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeyPair, Message};
+    use cita_crypto_trait::{CreateKey, Sign};
+
+    #[test]
+    fn test_signature_layouts() {
+        let keypair = KeyPair::gen_keypair();
+        let sig = Signature::sign(keypair.privkey(), &Message::from_low_u64_be(4)).unwrap();
+        for layout in [
+            SignatureLayout::SigPk,
+            SignatureLayout::PkSig,
+            SignatureLayout::SigOnly,
+        ] {
+            let bytes = sig.to_bytes_with(layout);
+            assert_eq!(bytes.len(), layout.encoded_len());
+            let parsed = Signature::from_bytes_with(&bytes, layout, Some(keypair.pubkey()));
+            assert_eq!(parsed.unwrap(), sig);
+        }
+        assert_eq!(
+            &sig.to_bytes_with(SignatureLayout::PkSig)[..32],
+            keypair.pubkey().as_ref() as &[u8]
+        );
+        assert!(Signature::from_bytes_with(sig.sig(), SignatureLayout::SigOnly, None).is_err());
+    }
+
+    #[test]
+    fn test_indexed_signature() {
+        let keys: Vec<KeyPair> = (0..4).map(|_| KeyPair::gen_keypair()).collect();
+        let validators: Vec<PubKey> = keys.iter().map(|k| *k.pubkey()).collect();
+        let sig = Signature::sign(keys[2].privkey(), &Message::from_low_u64_be(4)).unwrap();
+
+        let indexed = sig.to_indexed(&validators).unwrap();
+        assert_eq!(&indexed[..2], &[0, 2]);
+        assert_eq!(Signature::from_indexed(&indexed, &validators).unwrap(), sig);
+        assert!(sig.to_indexed(&validators[..2]).is_err());
+        assert!(Signature::from_indexed(&indexed, &validators[..2]).is_err());
+    }
+}
@@ -12,10 +12,84 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+// Lets `#[derive(Signable)]` output name `::cita_ed25519` inside this crate too.
+extern crate self as cita_ed25519;
+
+mod account;
+mod address;
+mod aggregate;
+#[cfg(feature = "rkyv")]
+mod archive;
+mod attestation;
+mod audit;
+mod batch;
+mod bech32;
+mod cache;
+mod canonical;
+mod ceremony;
+mod clock;
+mod config;
+mod cosign;
+mod countersign;
+mod ct;
+mod curve;
+mod derive;
 mod error;
+mod escrow;
+mod evidence;
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+mod governance;
+mod handoff;
+mod hasher;
+#[cfg(feature = "fips-like")]
+mod health;
+mod hex;
+#[cfg(feature = "pq-hybrid")]
+mod hybrid;
+mod inspect;
 mod keypair;
+mod keystore;
+mod layout;
+mod light;
+mod limits;
+mod material;
+mod meter;
+mod migration;
+mod mnemonic;
+mod multisig;
+mod offline;
+mod pool;
+mod precompile;
+mod quorum;
+mod recovery;
+mod request;
+mod sealed;
+mod selftest;
+mod serde_array;
+pub mod serde_flexible;
+mod serde_hex;
+mod session;
+mod shamir;
+mod signable;
 mod signature;
+mod signed_message;
 mod signer;
+mod signer_set;
+mod sortition;
+#[cfg(unix)]
+mod ssh_agent;
+mod stream;
+mod suite;
+mod testnet;
+mod timelock;
+#[cfg(feature = "tracing")]
+mod trace;
+mod vanity;
+mod verified;
+mod watch;
+mod watermark;
+mod wire;
 
 use cita_types::{Address, H256, H512};
 
@@ -29,7 +103,72 @@ pub type PrivKey = H512;
 pub type PubKey = H256;
 pub type Message = H256;
 
+pub use self::account::*;
+pub use self::address::*;
+pub use self::aggregate::*;
+#[cfg(feature = "rkyv")]
+pub use self::archive::*;
+pub use self::attestation::*;
+pub use self::audit::*;
+pub use self::batch::*;
+pub use self::bech32::*;
+pub use self::cache::*;
+pub use self::canonical::*;
+pub use self::ceremony::*;
+pub use self::config::*;
+pub use self::cosign::*;
+pub use self::countersign::*;
+pub use self::ct::*;
+pub use self::derive::*;
 pub use self::error::*;
+pub use self::escrow::*;
+pub use self::evidence::*;
+#[cfg(feature = "arbitrary")]
+pub use self::fuzz::*;
+pub use self::governance::*;
+pub use self::handoff::*;
+pub use self::hasher::*;
+#[cfg(feature = "fips-like")]
+pub use self::health::*;
+#[cfg(feature = "pq-hybrid")]
+pub use self::hybrid::*;
+pub use self::inspect::*;
 pub use self::keypair::*;
+pub use self::keystore::*;
+pub use self::layout::*;
+pub use self::light::*;
+pub use self::limits::*;
+pub use self::material::*;
+pub use self::meter::*;
+pub use self::migration::*;
+pub use self::mnemonic::*;
+pub use self::multisig::*;
+pub use self::offline::*;
+pub use self::pool::*;
+pub use self::precompile::*;
+pub use self::quorum::*;
+pub use self::recovery::*;
+pub use self::request::*;
+pub use self::sealed::*;
+pub use self::selftest::*;
+pub use self::session::*;
+pub use self::shamir::*;
+pub use self::signable::*;
 pub use self::signature::*;
+pub use self::signed_message::*;
 pub use self::signer::*;
+pub use self::signer_set::*;
+pub use self::sortition::*;
+#[cfg(unix)]
+pub use self::ssh_agent::*;
+pub use self::stream::*;
+pub use self::suite::*;
+pub use self::testnet::*;
+pub use self::timelock::*;
+#[cfg(feature = "tracing")]
+pub use self::trace::*;
+pub use self::vanity::*;
+pub use self::verified::*;
+pub use self::watch::*;
+pub use self::watermark::*;
+pub use self::wire::*;
@@ -0,0 +1,283 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validator-set checkpoints for bridges and light clients.
+//!
+//! Each epoch's validators sign a [`SetCheckpoint`] naming the set for the
+//! next epoch. A [`LightVerifier`] starts from one trusted set and follows
+//! the chain of checkpoints, accepting a new set only when validators of the
+//! current one holding more than two thirds of its weight signed it.
+
+use super::{verify_quorum, Error, KeyPair, Message, PubKey, Signature};
+use crate::hasher::hash_data;
+use cita_crypto_trait::{CreateKey, Sign};
+use cita_types::H256;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+const SET_DOMAIN: &[u8] = b"cita-ed25519 validator set";
+const CHECKPOINT_DOMAIN: &[u8] = b"cita-ed25519 validator set checkpoint";
+
+#[derive(Serialize, Deserialize)]
+struct ValidatorSetFields {
+    validators: Vec<(PubKey, u64)>,
+}
+
+/// Validators and their voting weights.
+///
+/// Deserializing goes through [`ValidatorSet::new`], so every set has been
+/// validated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "ValidatorSetFields", into = "ValidatorSetFields")]
+pub struct ValidatorSet {
+    validators: Vec<(PubKey, u64)>,
+}
+
+impl ValidatorSet {
+    /// Sorts by key; fails on an empty set, a repeated key, zero weight or a
+    /// total weight that overflows `u64`.
+    pub fn new(mut validators: Vec<(PubKey, u64)>) -> Result<Self, Error> {
+        validators.sort();
+        if validators.is_empty()
+            || validators.windows(2).any(|w| w[0].0 == w[1].0)
+            || validators.iter().any(|(_, weight)| *weight == 0)
+            || validators
+                .iter()
+                .try_fold(0u64, |acc, (_, weight)| acc.checked_add(*weight))
+                .is_none()
+        {
+            return Err(Error::InvalidThreshold);
+        }
+        Ok(ValidatorSet { validators })
+    }
+
+    /// Validators with their weights, sorted by key.
+    pub fn validators(&self) -> &[(PubKey, u64)] {
+        &self.validators
+    }
+
+    pub fn hash(&self) -> H256 {
+        let mut s = RlpStream::new_list(1 + self.validators.len());
+        s.append(&SET_DOMAIN);
+        for (pubkey, weight) in &self.validators {
+            s.begin_list(2);
+            s.append(pubkey);
+            s.append(weight);
+        }
+        hash_data(&s.out())
+    }
+
+    pub fn total_weight(&self) -> u64 {
+        self.validators
+            .iter()
+            .fold(0u64, |acc, (_, weight)| acc.saturating_add(*weight))
+    }
+
+    /// The smallest weight above two thirds of the total.
+    pub fn quorum(&self) -> u64 {
+        (self.total_weight() as u128 * 2 / 3 + 1) as u64
+    }
+}
+
+impl TryFrom<ValidatorSetFields> for ValidatorSet {
+    type Error = Error;
+
+    fn try_from(fields: ValidatorSetFields) -> Result<Self, Self::Error> {
+        ValidatorSet::new(fields.validators)
+    }
+}
+
+impl From<ValidatorSet> for ValidatorSetFields {
+    fn from(set: ValidatorSet) -> Self {
+        ValidatorSetFields {
+            validators: set.validators,
+        }
+    }
+}
+
+/// The set hash for `epoch`, signed by the validators of `epoch - 1`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetCheckpoint {
+    pub epoch: u64,
+    pub set_hash: H256,
+    pub signatures: Vec<Signature>,
+}
+
+impl SetCheckpoint {
+    pub fn new(epoch: u64, next: &ValidatorSet) -> Self {
+        SetCheckpoint {
+            epoch,
+            set_hash: next.hash(),
+            signatures: Vec::new(),
+        }
+    }
+
+    pub fn signing_hash(&self) -> Message {
+        let mut s = RlpStream::new_list(3);
+        s.append(&CHECKPOINT_DOMAIN);
+        s.append(&self.epoch);
+        s.append(&self.set_hash);
+        hash_data(&s.out())
+    }
+
+    /// Add `keypair`'s signature.
+    pub fn sign(&mut self, keypair: &KeyPair) -> Result<(), Error> {
+        let signature = Signature::sign(keypair.privkey(), &self.signing_hash())?;
+        self.signatures.push(signature);
+        Ok(())
+    }
+}
+
+impl Encodable for SetCheckpoint {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3);
+        s.append(&self.epoch);
+        s.append(&self.set_hash);
+        Signature::rlp_append_list(&self.signatures, s);
+    }
+}
+
+impl Decodable for SetCheckpoint {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 3 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(SetCheckpoint {
+            epoch: rlp.val_at(0)?,
+            set_hash: rlp.val_at(1)?,
+            signatures: rlp.list_at(2)?,
+        })
+    }
+}
+
+/// Trusted validator sets by epoch, extended one verified checkpoint at a
+/// time.
+#[derive(Debug, Clone)]
+pub struct LightVerifier {
+    sets: BTreeMap<u64, ValidatorSet>,
+}
+
+impl LightVerifier {
+    /// Trust `set` for `epoch` without proof, e.g. from genesis.
+    pub fn new(epoch: u64, set: ValidatorSet) -> Self {
+        let mut sets = BTreeMap::new();
+        sets.insert(epoch, set);
+        LightVerifier { sets }
+    }
+
+    pub fn latest_epoch(&self) -> u64 {
+        *self.sets.keys().next_back().unwrap()
+    }
+
+    pub fn latest(&self) -> &ValidatorSet {
+        self.sets.values().next_back().unwrap()
+    }
+
+    pub fn set_at(&self, epoch: u64) -> Option<&ValidatorSet> {
+        self.sets.get(&epoch)
+    }
+
+    /// Check that `checkpoint` hands over from the latest trusted set to
+    /// `next`, and trust `next` from then on.
+    pub fn verify_transition(
+        &mut self,
+        checkpoint: &SetCheckpoint,
+        next: ValidatorSet,
+    ) -> Result<(), Error> {
+        if checkpoint.epoch != self.latest_epoch().wrapping_add(1) || checkpoint.epoch == 0 {
+            return Err(Error::InvalidEpoch);
+        }
+        if checkpoint.set_hash != next.hash() {
+            return Err(Error::InvalidMessage);
+        }
+        let current = self.latest();
+        let result = verify_quorum(
+            current.validators(),
+            current.quorum(),
+            &checkpoint.signing_hash(),
+            &checkpoint.signatures,
+        );
+        if !result.met {
+            return Err(Error::InvalidThreshold);
+        }
+        self.sets.insert(checkpoint.epoch, next);
+        Ok(())
+    }
+
+    /// Forget every set before `epoch`, keeping at least the latest.
+    pub fn prune_before(&mut self, epoch: u64) {
+        let epoch = epoch.min(self.latest_epoch());
+        self.sets = self.sets.split_off(&epoch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validator_set(keys: &[KeyPair]) -> ValidatorSet {
+        ValidatorSet::new(keys.iter().map(|k| (*k.pubkey(), 1)).collect()).unwrap()
+    }
+
+    #[test]
+    fn test_light_verifier() {
+        let keys: Vec<KeyPair> = (0..6).map(|_| KeyPair::gen_keypair()).collect();
+        let genesis = validator_set(&keys[..4]);
+        assert_eq!(genesis.quorum(), 3);
+        let mut verifier = LightVerifier::new(0, genesis);
+
+        let next = validator_set(&keys[2..]);
+        let mut checkpoint = SetCheckpoint::new(1, &next);
+        for key in &keys[..2] {
+            checkpoint.sign(key).unwrap();
+        }
+        // Key 4 is only in the next set, so its signature does not count.
+        checkpoint.sign(&keys[4]).unwrap();
+        assert!(verifier
+            .verify_transition(&checkpoint, next.clone())
+            .is_err());
+
+        checkpoint.sign(&keys[3]).unwrap();
+        let checkpoint: SetCheckpoint = rlp::decode(&rlp::encode(&checkpoint)).unwrap();
+        assert!(verifier
+            .verify_transition(&checkpoint, validator_set(&keys[1..5]))
+            .is_err());
+        verifier
+            .verify_transition(&checkpoint, next.clone())
+            .unwrap();
+        assert_eq!(verifier.latest_epoch(), 1);
+        assert_eq!(verifier.latest(), &next);
+        // The same checkpoint cannot be applied twice.
+        assert!(verifier.verify_transition(&checkpoint, next).is_err());
+
+        verifier.prune_before(5);
+        assert!(verifier.set_at(0).is_none());
+        assert_eq!(verifier.latest_epoch(), 1);
+    }
+
+    #[test]
+    fn test_validator_set_deserialize_validates() {
+        let keys: Vec<KeyPair> = (0..2).map(|_| KeyPair::gen_keypair()).collect();
+        let set = validator_set(&keys);
+        let json = serde_json::to_string(&set).unwrap();
+        assert_eq!(serde_json::from_str::<ValidatorSet>(&json).unwrap(), set);
+
+        let pubkey = serde_json::to_string(keys[0].pubkey()).unwrap();
+        let duplicated = format!("{{\"validators\":[[{0},1],[{0},1]]}}", pubkey);
+        assert!(serde_json::from_str::<ValidatorSet>(&duplicated).is_err());
+        assert!(serde_json::from_str::<ValidatorSet>("{\"validators\":[]}").is_err());
+    }
+}
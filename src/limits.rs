@@ -0,0 +1,233 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Size limits for input from untrusted peers.
+//!
+//! [`verify_batch`], [`recover_addresses`], [`seal`] and [`open_sealed`]
+//! apply [`Limits::default`] on their own; each has an `_unbounded` variant
+//! for input the caller has already bounded. Other bounds go through a
+//! [`Limits`] value, which rejects oversized input before doing any work on
+//! it.
+
+#[cfg(doc)]
+use super::{open_sealed, recover_addresses, seal, verify_batch};
+use super::{
+    open_sealed_unbounded, recover_addresses_unbounded, seal_unbounded, verify_batch_unbounded,
+    Address, BatchItem, Error, KeyPair, Message, PrivKey, PubKey, Signature, SigningWriter,
+    VerifyingReader, SEAL_OVERHEAD,
+};
+use std::fmt;
+use std::io::{self, Read};
+
+/// Which limit was hit, and by how much.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    MessageLen { len: u64, max: u64 },
+    BatchLen { len: u64, max: u64 },
+    EnvelopeLen { len: u64, max: u64 },
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (what, len, max) = match *self {
+            LimitExceeded::MessageLen { len, max } => ("Message", len, max),
+            LimitExceeded::BatchLen { len, max } => ("Batch", len, max),
+            LimitExceeded::EnvelopeLen { len, max } => ("Envelope", len, max),
+        };
+        write!(f, "{} Too Large: {} > {}", what, len, max)
+    }
+}
+
+/// Bounds applied before any cryptographic work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Longest raw message accepted by the byte-signing APIs.
+    pub max_message_len: u64,
+    /// Most signatures in one batch call.
+    pub max_batch_len: usize,
+    /// Longest sealed envelope, overhead included.
+    pub max_envelope_len: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_message_len: 16 << 20,
+            max_batch_len: 10_000,
+            max_envelope_len: 64 << 10,
+        }
+    }
+}
+
+impl Limits {
+    pub fn check_message_len(&self, len: u64) -> Result<(), Error> {
+        if len > self.max_message_len {
+            return Err(Error::LimitExceeded(LimitExceeded::MessageLen {
+                len,
+                max: self.max_message_len,
+            }));
+        }
+        Ok(())
+    }
+
+    pub fn check_batch_len(&self, len: usize) -> Result<(), Error> {
+        if len > self.max_batch_len {
+            return Err(Error::LimitExceeded(LimitExceeded::BatchLen {
+                len: len as u64,
+                max: self.max_batch_len as u64,
+            }));
+        }
+        Ok(())
+    }
+
+    pub fn check_envelope_len(&self, len: usize) -> Result<(), Error> {
+        if len > self.max_envelope_len {
+            return Err(Error::LimitExceeded(LimitExceeded::EnvelopeLen {
+                len: len as u64,
+                max: self.max_envelope_len as u64,
+            }));
+        }
+        Ok(())
+    }
+
+    /// [`verify_batch`] for at most `max_batch_len` items.
+    pub fn verify_batch(&self, items: &[BatchItem]) -> Result<Vec<Result<(), Error>>, Error> {
+        self.check_batch_len(items.len())?;
+        Ok(verify_batch_unbounded(items))
+    }
+
+    /// [`recover_addresses`] for at most `max_batch_len` items.
+    pub fn recover_addresses(
+        &self,
+        items: &[(Message, Signature)],
+    ) -> Result<Vec<Result<Address, Error>>, Error> {
+        self.check_batch_len(items.len())?;
+        Ok(recover_addresses_unbounded(items))
+    }
+
+    /// [`seal`], refusing plaintexts whose envelope would exceed
+    /// `max_envelope_len`.
+    pub fn seal(&self, pubkey: &PubKey, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        self.check_envelope_len(plaintext.len().saturating_add(SEAL_OVERHEAD))?;
+        seal_unbounded(pubkey, plaintext)
+    }
+
+    pub fn open_sealed(&self, keypair: &KeyPair, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        self.check_envelope_len(ciphertext.len())?;
+        open_sealed_unbounded(keypair, ciphertext)
+    }
+
+    /// Sign everything `reader` yields, as [`SigningWriter`] does, reading
+    /// no more than one byte past `max_message_len`.
+    pub fn sign_reader<R: Read>(&self, reader: R, privkey: &PrivKey) -> Result<Signature, Error> {
+        let mut writer = SigningWriter::new(io::sink(), privkey);
+        let len = io::copy(
+            &mut reader.take(self.max_message_len.saturating_add(1)),
+            &mut writer,
+        )?;
+        self.check_message_len(len)?;
+        Ok(writer.finish()?.1)
+    }
+
+    /// Check `signature` over everything `reader` yields, as
+    /// [`VerifyingReader`] does, reading no more than one byte past
+    /// `max_message_len`.
+    pub fn verify_reader<R: Read>(
+        &self,
+        reader: R,
+        pubkey: PubKey,
+        signature: &Signature,
+    ) -> Result<(), Error> {
+        let mut reader =
+            VerifyingReader::new(reader.take(self.max_message_len.saturating_add(1)), pubkey);
+        let len = io::copy(&mut reader, &mut io::sink())?;
+        self.check_message_len(len)?;
+        reader.finish(signature).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cita_crypto_trait::CreateKey;
+
+    #[test]
+    fn test_limits() {
+        let keypair = KeyPair::gen_keypair();
+        let limits = Limits {
+            max_message_len: 100,
+            max_batch_len: 2,
+            max_envelope_len: 40 + SEAL_OVERHEAD,
+        };
+        let data = [7u8; 101];
+
+        let signature = limits.sign_reader(&data[..100], keypair.privkey()).unwrap();
+        limits
+            .verify_reader(&data[..100], *keypair.pubkey(), &signature)
+            .unwrap();
+        assert!(matches!(
+            limits.sign_reader(&data[..], keypair.privkey()),
+            Err(Error::LimitExceeded(LimitExceeded::MessageLen {
+                len: 101,
+                max: 100
+            }))
+        ));
+        assert!(limits
+            .verify_reader(&data[..], *keypair.pubkey(), &signature)
+            .is_err());
+
+        let items = vec![(Message::zero(), signature.clone()); 3];
+        let e = limits.recover_addresses(&items).unwrap_err();
+        assert_eq!(e.to_string(), "Crypto error: Batch Too Large: 3 > 2");
+        assert!(limits.recover_addresses(&items[..2]).is_ok());
+
+        let sealed = limits.seal(keypair.pubkey(), &data[..40]).unwrap();
+        assert_eq!(limits.open_sealed(&keypair, &sealed).unwrap(), &data[..40]);
+        assert!(limits.seal(keypair.pubkey(), &data[..41]).is_err());
+
+        let unlimited = Limits {
+            max_message_len: u64::MAX,
+            ..limits
+        };
+        let signature = unlimited.sign_reader(&data[..], keypair.privkey()).unwrap();
+        unlimited
+            .verify_reader(&data[..], *keypair.pubkey(), &signature)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_default_limits_apply_to_plain_functions() {
+        use crate::{open_sealed, recover_addresses, seal};
+
+        let keypair = KeyPair::gen_keypair();
+        let defaults = Limits::default();
+        // Invalid signatures keep the unbounded run cheap; what matters is
+        // that it gets past the limit.
+        let items = vec![(Message::zero(), Signature::default()); defaults.max_batch_len + 1];
+        let results = recover_addresses(&items);
+        assert_eq!(results.len(), items.len());
+        assert!(results
+            .iter()
+            .all(|r| matches!(r, Err(Error::LimitExceeded(LimitExceeded::BatchLen { .. })))));
+        assert!(recover_addresses_unbounded(&items)
+            .iter()
+            .all(|r| !matches!(r, Err(Error::LimitExceeded(_)))));
+
+        let big = vec![0u8; defaults.max_envelope_len];
+        assert!(seal(keypair.pubkey(), &big).is_err());
+        let sealed = seal_unbounded(keypair.pubkey(), &big).unwrap();
+        assert!(open_sealed(&keypair, &sealed).is_err());
+        assert_eq!(open_sealed_unbounded(&keypair, &sealed).unwrap(), big);
+    }
+}
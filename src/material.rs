@@ -0,0 +1,124 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Error, KeyPair, PrivKey};
+use cita_crypto_trait::CreateKey;
+use sodiumoxide::utils::{memcmp, memzero};
+use std::fmt;
+
+/// A private key in any of the forms callers hold one in.
+///
+/// `PrivKey` is libsodium's 64-byte secret key, the 32-byte seed followed by
+/// the public key; most other Ed25519 libraries pass the seed alone.
+pub enum SecretKeyMaterial {
+    /// The 32-byte RFC 8032 seed.
+    Seed32([u8; 32]),
+    /// The seed followed by its public key, as stored in [`PrivKey`].
+    Expanded64(PrivKey),
+    /// A key that never leaves a hardware device, named by its handle.
+    HardwareHandle(String),
+}
+
+impl SecretKeyMaterial {
+    /// The 32-byte seed, unless the key lives in hardware.
+    pub fn seed(&self) -> Option<[u8; 32]> {
+        match self {
+            SecretKeyMaterial::Seed32(seed) => Some(*seed),
+            SecretKeyMaterial::Expanded64(privkey) => {
+                let mut seed = [0u8; 32];
+                seed.copy_from_slice(&privkey.0[..32]);
+                Some(seed)
+            }
+            SecretKeyMaterial::HardwareHandle(_) => None,
+        }
+    }
+
+    /// Build the software key pair; fails for hardware handles and for a
+    /// 64-byte key whose public half does not match its seed.
+    pub fn to_keypair(&self) -> Result<KeyPair, Error> {
+        let mut seed = self.seed().ok_or(Error::InvalidPrivKey)?;
+        let keypair = KeyPair::from_seed(&seed);
+        memzero(&mut seed);
+        if let SecretKeyMaterial::Expanded64(privkey) = self {
+            if !memcmp(privkey.as_ref(), keypair.privkey().as_ref()) {
+                return Err(Error::InvalidPrivKey);
+            }
+        }
+        Ok(keypair)
+    }
+}
+
+impl From<[u8; 32]> for SecretKeyMaterial {
+    fn from(seed: [u8; 32]) -> Self {
+        SecretKeyMaterial::Seed32(seed)
+    }
+}
+
+impl From<PrivKey> for SecretKeyMaterial {
+    fn from(privkey: PrivKey) -> Self {
+        SecretKeyMaterial::Expanded64(privkey)
+    }
+}
+
+impl Drop for SecretKeyMaterial {
+    fn drop(&mut self) {
+        match self {
+            SecretKeyMaterial::Seed32(seed) => memzero(seed),
+            SecretKeyMaterial::Expanded64(privkey) => memzero(&mut privkey.0),
+            SecretKeyMaterial::HardwareHandle(_) => {}
+        }
+    }
+}
+
+impl fmt::Debug for SecretKeyMaterial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SecretKeyMaterial::Seed32(_) => f.write_str("Seed32(..)"),
+            SecretKeyMaterial::Expanded64(_) => f.write_str("Expanded64(..)"),
+            SecretKeyMaterial::HardwareHandle(handle) => {
+                f.debug_tuple("HardwareHandle").field(handle).finish()
+            }
+        }
+    }
+}
+
+impl KeyPair {
+    pub fn from_material(material: &SecretKeyMaterial) -> Result<Self, Error> {
+        material.to_keypair()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_key_material() {
+        let keypair = KeyPair::gen_keypair();
+        let seed = SecretKeyMaterial::Expanded64(*keypair.privkey())
+            .seed()
+            .unwrap();
+        let from_seed = KeyPair::from_material(&SecretKeyMaterial::from(seed)).unwrap();
+        let from_privkey = KeyPair::from_material(&(*keypair.privkey()).into()).unwrap();
+        assert_eq!(from_seed.pubkey(), keypair.pubkey());
+        assert_eq!(from_privkey.pubkey(), keypair.pubkey());
+
+        let mut mismatched = *keypair.privkey();
+        mismatched.0[63] ^= 1;
+        assert!(KeyPair::from_material(&mismatched.into()).is_err());
+        let hsm = SecretKeyMaterial::HardwareHandle("slot-1".to_owned());
+        assert!(KeyPair::from_material(&hsm).is_err());
+        assert_eq!(format!("{:?}", SecretKeyMaterial::from(seed)), "Seed32(..)");
+    }
+}
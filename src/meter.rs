@@ -0,0 +1,112 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cost reporting for embedders that charge for cryptographic work, such as
+//! gas-metered precompiles.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+/// A unit of work reported to the meter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// One Ed25519 signature checked, alone or as part of a batch.
+    Verify,
+    /// One Ed25519 signature made.
+    Sign,
+    /// One public key hashed into an address.
+    AddressHash,
+}
+
+type Meter = Box<dyn Fn(Operation, u64) + Send + Sync>;
+
+static METER: RwLock<Option<Meter>> = RwLock::new(None);
+// Lets the hot paths skip the lock while no meter is installed.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Install a callback told how many units of each operation this crate
+/// performs, from whichever thread does the work. Replaces any earlier
+/// callback.
+pub fn set_meter<F>(meter: F)
+where
+    F: Fn(Operation, u64) + Send + Sync + 'static,
+{
+    *METER.write().unwrap() = Some(Box::new(meter));
+    ENABLED.store(true, Ordering::Release);
+}
+
+/// Remove the callback installed with [`set_meter`].
+pub fn clear_meter() {
+    ENABLED.store(false, Ordering::Release);
+    *METER.write().unwrap() = None;
+}
+
+pub(crate) fn charge(operation: Operation, units: u64) {
+    if !ENABLED.load(Ordering::Acquire) {
+        return;
+    }
+    if let Some(meter) = METER.read().unwrap().as_ref() {
+        meter(operation, units);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{verify_batch, BatchItem, KeyPair, Message, Signature};
+    use cita_crypto_trait::{CreateKey, Sign};
+    use std::sync::atomic::AtomicU64;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_meter() {
+        let keypair = KeyPair::gen_keypair();
+        let message = Message::from_low_u64_be(487);
+        let counts: Arc<[AtomicU64; 3]> = Arc::new(Default::default());
+        let seen = counts.clone();
+        // Other tests run alongside; only count work done on this thread.
+        let me = thread::current().id();
+        set_meter(move |operation, units| {
+            if thread::current().id() == me {
+                seen[operation as usize].fetch_add(units, Ordering::SeqCst);
+            }
+        });
+
+        let signature = Signature::sign(keypair.privkey(), &message).unwrap();
+        signature
+            .verify_address(&keypair.address(), &message)
+            .unwrap();
+        let items: Vec<BatchItem> = (0..3)
+            .map(|_| BatchItem {
+                message,
+                signature: signature.clone(),
+                pubkey: *keypair.pubkey(),
+            })
+            .collect();
+        assert!(verify_batch(&items).iter().all(|r| r.is_ok()));
+        // A failing batch is billed once per item, not again on fallback.
+        let mut failing = items;
+        failing[1].signature.0[0] ^= 1;
+        assert!(verify_batch(&failing)[1].is_err());
+        clear_meter();
+        signature.recover(&message).unwrap();
+
+        let count = |operation: Operation| counts[operation as usize].load(Ordering::SeqCst);
+        assert_eq!(count(Operation::Verify), 7);
+        assert_eq!(count(Operation::Sign), 1);
+        // keypair.address() and verify_address each hash once.
+        assert_eq!(count(Operation::AddressHash), 2);
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Moving signed data from one signature suite to another without a flag
+//! day: data carries both signatures plus a statement by the old key that
+//! vouches for the new one, and verifiers accept either signature while a
+//! transition window is open.
+
+use super::{Error, Message, SuiteRegistry, TaggedSignature};
+use crate::hasher::hash_data;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+
+const LINKAGE_DOMAIN: &[u8] = b"cita-ed25519 suite migration";
+
+/// A public key together with the suite it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuiteKey {
+    pub suite_id: u8,
+    pub pubkey: Vec<u8>,
+}
+
+/// The heights during which either signature is accepted. Before `start`
+/// only the old signature counts, after `end` only the new one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransitionWindow {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// What the old key signs to hand over to the new key.
+pub fn linkage_hash(old_key: &SuiteKey, new_key: &SuiteKey) -> Message {
+    let mut s = RlpStream::new_list(5);
+    s.append(&LINKAGE_DOMAIN);
+    s.append(&old_key.suite_id);
+    s.append(&old_key.pubkey);
+    s.append(&new_key.suite_id);
+    s.append(&new_key.pubkey);
+    hash_data(&s.out())
+}
+
+/// A payload signature under both suites.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    pub old_key: SuiteKey,
+    pub new_key: SuiteKey,
+    pub old_signature: TaggedSignature,
+    pub new_signature: TaggedSignature,
+    /// The old key's signature over [`linkage_hash`].
+    pub linkage: TaggedSignature,
+}
+
+impl Migration {
+    fn check(
+        &self,
+        registry: &SuiteRegistry,
+        key: &SuiteKey,
+        message: &Message,
+        sig: &TaggedSignature,
+    ) -> bool {
+        sig.suite_id == key.suite_id && registry.verify(&key.pubkey, message, sig).unwrap_or(false)
+    }
+
+    /// Check the linkage statement and the payload signatures that
+    /// `height` calls for under `window`.
+    pub fn verify(
+        &self,
+        registry: &SuiteRegistry,
+        message: &Message,
+        height: u64,
+        window: &TransitionWindow,
+    ) -> Result<(), Error> {
+        let linkage = linkage_hash(&self.old_key, &self.new_key);
+        if !self.check(registry, &self.old_key, &linkage, &self.linkage) {
+            return Err(Error::InvalidSignature);
+        }
+        let old_ok = || self.check(registry, &self.old_key, message, &self.old_signature);
+        let new_ok = || self.check(registry, &self.new_key, message, &self.new_signature);
+        let valid = if height < window.start {
+            old_ok()
+        } else if height <= window.end {
+            old_ok() || new_ok()
+        } else {
+            new_ok()
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::InvalidSignature)
+        }
+    }
+}
+
+impl Encodable for SuiteKey {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2);
+        s.append(&self.suite_id);
+        s.append(&self.pubkey);
+    }
+}
+
+impl Decodable for SuiteKey {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 2 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(SuiteKey {
+            suite_id: rlp.val_at(0)?,
+            pubkey: rlp.val_at(1)?,
+        })
+    }
+}
+
+impl Encodable for Migration {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(5);
+        s.append(&self.old_key);
+        s.append(&self.new_key);
+        s.append(&self.old_signature.to_bytes());
+        s.append(&self.new_signature.to_bytes());
+        s.append(&self.linkage.to_bytes());
+    }
+}
+
+impl Decodable for Migration {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 5 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        let tagged = |i: usize| -> Result<TaggedSignature, DecoderError> {
+            let bytes: Vec<u8> = rlp.val_at(i)?;
+            TaggedSignature::from_bytes(&bytes)
+                .map_err(|_| DecoderError::Custom("empty tagged signature"))
+        };
+        Ok(Migration {
+            old_key: rlp.val_at(0)?,
+            new_key: rlp.val_at(1)?,
+            old_signature: tagged(2)?,
+            new_signature: tagged(3)?,
+            linkage: tagged(4)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Address, Ed25519Suite, KeyPair, Signature, SignatureSuite, ED25519_SUITE_ID};
+    use cita_crypto_trait::{CreateKey, Sign};
+
+    // A second suite to migrate to, verifying like Ed25519 under another ID.
+    struct NextSuite;
+
+    impl SignatureSuite for NextSuite {
+        fn id(&self) -> u8 {
+            0x42
+        }
+
+        fn name(&self) -> &'static str {
+            "next"
+        }
+
+        fn capabilities(&self) -> crate::Capabilities {
+            Ed25519Suite.capabilities()
+        }
+
+        fn verify(&self, pubkey: &[u8], message: &Message, sig: &[u8]) -> Result<bool, Error> {
+            Ed25519Suite.verify(pubkey, message, sig)
+        }
+
+        fn recover_address(&self, message: &Message, sig: &[u8]) -> Result<Address, Error> {
+            Ed25519Suite.recover_address(message, sig)
+        }
+    }
+
+    fn tagged(suite_id: u8, keypair: &KeyPair, message: &Message) -> TaggedSignature {
+        let signature = Signature::sign(keypair.privkey(), message).unwrap();
+        TaggedSignature {
+            suite_id,
+            signature: signature.0.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_migration_window() {
+        let mut registry = SuiteRegistry::with_builtin();
+        registry.register(Box::new(NextSuite));
+        let (old, new) = (KeyPair::gen_keypair(), KeyPair::gen_keypair());
+        let old_key = SuiteKey {
+            suite_id: ED25519_SUITE_ID,
+            pubkey: old.pubkey().0.to_vec(),
+        };
+        let new_key = SuiteKey {
+            suite_id: 0x42,
+            pubkey: new.pubkey().0.to_vec(),
+        };
+        let msg = Message::from_low_u64_be(8);
+        let linkage = tagged(ED25519_SUITE_ID, &old, &linkage_hash(&old_key, &new_key));
+        let migration = Migration {
+            old_signature: tagged(ED25519_SUITE_ID, &old, &msg),
+            new_signature: tagged(0x42, &new, &Message::from_low_u64_be(9)),
+            old_key,
+            new_key,
+            linkage,
+        };
+        let migration: Migration = rlp::decode(&rlp::encode(&migration)).unwrap();
+        let window = TransitionWindow { start: 10, end: 20 };
+
+        // The new signature is over the wrong message: fine until the window
+        // closes.
+        assert!(migration.verify(&registry, &msg, 5, &window).is_ok());
+        assert!(migration.verify(&registry, &msg, 15, &window).is_ok());
+        assert!(migration.verify(&registry, &msg, 25, &window).is_err());
+
+        let mut fixed = migration;
+        fixed.new_signature = tagged(0x42, &new, &msg);
+        assert!(fixed.verify(&registry, &msg, 25, &window).is_ok());
+        fixed.linkage = tagged(0x42, &new, &msg);
+        assert!(fixed.verify(&registry, &msg, 25, &window).is_err());
+    }
+}
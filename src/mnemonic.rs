@@ -0,0 +1,103 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! BIP-39 phrases for backing up keys.
+//!
+//! The phrase and passphrase give a 64-byte BIP-39 seed, and the key is
+//! derived from it with SLIP-0010 along a hardened path, as Ed25519 wallets
+//! do. [`MNEMONIC_PATH`] is what [`KeyPair::derive_for`] would use for chain 0,
+//! account 0 and the consensus key; pass a wallet's own path to
+//! [`KeyPair::from_mnemonic_at`] to recover its keys.
+
+use super::{Error, KeyPair, DERIVATION_PURPOSE};
+use crate::derive::slip10_derive;
+use bip39::Mnemonic;
+use sodiumoxide::randombytes::randombytes_into;
+use sodiumoxide::utils::memzero;
+
+/// `m/44'/0'/0'/0'`, written without the hardened bit.
+pub const MNEMONIC_PATH: [u32; 4] = [DERIVATION_PURPOSE, 0, 0, 0];
+
+impl KeyPair {
+    /// Generate a key pair with a new phrase of 12, 15, 18, 21 or 24 words
+    /// and no passphrase.
+    pub fn gen_with_mnemonic(word_count: usize) -> Result<(KeyPair, String), Error> {
+        if !(12..=24).contains(&word_count) || word_count % 3 != 0 {
+            return Err(Error::InvalidEncoding);
+        }
+        let mut entropy = [0u8; 32];
+        let entropy_len = word_count / 3 * 4;
+        randombytes_into(&mut entropy[..entropy_len]);
+        let mnemonic = Mnemonic::from_entropy(&entropy[..entropy_len]);
+        memzero(&mut entropy);
+        let phrase = mnemonic.map_err(|_| Error::InvalidEncoding)?.to_string();
+        let keypair = KeyPair::from_mnemonic(&phrase, "")?;
+        Ok((keypair, phrase))
+    }
+
+    /// Recover the key pair at [`MNEMONIC_PATH`] for an English `phrase`.
+    /// A phrase with a bad word or checksum gives `InvalidEncoding`.
+    pub fn from_mnemonic(phrase: &str, passphrase: &str) -> Result<KeyPair, Error> {
+        KeyPair::from_mnemonic_at(phrase, passphrase, &MNEMONIC_PATH)
+    }
+
+    /// Recover the key pair at `path`; every index is hardened and must be
+    /// below 2^31.
+    pub fn from_mnemonic_at(
+        phrase: &str,
+        passphrase: &str,
+        path: &[u32],
+    ) -> Result<KeyPair, Error> {
+        let mnemonic = Mnemonic::parse(phrase).map_err(|_| Error::InvalidEncoding)?;
+        let mut seed = mnemonic.to_seed(passphrase);
+        let key = slip10_derive(&seed, path);
+        memzero(&mut seed);
+        let mut key = key?;
+        let keypair = KeyPair::from_seed(&key);
+        memzero(&mut key);
+        Ok(keypair)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cita_crypto_trait::CreateKey;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_mnemonic_roundtrip() {
+        let (keypair, phrase) = KeyPair::gen_with_mnemonic(24).unwrap();
+        assert_eq!(phrase.split(' ').count(), 24);
+        let restored = KeyPair::from_mnemonic(&phrase, "").unwrap();
+        assert_eq!(restored.privkey(), keypair.privkey());
+        let protected = KeyPair::from_mnemonic(&phrase, "TREZOR").unwrap();
+        assert_ne!(protected.pubkey(), keypair.pubkey());
+        assert!(KeyPair::gen_with_mnemonic(13).is_err());
+    }
+
+    #[test]
+    fn test_mnemonic_path() {
+        let keypair = KeyPair::from_mnemonic(PHRASE, "").unwrap();
+        let other = KeyPair::from_mnemonic_at(PHRASE, "", &[44, 0, 0, 1]).unwrap();
+        assert_ne!(other.pubkey(), keypair.pubkey());
+        assert!(KeyPair::from_mnemonic_at(PHRASE, "", &[0x8000_002c]).is_err());
+        let bad_checksum = PHRASE.replace("about", "abandon");
+        assert!(matches!(
+            KeyPair::from_mnemonic(&bad_checksum, ""),
+            Err(Error::InvalidEncoding)
+        ));
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Address, Error, Message, PubKey, Signature};
+use crate::hasher::hash_data;
+use cita_crypto_trait::Sign;
+use rlp::RlpStream;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+const MULTISIG_DOMAIN: &[u8] = b"cita-ed25519 multisig";
+
+#[derive(Serialize, Deserialize)]
+struct MultisigFields {
+    threshold: u32,
+    pubkeys: Vec<PubKey>,
+}
+
+/// An m-of-n group of keys with a stable address.
+///
+/// Keys are sorted and deduplicated on construction, so the same group and
+/// threshold always give the same address whatever order the keys came in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "MultisigFields", into = "MultisigFields")]
+pub struct MultisigAccount {
+    threshold: u32,
+    pubkeys: Vec<PubKey>,
+}
+
+impl MultisigAccount {
+    pub fn new(threshold: u32, pubkeys: &[PubKey]) -> Result<Self, Error> {
+        let mut pubkeys = pubkeys.to_vec();
+        pubkeys.sort_unstable();
+        pubkeys.dedup();
+        if threshold == 0 || threshold as usize > pubkeys.len() {
+            return Err(Error::InvalidThreshold);
+        }
+        Ok(MultisigAccount { threshold, pubkeys })
+    }
+
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    /// Member keys, sorted.
+    pub fn pubkeys(&self) -> &[PubKey] {
+        &self.pubkeys
+    }
+
+    /// Hash of the domain tag, threshold and sorted keys.
+    pub fn address(&self) -> Address {
+        let mut s = RlpStream::new_list(3);
+        s.append(&MULTISIG_DOMAIN);
+        s.append(&self.threshold);
+        s.append_list(&self.pubkeys);
+        Address::from(hash_data(&s.out()))
+    }
+
+    /// Whether at least `threshold` distinct members signed `message`.
+    /// Signatures from non-members or that fail to verify are ignored.
+    pub fn verify(&self, message: &Message, signatures: &[Signature]) -> bool {
+        let mut signed = vec![false; self.pubkeys.len()];
+        for signature in signatures {
+            let signer = PubKey::from_slice(signature.pk());
+            if let Ok(i) = self.pubkeys.binary_search(&signer) {
+                if !signed[i] && signature.verify_public(&signer, message).is_ok() {
+                    signed[i] = true;
+                }
+            }
+        }
+        signed.iter().filter(|s| **s).count() >= self.threshold as usize
+    }
+}
+
+impl TryFrom<MultisigFields> for MultisigAccount {
+    type Error = Error;
+
+    fn try_from(fields: MultisigFields) -> Result<Self, Self::Error> {
+        MultisigAccount::new(fields.threshold, &fields.pubkeys)
+    }
+}
+
+impl From<MultisigAccount> for MultisigFields {
+    fn from(account: MultisigAccount) -> Self {
+        MultisigFields {
+            threshold: account.threshold,
+            pubkeys: account.pubkeys,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+    use cita_crypto_trait::CreateKey;
+
+    #[test]
+    fn test_multisig_account() {
+        let keys: Vec<KeyPair> = (0..3).map(|_| KeyPair::gen_keypair()).collect();
+        let pubkeys: Vec<PubKey> = keys.iter().map(|k| *k.pubkey()).collect();
+        let account = MultisigAccount::new(2, &pubkeys).unwrap();
+        let reversed: Vec<PubKey> = pubkeys.iter().rev().copied().collect();
+        assert_eq!(
+            MultisigAccount::new(2, &reversed).unwrap().address(),
+            account.address()
+        );
+        assert_ne!(
+            MultisigAccount::new(3, &pubkeys).unwrap().address(),
+            account.address()
+        );
+        assert!(MultisigAccount::new(4, &pubkeys).is_err());
+
+        let msg = Message::from_low_u64_be(1);
+        let sig0 = Signature::sign(keys[0].privkey(), &msg).unwrap();
+        let sig1 = Signature::sign(keys[1].privkey(), &msg).unwrap();
+        assert!(!account.verify(&msg, &[sig0.clone(), sig0.clone()]));
+        assert!(account.verify(&msg, &[sig0, sig1]));
+
+        let json = serde_json::to_string(&account).unwrap();
+        let decoded: MultisigAccount = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.address(), account.address());
+    }
+}
@@ -0,0 +1,286 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signing on an air-gapped machine.
+//!
+//! The online side builds an [`UnsignedBundle`] of digests, each with a line
+//! of context for the operator, and moves it across as a file or a series of
+//! QR codes. The offline side answers with [`BundleSignatures`], which the
+//! online side checks and pairs back up with [`UnsignedBundle::apply_signatures`].
+
+use super::{Address, Error, KeyPair, Message, Signature};
+use crate::hasher::hash_data;
+use crate::hex::Hex;
+use crate::{decode_hex_ct, pubkey_to_address};
+use cita_crypto_trait::{CreateKey, Sign};
+use cita_types::H256;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+const BUNDLE_DOMAIN: &[u8] = b"cita-ed25519 unsigned bundle";
+const QR_PREFIX: &str = "CEB:";
+
+/// One digest to sign and what it is for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleEntry {
+    pub message: Message,
+    pub context: String,
+}
+
+impl Encodable for BundleEntry {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2);
+        s.append(&self.message);
+        s.append(&self.context);
+    }
+}
+
+impl Decodable for BundleEntry {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 2 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(BundleEntry {
+            message: rlp.val_at(0)?,
+            context: rlp.val_at(1)?,
+        })
+    }
+}
+
+/// Digests waiting for the key at `signer` to sign them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnsignedBundle {
+    pub signer: Address,
+    pub entries: Vec<BundleEntry>,
+}
+
+impl Encodable for UnsignedBundle {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2);
+        s.append(&self.signer);
+        s.append_list(&self.entries);
+    }
+}
+
+impl Decodable for UnsignedBundle {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 2 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(UnsignedBundle {
+            signer: rlp.val_at(0)?,
+            entries: rlp.list_at(1)?,
+        })
+    }
+}
+
+/// The offline side's answer, one signature per entry in order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleSignatures {
+    pub bundle_id: H256,
+    pub signatures: Vec<Signature>,
+}
+
+impl Encodable for BundleSignatures {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2);
+        s.append(&self.bundle_id);
+        Signature::rlp_append_list(&self.signatures, s);
+    }
+}
+
+impl Decodable for BundleSignatures {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 2 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(BundleSignatures {
+            bundle_id: rlp.val_at(0)?,
+            signatures: rlp.list_at(1)?,
+        })
+    }
+}
+
+impl UnsignedBundle {
+    pub fn new(signer: Address) -> Self {
+        UnsignedBundle {
+            signer,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, message: Message, context: &str) {
+        self.entries.push(BundleEntry {
+            message,
+            context: context.to_owned(),
+        });
+    }
+
+    /// Binds [`BundleSignatures`] to exactly this bundle.
+    pub fn id(&self) -> H256 {
+        let mut s = RlpStream::new_list(2);
+        s.append(&BUNDLE_DOMAIN);
+        s.append(self);
+        hash_data(&s.out())
+    }
+
+    /// Sign every entry; run on the air-gapped machine after the operator
+    /// has reviewed the contexts.
+    pub fn sign(&self, keypair: &KeyPair) -> Result<BundleSignatures, Error> {
+        if keypair.address() != self.signer {
+            return Err(Error::InvalidPrivKey);
+        }
+        let signatures = self
+            .entries
+            .iter()
+            .map(|entry| Signature::sign(keypair.privkey(), &entry.message))
+            .collect::<Result<_, _>>()?;
+        Ok(BundleSignatures {
+            bundle_id: self.id(),
+            signatures,
+        })
+    }
+
+    /// Check `signed` against this bundle and pair each digest with its
+    /// signature.
+    pub fn apply_signatures(
+        &self,
+        signed: &BundleSignatures,
+    ) -> Result<Vec<(Message, Signature)>, Error> {
+        if signed.bundle_id != self.id() || signed.signatures.len() != self.entries.len() {
+            return Err(Error::InvalidMessage);
+        }
+        self.entries
+            .iter()
+            .zip(&signed.signatures)
+            .map(|(entry, signature)| {
+                let pubkey = signature.recover(&entry.message)?;
+                if pubkey_to_address(&pubkey) != self.signer {
+                    return Err(Error::InvalidPubKey);
+                }
+                Ok((entry.message, signature.clone()))
+            })
+            .collect()
+    }
+}
+
+/// Write a bundle or its signatures as JSON, for transfer on removable media.
+pub fn write_bundle_file<T: Serialize, P: AsRef<Path>>(path: P, value: &T) -> Result<(), Error> {
+    let json = serde_json::to_string_pretty(value).map_err(|_| Error::InvalidEncoding)?;
+    fs::write(path, json)?;
+    Ok(())
+}
+
+pub fn read_bundle_file<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> Result<T, Error> {
+    let json = fs::read_to_string(path)?;
+    serde_json::from_str(&json).map_err(|_| Error::InvalidEncoding)
+}
+
+/// Split the RLP encoding of `value` into QR payloads of at most `max_len`
+/// characters, each `CEB:<i>/<n>:<HEX>`.
+///
+/// Only upper-case letters, digits, `:` and `/` are used, so the payloads fit
+/// the QR alphanumeric mode.
+pub fn to_qr_chunks<T: Encodable>(value: &T, max_len: usize) -> Result<Vec<String>, Error> {
+    let hex = Hex(&rlp::encode(value)).to_string().to_uppercase();
+    // Room for the header with up to three-digit counts.
+    let room = max_len.saturating_sub(QR_PREFIX.len() + 8) & !1;
+    if room == 0 {
+        return Err(Error::InvalidEncoding);
+    }
+    let parts: Vec<&str> = hex
+        .as_bytes()
+        .chunks(room)
+        .map(|part| std::str::from_utf8(part).unwrap())
+        .collect();
+    if parts.len() > 999 {
+        return Err(Error::InvalidEncoding);
+    }
+    Ok(parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| format!("{}{}/{}:{}", QR_PREFIX, i + 1, parts.len(), part))
+        .collect())
+}
+
+/// Reassemble [`to_qr_chunks`] output, scanned in any order.
+pub fn from_qr_chunks<T: Decodable, S: AsRef<str>>(chunks: &[S]) -> Result<T, Error> {
+    let mut parts: Vec<(usize, &str)> = Vec::with_capacity(chunks.len());
+    for chunk in chunks {
+        let rest = chunk
+            .as_ref()
+            .strip_prefix(QR_PREFIX)
+            .ok_or(Error::InvalidEncoding)?;
+        let (header, data) = rest.split_once(':').ok_or(Error::InvalidEncoding)?;
+        let (i, n) = header.split_once('/').ok_or(Error::InvalidEncoding)?;
+        let (i, n): (usize, usize) = match (i.parse(), n.parse()) {
+            (Ok(i), Ok(n)) => (i, n),
+            _ => return Err(Error::InvalidEncoding),
+        };
+        if n != chunks.len() || i == 0 || i > n {
+            return Err(Error::InvalidEncoding);
+        }
+        parts.push((i, data));
+    }
+    parts.sort_by_key(|(i, _)| *i);
+    if parts.iter().enumerate().any(|(k, (i, _))| *i != k + 1) {
+        return Err(Error::InvalidEncoding);
+    }
+    let hex: String = parts.into_iter().map(|(_, data)| data).collect();
+    Ok(rlp::decode(&decode_hex_ct(&hex)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn test_offline_bundle() {
+        let keypair = KeyPair::gen_keypair();
+        let mut bundle = UnsignedBundle::new(keypair.address());
+        for i in 0..5 {
+            bundle.push(Message::from_low_u64_be(i), &format!("transfer #{}", i));
+        }
+
+        let path = env::temp_dir().join(format!("cita-ed25519-{}-bundle.json", process::id()));
+        write_bundle_file(&path, &bundle).unwrap();
+        let offline: UnsignedBundle = read_bundle_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(offline.sign(&KeyPair::gen_keypair()).is_err());
+        let signed = offline.sign(&keypair).unwrap();
+
+        let chunks = to_qr_chunks(&signed, 200).unwrap();
+        assert!(chunks.len() > 1 && chunks.iter().all(|c| c.len() <= 200));
+        assert!(chunks.iter().all(|c| c
+            .chars()
+            .all(|ch| ch.is_ascii_digit() || ch.is_ascii_uppercase() || ch == ':' || ch == '/')));
+        let mut scanned = chunks.clone();
+        scanned.reverse();
+        let returned: BundleSignatures = from_qr_chunks(&scanned).unwrap();
+        assert!(from_qr_chunks::<BundleSignatures, _>(&chunks[1..]).is_err());
+
+        let pairs = bundle.apply_signatures(&returned).unwrap();
+        assert_eq!(pairs.len(), 5);
+        assert_eq!(pairs[3].1.recover(&pairs[3].0).unwrap(), *keypair.pubkey());
+
+        let mut changed = bundle.clone();
+        changed.entries[0].context = "something else".to_owned();
+        assert!(changed.apply_signatures(&returned).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Error, Message, PubKey, Signature};
+use cita_crypto_trait::Sign;
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// Which queue a verification waits in. Workers always drain `Consensus`
+/// first, so mempool bursts cannot delay votes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    Consensus,
+    Mempool,
+}
+
+struct Job {
+    signature: Signature,
+    pubkey: PubKey,
+    message: Message,
+    reply: Sender<Result<(), Error>>,
+}
+
+#[derive(Default)]
+struct Queues {
+    consensus: VecDeque<Job>,
+    mempool: VecDeque<Job>,
+    shutdown: bool,
+}
+
+struct Shared {
+    queues: Mutex<Queues>,
+    ready: Condvar,
+    capacity: usize,
+}
+
+/// The pending result of a submitted verification.
+pub struct VerifyHandle {
+    reply: Receiver<Result<(), Error>>,
+}
+
+impl VerifyHandle {
+    pub(crate) fn new(reply: Receiver<Result<(), Error>>) -> Self {
+        VerifyHandle { reply }
+    }
+
+    /// Block until the verification has run.
+    pub fn wait(self) -> Result<(), Error> {
+        self.reply.recv().unwrap_or(Err(Error::Cancelled))
+    }
+}
+
+/// A fixed set of worker threads verifying signatures from two bounded,
+/// prioritised queues.
+pub struct VerifierPool {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl VerifierPool {
+    /// Start `threads` workers; each queue holds at most `capacity` jobs.
+    pub fn new(threads: usize, capacity: usize) -> Self {
+        let shared = Arc::new(Shared {
+            queues: Mutex::new(Queues::default()),
+            ready: Condvar::new(),
+            capacity,
+        });
+        let workers = (0..threads.max(1))
+            .map(|_| {
+                let shared = Arc::clone(&shared);
+                thread::spawn(move || worker(&shared))
+            })
+            .collect();
+        VerifierPool { shared, workers }
+    }
+
+    /// Queue a verification, failing with `Error::QueueFull` when the lane is
+    /// at capacity and `Error::Cancelled` after shutdown.
+    pub fn submit(
+        &self,
+        priority: Priority,
+        signature: Signature,
+        pubkey: PubKey,
+        message: Message,
+    ) -> Result<VerifyHandle, Error> {
+        let (reply, receiver) = mpsc::channel();
+        let job = Job {
+            signature,
+            pubkey,
+            message,
+            reply,
+        };
+        let mut queues = self.shared.queues.lock().unwrap();
+        if queues.shutdown {
+            return Err(Error::Cancelled);
+        }
+        let lane = match priority {
+            Priority::Consensus => &mut queues.consensus,
+            Priority::Mempool => &mut queues.mempool,
+        };
+        if lane.len() >= self.shared.capacity {
+            return Err(Error::QueueFull);
+        }
+        lane.push_back(job);
+        self.shared.ready.notify_one();
+        Ok(VerifyHandle::new(receiver))
+    }
+
+    /// Stop accepting work, finish what is queued and join the workers.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        self.shared.queues.lock().unwrap().shutdown = true;
+        self.shared.ready.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for VerifierPool {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn worker(shared: &Shared) {
+    loop {
+        let job = {
+            let mut queues = shared.queues.lock().unwrap();
+            loop {
+                if let Some(job) = queues.consensus.pop_front() {
+                    break job;
+                }
+                if let Some(job) = queues.mempool.pop_front() {
+                    break job;
+                }
+                if queues.shutdown {
+                    return;
+                }
+                queues = shared.ready.wait(queues).unwrap();
+            }
+        };
+        let result = job
+            .signature
+            .verify_public(&job.pubkey, &job.message)
+            .map(|_| ());
+        // The submitter may have dropped its handle.
+        let _ = job.reply.send(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+    use cita_crypto_trait::CreateKey;
+
+    #[test]
+    fn test_verifier_pool() {
+        let pool = VerifierPool::new(2, 64);
+        let keypair = KeyPair::gen_keypair();
+        let handles: Vec<(bool, VerifyHandle)> = (0..20u64)
+            .map(|i| {
+                let message = Message::from_low_u64_be(i);
+                let mut sig = Signature::sign(keypair.privkey(), &message).unwrap();
+                let valid = i % 3 != 0;
+                if !valid {
+                    sig.0[0] ^= 1;
+                }
+                let priority = if i % 2 == 0 {
+                    Priority::Consensus
+                } else {
+                    Priority::Mempool
+                };
+                let handle = pool
+                    .submit(priority, sig, *keypair.pubkey(), message)
+                    .unwrap();
+                (valid, handle)
+            })
+            .collect();
+        for (valid, handle) in handles {
+            assert_eq!(handle.wait().is_ok(), valid);
+        }
+        pool.shutdown();
+    }
+
+    #[test]
+    fn test_verifier_pool_bounded() {
+        let pool = VerifierPool::new(1, 0);
+        let result = pool.submit(
+            Priority::Mempool,
+            Signature::default(),
+            PubKey::zero(),
+            Message::zero(),
+        );
+        assert!(matches!(result, Err(Error::QueueFull)));
+    }
+}
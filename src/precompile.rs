@@ -0,0 +1,92 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Error, Message, PubKey, Signature, HASH_BYTES_LEN, SIGNATURE_BYTES_LEN};
+use crate::curve::is_canonical_scalar;
+use crate::hasher::hash_data;
+use crate::meter::{charge, Operation};
+use cita_crypto_trait::Sign;
+
+/// Length of the [`verify_raw`] input: the 32-byte message hash followed by
+/// the 96-byte signature.
+pub const PRECOMPILE_INPUT_LEN: usize = HASH_BYTES_LEN + SIGNATURE_BYTES_LEN;
+
+/// Verify a packed `message || signature` buffer and return the hash of the
+/// signer's public key, whose last 20 bytes are its address.
+///
+/// Anything but exactly [`PRECOMPILE_INPUT_LEN`] bytes is rejected, as are
+/// non-canonical `S` values and small-order or malformed keys and `R`
+/// points. The function never panics on any input.
+pub fn verify_raw(input: &[u8]) -> Result<[u8; 32], Error> {
+    if input.len() != PRECOMPILE_INPUT_LEN {
+        return Err(Error::InvalidEncoding);
+    }
+    let (message, signature) = input.split_at(HASH_BYTES_LEN);
+    let message = Message::from_slice(message);
+    let mut bytes = [0u8; SIGNATURE_BYTES_LEN];
+    bytes.copy_from_slice(signature);
+    let mut s = [0u8; 32];
+    s.copy_from_slice(&bytes[32..64]);
+    if !is_canonical_scalar(&s) {
+        return Err(Error::InvalidSignature);
+    }
+    let signature = Signature(bytes);
+    let pubkey: PubKey = signature.recover(&message)?;
+    charge(Operation::AddressHash, 1);
+    Ok(hash_data(&pubkey.0).0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::GROUP_ORDER;
+    use crate::KeyPair;
+    use cita_crypto_trait::CreateKey;
+
+    #[test]
+    fn test_verify_raw() {
+        let keypair = KeyPair::gen_keypair();
+        let message = Message::from_low_u64_be(488);
+        let signature = Signature::sign(keypair.privkey(), &message).unwrap();
+        let mut input = [message.as_ref(), signature.as_ref()].concat();
+
+        let hash = verify_raw(&input).unwrap();
+        assert_eq!(&hash[12..], keypair.address().as_ref() as &[u8]);
+
+        for len in [0, 1, 32, 96, 127, 129, 256] {
+            let mut buffer = input.clone();
+            buffer.resize(len, 0);
+            assert!(verify_raw(&buffer).is_err());
+        }
+        for i in [0, 31, 32, 63, 64, 95, 96, 127] {
+            let mut tampered = input.clone();
+            tampered[i] ^= 0x80;
+            assert!(verify_raw(&tampered).is_err(), "byte {}", i);
+        }
+        // S + L verifies as the same signature in lax implementations.
+        let mut carry = 0u16;
+        for (s, l) in input[64..96].iter_mut().zip(GROUP_ORDER.iter()) {
+            carry += *s as u16 + *l as u16;
+            *s = carry as u8;
+            carry >>= 8;
+        }
+        assert!(carry == 0 && verify_raw(&input).is_err());
+        // Identity public key and R, with S = 0.
+        let mut identity = [0u8; PRECOMPILE_INPUT_LEN];
+        identity[32] = 1;
+        identity[96] = 1;
+        assert!(verify_raw(&identity).is_err());
+        assert!(verify_raw(&[0u8; PRECOMPILE_INPUT_LEN]).is_err());
+    }
+}
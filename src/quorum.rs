@@ -0,0 +1,117 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{verify_batch, BatchItem, Message, PubKey, Signature};
+
+/// Outcome of [`verify_quorum`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuorumResult {
+    /// For each key, in the order given, whether it validly signed.
+    pub signed: Vec<bool>,
+    /// Total weight of the keys that signed.
+    pub weight: u64,
+    /// Whether `weight` reached the threshold.
+    pub met: bool,
+}
+
+/// Which of `pubkeys` validly signed `message` among `signatures`.
+///
+/// Signatures are matched to keys by their embedded public key and checked
+/// together with [`verify_batch`]. Signatures from unknown keys and invalid
+/// ones are ignored; a bad signature does not hide a good one from the same
+/// key.
+pub fn verify_any(pubkeys: &[PubKey], message: &Message, signatures: &[Signature]) -> Vec<bool> {
+    let mut positions = Vec::new();
+    let mut items = Vec::new();
+    for (i, signature) in signatures.iter().enumerate() {
+        if signatures[..i].contains(signature) {
+            continue;
+        }
+        let position = pubkeys
+            .iter()
+            .position(|pubkey| signature.embedded_pubkey_matches(pubkey));
+        if let Some(position) = position {
+            positions.push(position);
+            items.push(BatchItem {
+                message: *message,
+                signature: signature.clone(),
+                pubkey: pubkeys[position],
+            });
+        }
+    }
+    let mut signed = vec![false; pubkeys.len()];
+    for (position, result) in positions.into_iter().zip(verify_batch(&items)) {
+        signed[position] |= result.is_ok();
+    }
+    signed
+}
+
+/// Whether the keys that validly signed `message` carry at least
+/// `threshold` of the total weight in `weights`.
+pub fn verify_quorum(
+    weights: &[(PubKey, u64)],
+    threshold: u64,
+    message: &Message,
+    signatures: &[Signature],
+) -> QuorumResult {
+    let pubkeys: Vec<PubKey> = weights.iter().map(|(pubkey, _)| *pubkey).collect();
+    let signed = verify_any(&pubkeys, message, signatures);
+    let weight = weights
+        .iter()
+        .zip(&signed)
+        .filter(|(_, signed)| **signed)
+        .fold(0u64, |acc, ((_, weight), _)| acc.saturating_add(*weight));
+    QuorumResult {
+        signed,
+        weight,
+        met: weight >= threshold,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+    use cita_crypto_trait::{CreateKey, Sign};
+
+    #[test]
+    fn test_verify_quorum() {
+        let keys: Vec<KeyPair> = (0..5).map(|_| KeyPair::gen_keypair()).collect();
+        let message = Message::from_low_u64_be(492);
+        let weights: Vec<(PubKey, u64)> = keys
+            .iter()
+            .zip([10, 20, 30, 25, 15])
+            .map(|(k, w)| (*k.pubkey(), w))
+            .collect();
+        let sign = |i: usize| Signature::sign(keys[i].privkey(), &message).unwrap();
+
+        let mut forged = sign(3);
+        forged.0[10] ^= 1;
+        let outsider = Signature::sign(KeyPair::gen_keypair().privkey(), &message).unwrap();
+        let signatures = vec![forged, sign(1), sign(2), sign(2), outsider];
+
+        let pubkeys: Vec<PubKey> = weights.iter().map(|(p, _)| *p).collect();
+        assert_eq!(
+            verify_any(&pubkeys, &message, &signatures),
+            vec![false, true, true, false, false]
+        );
+        let mut late = signatures.clone();
+        late.push(sign(3));
+        assert!(verify_any(&pubkeys, &message, &late)[3]);
+        let result = verify_quorum(&weights, 50, &message, &signatures);
+        assert_eq!(result.weight, 50);
+        assert!(result.met);
+        assert!(!verify_quorum(&weights, 67, &message, &signatures).met);
+    }
+}
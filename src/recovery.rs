@@ -0,0 +1,190 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{
+    combine, open_sealed, seal, split, Address, Error, KeyPair, PrivKey, PubKey, Share,
+    ADDR_BYTES_LEN,
+};
+use cita_crypto_trait::CreateKey;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+use sodiumoxide::utils::memzero;
+
+// address || threshold || index || share data
+const SHARE_HEADER_LEN: usize = ADDR_BYTES_LEN + 2;
+const SEED_LEN: usize = 32;
+
+/// A Shamir share of a private key, sealed to one guardian.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GuardianShare {
+    pub guardian: PubKey,
+    /// Address of the protected key.
+    pub address: Address,
+    pub threshold: u8,
+    #[serde(with = "crate::serde_hex")]
+    pub ciphertext: Vec<u8>,
+}
+
+impl Encodable for GuardianShare {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(4);
+        s.append(&self.guardian);
+        s.append(&self.address);
+        s.append(&self.threshold);
+        s.append(&self.ciphertext);
+    }
+}
+
+impl Decodable for GuardianShare {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 4 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(GuardianShare {
+            guardian: rlp.val_at(0)?,
+            address: rlp.val_at(1)?,
+            threshold: rlp.val_at(2)?,
+            ciphertext: rlp.val_at(3)?,
+        })
+    }
+}
+
+/// A share a guardian has decrypted and handed back for [`restore`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecoveryShare {
+    pub address: Address,
+    pub threshold: u8,
+    pub share: Share,
+}
+
+/// Split `privkey` into one encrypted share per guardian, any `threshold` of
+/// which can restore it.
+pub fn protect(
+    privkey: &PrivKey,
+    guardians: &[PubKey],
+    threshold: u8,
+) -> Result<Vec<GuardianShare>, Error> {
+    if guardians.len() > usize::from(u8::MAX)
+        || guardians
+            .iter()
+            .enumerate()
+            .any(|(i, g)| guardians[..i].contains(g))
+    {
+        return Err(Error::InvalidShares);
+    }
+    let address = KeyPair::from_privkey(*privkey)?.address();
+    let shares = split(&privkey.0[..SEED_LEN], threshold, guardians.len() as u8)?;
+
+    guardians
+        .iter()
+        .zip(shares)
+        .map(|(guardian, share)| {
+            let mut plaintext = Vec::with_capacity(SHARE_HEADER_LEN + SEED_LEN);
+            plaintext.extend_from_slice(&address.0);
+            plaintext.push(threshold);
+            plaintext.push(share.index);
+            plaintext.extend_from_slice(&share.data);
+            let ciphertext = seal(guardian, &plaintext);
+            memzero(&mut plaintext);
+            Ok(GuardianShare {
+                guardian: *guardian,
+                address,
+                threshold,
+                ciphertext: ciphertext?,
+            })
+        })
+        .collect()
+}
+
+impl GuardianShare {
+    /// Decrypt this share with the guardian's key pair.
+    pub fn decrypt(&self, guardian: &KeyPair) -> Result<RecoveryShare, Error> {
+        if guardian.pubkey() != &self.guardian {
+            return Err(Error::InvalidPubKey);
+        }
+        let mut plaintext = open_sealed(guardian, &self.ciphertext)?;
+        let result = if plaintext.len() != SHARE_HEADER_LEN + SEED_LEN
+            || plaintext[..ADDR_BYTES_LEN] != self.address.0
+            || plaintext[ADDR_BYTES_LEN] != self.threshold
+        {
+            Err(Error::DecryptionFailed)
+        } else {
+            Ok(RecoveryShare {
+                address: self.address,
+                threshold: self.threshold,
+                share: Share {
+                    index: plaintext[ADDR_BYTES_LEN + 1],
+                    data: plaintext[SHARE_HEADER_LEN..].to_vec(),
+                },
+            })
+        };
+        memzero(&mut plaintext);
+        result
+    }
+}
+
+/// Rebuild the protected key from decrypted guardian shares.
+pub fn restore(shares: &[RecoveryShare]) -> Result<KeyPair, Error> {
+    let first = shares.first().ok_or(Error::InvalidShares)?;
+    if shares
+        .iter()
+        .any(|s| s.address != first.address || s.threshold != first.threshold)
+    {
+        return Err(Error::InvalidShares);
+    }
+
+    // combine rejects repeated indices and fewer than `threshold` shares.
+    let parts: Vec<Share> = shares.iter().map(|s| s.share.clone()).collect();
+    let mut secret = combine(&parts, first.threshold)?;
+    if secret.len() != SEED_LEN {
+        memzero(&mut secret);
+        return Err(Error::InvalidShares);
+    }
+    let mut seed = [0u8; SEED_LEN];
+    seed.copy_from_slice(&secret);
+    memzero(&mut secret);
+    let keypair = KeyPair::from_seed(&seed);
+    memzero(&mut seed);
+
+    if keypair.address() != first.address {
+        return Err(Error::InvalidShares);
+    }
+    Ok(keypair)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protect_restore() {
+        let keypair = KeyPair::gen_keypair();
+        let guardians: Vec<KeyPair> = (0..5).map(|_| KeyPair::gen_keypair()).collect();
+        let pubkeys: Vec<PubKey> = guardians.iter().map(|g| *g.pubkey()).collect();
+        let protected = protect(keypair.privkey(), &pubkeys, 3).unwrap();
+
+        let shares: Vec<RecoveryShare> = [4, 1, 2]
+            .iter()
+            .map(|&i| protected[i].decrypt(&guardians[i]).unwrap())
+            .collect();
+        let restored = restore(&shares).unwrap();
+        assert_eq!(restored.privkey(), keypair.privkey());
+
+        assert!(restore(&shares[..2]).is_err());
+        // A repeated guardian does not count twice towards the threshold.
+        let repeated = [shares[0].clone(), shares[1].clone(), shares[1].clone()];
+        assert!(matches!(restore(&repeated), Err(Error::InvalidShares)));
+        assert!(protected[0].decrypt(&guardians[1]).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signed requests that cannot be replayed, for authenticating admin RPCs
+//! with node keys.
+
+use super::{pubkey_to_address, Address, Error, KeyPair, Message, PubKey, Signature};
+use crate::clock::now;
+use crate::hasher::hash_data;
+use cita_crypto_trait::{CreateKey, Sign};
+use rlp::RlpStream;
+use serde::{Deserialize, Serialize};
+use sodiumoxide::randombytes::randombytes_into;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const REQUEST_DOMAIN: &[u8] = b"cita-ed25519 signed request";
+
+/// Remembers which nonces each signer has used, so that the verifying side
+/// can reject replays. Implementations may be backed by a database.
+pub trait NonceStore {
+    /// Record `nonce` for `signer` and return whether it is fresh. The nonce
+    /// only needs to be remembered until `expires_at`, after which the
+    /// request is rejected for having expired anyway.
+    fn check_and_insert(&self, signer: &Address, nonce: u128, expires_at: u64) -> bool;
+}
+
+/// Accepts any nonce not seen before, for random nonces.
+#[derive(Debug, Default)]
+pub struct MemoryNonceStore {
+    seen: Mutex<HashMap<(Address, u128), u64>>,
+}
+
+impl MemoryNonceStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget nonces whose requests have expired by `time`.
+    pub fn prune(&self, time: u64) {
+        self.seen
+            .lock()
+            .unwrap()
+            .retain(|_, expires_at| *expires_at >= time);
+    }
+}
+
+impl NonceStore for MemoryNonceStore {
+    fn check_and_insert(&self, signer: &Address, nonce: u128, expires_at: u64) -> bool {
+        self.seen
+            .lock()
+            .unwrap()
+            .insert((*signer, nonce), expires_at)
+            .is_none()
+    }
+}
+
+/// Accepts only nonces above the last one seen from the same signer, for
+/// counters. Needs one entry per signer.
+#[derive(Debug, Default)]
+pub struct MonotonicNonceStore {
+    last: Mutex<HashMap<Address, u128>>,
+}
+
+impl MonotonicNonceStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NonceStore for MonotonicNonceStore {
+    fn check_and_insert(&self, signer: &Address, nonce: u128, _: u64) -> bool {
+        let mut last = self.last.lock().unwrap();
+        match last.get(signer) {
+            Some(&previous) if nonce <= previous => false,
+            _ => {
+                last.insert(*signer, nonce);
+                true
+            }
+        }
+    }
+}
+
+/// A payload signed together with a nonce and an expiry time in seconds
+/// since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedRequest {
+    #[serde(with = "crate::serde_hex")]
+    pub payload: Vec<u8>,
+    pub nonce: u128,
+    pub expires_at: u64,
+    pub signature: Signature,
+}
+
+fn request_hash(payload: &[u8], nonce: u128, expires_at: u64) -> Message {
+    let mut s = RlpStream::new_list(4);
+    s.append(&REQUEST_DOMAIN);
+    s.append(&payload);
+    s.append(&&nonce.to_be_bytes()[..]);
+    s.append(&expires_at);
+    hash_data(&s.out())
+}
+
+impl SignedRequest {
+    /// Sign `payload` with a caller-chosen nonce, e.g. a counter.
+    pub fn sign(
+        keypair: &KeyPair,
+        payload: Vec<u8>,
+        nonce: u128,
+        expires_at: u64,
+    ) -> Result<Self, Error> {
+        let message = request_hash(&payload, nonce, expires_at);
+        Ok(SignedRequest {
+            signature: Signature::sign(keypair.privkey(), &message)?,
+            payload,
+            nonce,
+            expires_at,
+        })
+    }
+
+    /// Sign `payload` with a random 128-bit nonce, valid for `ttl`.
+    pub fn sign_random(keypair: &KeyPair, payload: Vec<u8>, ttl: Duration) -> Result<Self, Error> {
+        let mut nonce = [0u8; 16];
+        randombytes_into(&mut nonce);
+        let expires_at = now().saturating_add(ttl.as_secs());
+        Self::sign(keypair, payload, u128::from_be_bytes(nonce), expires_at)
+    }
+
+    /// Check the signature and expiry, then record the nonce in `store`.
+    /// Returns the signer's address.
+    pub fn verify<S: NonceStore + ?Sized>(&self, store: &S) -> Result<Address, Error> {
+        self.verify_at(store, now())
+    }
+
+    /// [`SignedRequest::verify`] at `time` seconds since the Unix epoch.
+    pub fn verify_at<S: NonceStore + ?Sized>(
+        &self,
+        store: &S,
+        time: u64,
+    ) -> Result<Address, Error> {
+        if time > self.expires_at {
+            return Err(Error::Expired);
+        }
+        let message = request_hash(&self.payload, self.nonce, self.expires_at);
+        let pubkey = self.signature.recover(&message)?;
+        let signer = pubkey_to_address(&pubkey);
+        if !store.check_and_insert(&signer, self.nonce, self.expires_at) {
+            return Err(Error::Replayed);
+        }
+        Ok(signer)
+    }
+
+    pub fn signer(&self) -> PubKey {
+        PubKey::from_slice(self.signature.pk())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_request_replay() {
+        let keypair = KeyPair::gen_keypair();
+        let store = MemoryNonceStore::new();
+        let request =
+            SignedRequest::sign_random(&keypair, b"restart".to_vec(), Duration::from_secs(30))
+                .unwrap();
+        let json = serde_json::to_string(&request).unwrap();
+        let request: SignedRequest = serde_json::from_str(&json).unwrap();
+        assert_eq!(request.verify(&store).unwrap(), keypair.address());
+        assert!(matches!(request.verify(&store), Err(Error::Replayed)));
+
+        let late = request.expires_at + 1;
+        let fresh = MemoryNonceStore::new();
+        assert!(matches!(
+            request.verify_at(&fresh, late),
+            Err(Error::Expired)
+        ));
+        // Pruning keeps nonces whose requests are still live.
+        store.prune(request.expires_at);
+        assert!(matches!(request.verify(&store), Err(Error::Replayed)));
+
+        let mut tampered = request;
+        tampered.payload = b"halt".to_vec();
+        assert!(tampered.verify(&fresh).is_err());
+    }
+
+    #[test]
+    fn test_monotonic_nonces() {
+        let keypair = KeyPair::gen_keypair();
+        let store = MonotonicNonceStore::new();
+        let sign = |nonce| SignedRequest::sign(&keypair, vec![1], nonce, u64::MAX).unwrap();
+        sign(1).verify(&store).unwrap();
+        sign(5).verify(&store).unwrap();
+        assert!(sign(5).verify(&store).is_err());
+        assert!(sign(3).verify(&store).is_err());
+    }
+}
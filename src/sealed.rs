@@ -0,0 +1,207 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Error, KeyPair, Limits, PubKey};
+use cita_crypto_trait::CreateKey;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+use sodiumoxide::crypto::box_;
+use sodiumoxide::crypto::sealedbox;
+use sodiumoxide::crypto::secretbox;
+use sodiumoxide::crypto::sign::{to_curve25519_pk, to_curve25519_sk, PublicKey, SecretKey};
+
+/// Bytes a sealed box adds on top of the plaintext.
+pub const SEAL_OVERHEAD: usize = sealedbox::SEALBYTES;
+
+pub(crate) fn curve25519_pk(pubkey: &PubKey) -> Result<box_::PublicKey, Error> {
+    let pk = PublicKey::from_slice(pubkey.as_ref()).ok_or(Error::InvalidPubKey)?;
+    to_curve25519_pk(&pk).map_err(|_| Error::InvalidPubKey)
+}
+
+pub(crate) fn curve25519_sk(keypair: &KeyPair) -> Result<box_::SecretKey, Error> {
+    let sk = SecretKey::from_slice(keypair.privkey().as_ref()).ok_or(Error::InvalidPrivKey)?;
+    to_curve25519_sk(&sk).map_err(|_| Error::InvalidPrivKey)
+}
+
+/// Anonymously encrypt `plaintext` to the holder of `pubkey`.
+///
+/// The Ed25519 key is converted to its X25519 form and used with a libsodium
+/// sealed box, so no separate encryption key has to be distributed.
+/// Envelopes longer than the default [`Limits`] allow are refused; see
+/// [`seal_unbounded`].
+pub fn seal(pubkey: &PubKey, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    Limits::default().check_envelope_len(plaintext.len().saturating_add(SEAL_OVERHEAD))?;
+    seal_unbounded(pubkey, plaintext)
+}
+
+/// [`seal`] without the default envelope limit.
+pub fn seal_unbounded(pubkey: &PubKey, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    Ok(sealedbox::seal(plaintext, &curve25519_pk(pubkey)?))
+}
+
+/// Decrypt a box produced by [`seal`] for `keypair`, refusing envelopes
+/// longer than the default [`Limits`] allow.
+pub fn open_sealed(keypair: &KeyPair, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+    Limits::default().check_envelope_len(ciphertext.len())?;
+    open_sealed_unbounded(keypair, ciphertext)
+}
+
+/// [`open_sealed`] without the default envelope limit.
+pub fn open_sealed_unbounded(keypair: &KeyPair, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+    let pk = curve25519_pk(keypair.pubkey())?;
+    let sk = curve25519_sk(keypair)?;
+    sealedbox::open(ciphertext, &pk, &sk).map_err(|_| Error::DecryptionFailed)
+}
+
+/// Format version written by [`seal_to_many`].
+pub const MULTI_SEALED_VERSION: u8 = 1;
+
+/// The content key wrapped for one recipient.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WrappedKey {
+    pub recipient: PubKey,
+    #[serde(with = "crate::serde_hex")]
+    pub sealed_key: Vec<u8>,
+}
+
+/// A payload encrypted once under a random content key, with that key sealed
+/// separately to every recipient.
+///
+/// Every recipient learns the content key, so any of them could re-encrypt a
+/// different payload for the others; sign the payload if that matters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MultiSealed {
+    pub version: u8,
+    pub recipients: Vec<WrappedKey>,
+    #[serde(with = "crate::serde_hex")]
+    pub nonce: Vec<u8>,
+    #[serde(with = "crate::serde_hex")]
+    pub ciphertext: Vec<u8>,
+}
+
+impl Encodable for WrappedKey {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2);
+        s.append(&self.recipient);
+        s.append(&self.sealed_key);
+    }
+}
+
+impl Decodable for WrappedKey {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 2 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(WrappedKey {
+            recipient: rlp.val_at(0)?,
+            sealed_key: rlp.val_at(1)?,
+        })
+    }
+}
+
+impl Encodable for MultiSealed {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(4);
+        s.append(&self.version);
+        s.append_list(&self.recipients);
+        s.append(&self.nonce);
+        s.append(&self.ciphertext);
+    }
+}
+
+impl Decodable for MultiSealed {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 4 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(MultiSealed {
+            version: rlp.val_at(0)?,
+            recipients: rlp.list_at(1)?,
+            nonce: rlp.val_at(2)?,
+            ciphertext: rlp.val_at(3)?,
+        })
+    }
+}
+
+/// Encrypt `plaintext` once for all of `recipients`.
+pub fn seal_to_many(recipients: &[PubKey], plaintext: &[u8]) -> Result<MultiSealed, Error> {
+    if recipients.is_empty() {
+        return Err(Error::InvalidPubKey);
+    }
+    let key = secretbox::gen_key();
+    let nonce = secretbox::gen_nonce();
+    let recipients = recipients
+        .iter()
+        .map(|recipient| {
+            Ok(WrappedKey {
+                recipient: *recipient,
+                sealed_key: seal(recipient, &key.0)?,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(MultiSealed {
+        version: MULTI_SEALED_VERSION,
+        recipients,
+        nonce: nonce.0.to_vec(),
+        ciphertext: secretbox::seal(plaintext, &nonce, &key),
+    })
+}
+
+/// Decrypt a [`MultiSealed`] container as one of its recipients.
+pub fn open_multi(keypair: &KeyPair, sealed: &MultiSealed) -> Result<Vec<u8>, Error> {
+    if sealed.version != MULTI_SEALED_VERSION {
+        return Err(Error::DecryptionFailed);
+    }
+    let wrapped = sealed
+        .recipients
+        .iter()
+        .find(|w| &w.recipient == keypair.pubkey())
+        .ok_or(Error::InvalidPubKey)?;
+    let mut key_bytes = open_sealed(keypair, &wrapped.sealed_key)?;
+    let key = secretbox::Key::from_slice(&key_bytes);
+    sodiumoxide::utils::memzero(&mut key_bytes);
+    let key = key.ok_or(Error::DecryptionFailed)?;
+    let nonce = secretbox::Nonce::from_slice(&sealed.nonce).ok_or(Error::DecryptionFailed)?;
+    secretbox::open(&sealed.ciphertext, &nonce, &key).map_err(|_| Error::DecryptionFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_open() {
+        let keypair = KeyPair::gen_keypair();
+        let sealed = seal(keypair.pubkey(), b"validator secret").unwrap();
+        assert_eq!(sealed.len(), 16 + SEAL_OVERHEAD);
+        assert_eq!(open_sealed(&keypair, &sealed).unwrap(), b"validator secret");
+
+        let other = KeyPair::gen_keypair();
+        assert!(open_sealed(&other, &sealed).is_err());
+    }
+
+    #[test]
+    fn test_seal_to_many() {
+        let validators: Vec<KeyPair> = (0..4).map(|_| KeyPair::gen_keypair()).collect();
+        let pubkeys: Vec<PubKey> = validators.iter().map(|v| *v.pubkey()).collect();
+        let sealed = seal_to_many(&pubkeys, b"shared secret").unwrap();
+
+        let decoded: MultiSealed = rlp::decode(&rlp::encode(&sealed)).unwrap();
+        assert_eq!(decoded, sealed);
+        for validator in &validators {
+            assert_eq!(open_multi(validator, &decoded).unwrap(), b"shared secret");
+        }
+        assert!(open_multi(&KeyPair::gen_keypair(), &sealed).is_err());
+    }
+}
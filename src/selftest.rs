@@ -0,0 +1,98 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Address, Error, KeyPair, Message, PubKey, Signature};
+use crate::ct::decode_hex_ct;
+use cita_crypto_trait::{CreateKey, Sign};
+use sodiumoxide::randombytes::randombytes_into;
+use std::convert::TryFrom;
+use std::sync::OnceLock;
+
+// RFC 8032 test 1 key, signing the bytes 0x00..=0x1f.
+const KAT_SEED: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+const KAT_PUBKEY: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+const KAT_SIGNATURE: &str = "00c1db988bb12fd7351a6054ae3fac90fab7e4fc56b1651c7181f5f55f896f66\
+                             3933d3a90605d9058e9d0ac45950ee2d3c9c9b14857415587179fe0ccac35f09\
+                             d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+#[cfg(feature = "sha3hash")]
+const KAT_ADDRESS: &str = "f7cc70adc63659b5d37671dc2b588db32446684a";
+#[cfg(feature = "blake2bhash")]
+const KAT_ADDRESS: &str = "8cb93cb09cca219b1c6c48e56f8a6c24b4eed90b";
+#[cfg(feature = "sm3hash")]
+const KAT_ADDRESS: &str = "d2262ea0c903447e6de1f569d9bd9075853299ad";
+
+static RESULT: OnceLock<bool> = OnceLock::new();
+
+fn known_answer() -> Result<bool, Error> {
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(&decode_hex_ct(KAT_SEED)?);
+    let keypair = KeyPair::from_seed(&seed);
+    let message: Vec<u8> = (0u8..32).collect();
+    let message = Message::from_slice(&message);
+    let expected = Signature::try_from(decode_hex_ct(KAT_SIGNATURE)?)?;
+
+    let signature = Signature::sign(keypair.privkey(), &message)?;
+    let mut encoded = vec![0xb8, 0x60];
+    encoded.extend_from_slice(&expected.0);
+    let mut tampered = expected.clone();
+    tampered.0[0] ^= 1;
+
+    Ok(
+        keypair.pubkey() == &PubKey::from_slice(&decode_hex_ct(KAT_PUBKEY)?)
+            && keypair.address() == Address::from_slice(&decode_hex_ct(KAT_ADDRESS)?)
+            && signature == expected
+            && rlp::encode(&signature)[..] == encoded[..]
+            && rlp::decode::<Signature>(&encoded)? == expected
+            && expected.recover(&message)? == *keypair.pubkey()
+            && expected.verify_address(&keypair.address(), &message)?
+            && tampered.recover(&message).is_err(),
+    )
+}
+
+fn round_trip() -> Result<bool, Error> {
+    let keypair = KeyPair::gen_keypair();
+    let mut message = Message::zero();
+    randombytes_into(&mut message.0);
+    let signature = Signature::sign(keypair.privkey(), &message)?;
+    Ok(signature.verify_public(keypair.pubkey(), &message)?
+        && signature.verify_address(&keypair.address(), &message)?)
+}
+
+/// Check the crate against its compiled-in known answers and a sign/verify
+/// round trip on a throwaway key.
+///
+/// Meant for node startup, before any keys are loaded. The checks run once
+/// per process; later calls return the cached outcome.
+pub fn integrity_check() -> Result<(), Error> {
+    let passed =
+        *RESULT.get_or_init(|| known_answer().unwrap_or(false) && round_trip().unwrap_or(false));
+    if passed {
+        Ok(())
+    } else {
+        Err(Error::IntegrityCheckFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integrity_check() {
+        assert!(known_answer().unwrap());
+        assert!(round_trip().unwrap());
+        integrity_check().unwrap();
+        assert_eq!(RESULT.get(), Some(&true));
+    }
+}
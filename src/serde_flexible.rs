@@ -0,0 +1,177 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `#[serde(with = "cita_ed25519::serde_flexible")]` for keys and signatures
+//! that may arrive in any common encoding.
+//!
+//! Human-readable formats may send a hex string (with or without `0x`), a
+//! base64 string, a byte buffer, or a sequence of integers; the form is
+//! detected from the input. Other formats use the value's own encoding, since
+//! they cannot describe which form follows.
+
+use super::{
+    decode_base64_ct, decode_hex_ct, privkey_from_slice, pubkey_from_slice, Error, PrivKey, PubKey,
+    Signature,
+};
+use serde::de::{Error as SerdeError, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sodiumoxide::utils::memzero;
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Fixed-length byte values the flexible decoder can produce.
+pub trait FixedBytes: Sized {
+    const LEN: usize;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error>;
+}
+
+impl FixedBytes for PubKey {
+    const LEN: usize = 32;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        pubkey_from_slice(bytes)
+    }
+}
+
+impl FixedBytes for PrivKey {
+    const LEN: usize = 64;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        privkey_from_slice(bytes)
+    }
+}
+
+impl FixedBytes for Signature {
+    const LEN: usize = 96;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Signature::try_from(bytes)
+    }
+}
+
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    value.serialize(serializer)
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: FixedBytes + Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    if deserializer.is_human_readable() {
+        deserialize_any(deserializer)
+    } else {
+        T::deserialize(deserializer)
+    }
+}
+
+/// Detect the encoding from the input itself.
+pub(crate) fn deserialize_any<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: FixedBytes,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(FlexibleVisitor(PhantomData))
+}
+
+struct FlexibleVisitor<T>(PhantomData<T>);
+
+impl<T: FixedBytes> FlexibleVisitor<T> {
+    fn check<E: SerdeError>(&self, bytes: &[u8]) -> Result<T, E> {
+        T::from_bytes(bytes).map_err(|_| E::invalid_length(bytes.len(), self))
+    }
+}
+
+impl<'de, T: FixedBytes> Visitor<'de> for FlexibleVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{} bytes as hex, base64, a byte buffer or an integer sequence",
+            T::LEN
+        )
+    }
+
+    fn visit_str<E: SerdeError>(self, s: &str) -> Result<T, E> {
+        // Only the length decides the form, so secrets are decoded in
+        // constant time either way.
+        let has_prefix = s.starts_with("0x");
+        let stripped = s.strip_prefix("0x").unwrap_or(s);
+        let decoded = if has_prefix || stripped.len() == T::LEN * 2 {
+            decode_hex_ct(s)
+        } else {
+            decode_base64_ct(s)
+        };
+        let mut bytes = decoded.map_err(|e| E::custom(e.to_string()))?;
+        let value = self.check(&bytes);
+        memzero(&mut bytes);
+        value
+    }
+
+    fn visit_bytes<E: SerdeError>(self, bytes: &[u8]) -> Result<T, E> {
+        self.check(bytes)
+    }
+
+    fn visit_seq<V: SeqAccess<'de>>(self, mut seq: V) -> Result<T, V::Error> {
+        let mut bytes = Vec::with_capacity(T::LEN);
+        while let Some(byte) = seq.next_element::<u8>()? {
+            if bytes.len() == T::LEN {
+                return Err(SerdeError::invalid_length(T::LEN + 1, &self));
+            }
+            bytes.push(byte);
+        }
+        self.check(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_serialize::base64::{ToBase64, STANDARD};
+    use rustc_serialize::hex::ToHex;
+
+    #[derive(Deserialize)]
+    struct Config {
+        #[serde(with = "crate::serde_flexible")]
+        pubkey: PubKey,
+    }
+
+    #[test]
+    fn test_flexible_forms() {
+        let bytes: Vec<u8> = (0..96u8).collect();
+        let expected = Signature::try_from(&bytes[..]).unwrap();
+        let inputs = [
+            format!("\"{}\"", bytes.to_hex()),
+            format!("\"0x{}\"", bytes.to_hex()),
+            format!("\"{}\"", bytes.to_base64(STANDARD)),
+            serde_json::to_string(&bytes).unwrap(),
+        ];
+        for input in &inputs {
+            let sig: Signature = serde_json::from_str(input).unwrap();
+            assert_eq!(sig, expected);
+        }
+        assert!(serde_json::from_str::<Signature>("\"abcd\"").is_err());
+
+        let config: Config =
+            serde_json::from_str(&format!("{{\"pubkey\":\"{}\"}}", bytes[..32].to_hex())).unwrap();
+        assert_eq!(config.pubkey, PubKey::from_slice(&bytes[..32]));
+    }
+}
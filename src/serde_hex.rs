@@ -0,0 +1,58 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `#[serde(with = "serde_hex")]` for byte buffers, written as plain hex strings.
+//!
+//! Buffers are often secret, such as key shares, so they are decoded with
+//! [`decode_hex_ct`] and the input string is wiped afterwards.
+
+use crate::ct::decode_hex_ct;
+use crate::hex::Hex;
+use serde::de::Error as SerdeError;
+use serde::{Deserialize, Deserializer, Serializer};
+use sodiumoxide::utils::memzero;
+
+pub fn serialize<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_str(&Hex(bytes))
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    let decoded = decode_hex_ct(&s);
+    memzero(&mut s.into_bytes());
+    decoded.map_err(SerdeError::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Share;
+
+    #[test]
+    fn test_serde_hex() {
+        let share: Share = serde_json::from_str(r#"{"index":1,"data":"0x0aFf"}"#).unwrap();
+        assert_eq!(share.data, vec![0x0a, 0xff]);
+        assert_eq!(
+            serde_json::to_string(&share).unwrap(),
+            r#"{"index":1,"data":"0aff"}"#
+        );
+        assert!(serde_json::from_str::<Share>(r#"{"index":1,"data":"0x0x0a"}"#).is_err());
+        assert!(serde_json::from_str::<Share>(r#"{"index":1,"data":"0g"}"#).is_err());
+    }
+}
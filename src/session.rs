@@ -0,0 +1,173 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Error, KeyPair, Message, PubKey, Signature};
+use crate::clock::now;
+use crate::hasher::hash_data;
+use cita_crypto_trait::{CreateKey, Sign};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const SESSION_DOMAIN: &[u8] = b"cita-ed25519 session key";
+
+/// A long-term key that only signs certificates for short-lived session keys.
+pub struct IdentityKey {
+    keypair: KeyPair,
+}
+
+/// The identity key's statement that `session_pubkey` may sign on its behalf
+/// between `not_before` and `not_after`, in seconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionCertificate {
+    pub identity: PubKey,
+    pub session_pubkey: PubKey,
+    pub not_before: u64,
+    pub not_after: u64,
+    pub signature: Signature,
+}
+
+fn binding_hash(identity: &PubKey, session: &PubKey, not_before: u64, not_after: u64) -> Message {
+    let mut s = RlpStream::new_list(5);
+    s.append(&SESSION_DOMAIN);
+    s.append(identity);
+    s.append(session);
+    s.append(&not_before);
+    s.append(&not_after);
+    hash_data(&s.out())
+}
+
+impl IdentityKey {
+    pub fn new(keypair: KeyPair) -> Self {
+        IdentityKey { keypair }
+    }
+
+    pub fn pubkey(&self) -> &PubKey {
+        self.keypair.pubkey()
+    }
+
+    /// Generate a session key valid for `ttl` from now, with the certificate
+    /// binding it to this identity.
+    pub fn issue_session_key(&self, ttl: Duration) -> Result<(KeyPair, SessionCertificate), Error> {
+        let session = KeyPair::gen_keypair();
+        let not_before = now();
+        let not_after = not_before.saturating_add(ttl.as_secs());
+        let message = binding_hash(self.pubkey(), session.pubkey(), not_before, not_after);
+        let certificate = SessionCertificate {
+            identity: *self.pubkey(),
+            session_pubkey: *session.pubkey(),
+            not_before,
+            not_after,
+            signature: Signature::sign(self.keypair.privkey(), &message)?,
+        };
+        Ok((session, certificate))
+    }
+}
+
+impl SessionCertificate {
+    /// Check the identity's signature over the binding, ignoring time.
+    pub fn verify(&self, root_pubkey: &PubKey) -> Result<(), Error> {
+        if &self.identity != root_pubkey {
+            return Err(Error::InvalidPubKey);
+        }
+        let message = binding_hash(
+            &self.identity,
+            &self.session_pubkey,
+            self.not_before,
+            self.not_after,
+        );
+        self.signature.verify_public(root_pubkey, &message)?;
+        Ok(())
+    }
+
+    pub fn is_valid_at(&self, time: u64) -> bool {
+        self.not_before <= time && time <= self.not_after
+    }
+}
+
+/// Check that `signature` over `message` was made by the session key of
+/// `certificate`, that `root_pubkey` issued the certificate and that it has
+/// not expired.
+pub fn verify_session(
+    signature: &Signature,
+    message: &Message,
+    certificate: &SessionCertificate,
+    root_pubkey: &PubKey,
+) -> Result<(), Error> {
+    verify_session_at(signature, message, certificate, root_pubkey, now())
+}
+
+/// [`verify_session`] at `time` seconds since the Unix epoch.
+pub fn verify_session_at(
+    signature: &Signature,
+    message: &Message,
+    certificate: &SessionCertificate,
+    root_pubkey: &PubKey,
+    time: u64,
+) -> Result<(), Error> {
+    certificate.verify(root_pubkey)?;
+    if !certificate.is_valid_at(time) {
+        return Err(Error::Expired);
+    }
+    signature.verify_public(&certificate.session_pubkey, message)?;
+    Ok(())
+}
+
+impl Encodable for SessionCertificate {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(5);
+        s.append(&self.identity);
+        s.append(&self.session_pubkey);
+        s.append(&self.not_before);
+        s.append(&self.not_after);
+        s.append(&self.signature);
+    }
+}
+
+impl Decodable for SessionCertificate {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 5 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(SessionCertificate {
+            identity: rlp.val_at(0)?,
+            session_pubkey: rlp.val_at(1)?,
+            not_before: rlp.val_at(2)?,
+            not_after: rlp.val_at(3)?,
+            signature: rlp.val_at(4)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_key() {
+        let identity = IdentityKey::new(KeyPair::gen_keypair());
+        let (session, certificate) = identity.issue_session_key(Duration::from_secs(60)).unwrap();
+        let certificate: SessionCertificate = rlp::decode(&rlp::encode(&certificate)).unwrap();
+        let msg = Message::from_low_u64_be(3);
+        let sig = Signature::sign(session.privkey(), &msg).unwrap();
+        verify_session(&sig, &msg, &certificate, identity.pubkey()).unwrap();
+
+        let late = certificate.not_after + 1;
+        assert!(verify_session_at(&sig, &msg, &certificate, identity.pubkey(), late).is_err());
+        let other = KeyPair::gen_keypair();
+        assert!(verify_session(&sig, &msg, &certificate, other.pubkey()).is_err());
+        let forged = Signature::sign(other.privkey(), &msg).unwrap();
+        assert!(verify_session(&forged, &msg, &certificate, identity.pubkey()).is_err());
+    }
+}
@@ -0,0 +1,361 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::Error;
+use serde::{Deserialize, Serialize};
+use sodiumoxide::randombytes::randombytes_into;
+use sodiumoxide::utils::memzero;
+use std::fmt;
+
+/// One share of a secret split with [`split`].
+///
+/// Shares are byte-wise Shamir shares over GF(2^8); `index` is the
+/// x-coordinate and is never zero.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Share {
+    pub index: u8,
+    #[serde(with = "crate::serde_hex")]
+    pub data: Vec<u8>,
+}
+
+impl fmt::Debug for Share {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Share")
+            .field("index", &self.index)
+            .field("len", &self.data.len())
+            .finish()
+    }
+}
+
+impl Drop for Share {
+    fn drop(&mut self) {
+        memzero(&mut self.data);
+    }
+}
+
+/// What one old holder deals to one new holder in [`Share::reshare`].
+///
+/// `dealer` is the old holder's index and `signers` the sorted indices of
+/// every old holder taking part, so [`combine_reshares`] can tell a missing
+/// or repeated sub-share.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubShare {
+    pub dealer: u8,
+    pub signers: Vec<u8>,
+    pub share: Share,
+}
+
+// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1, without tables or
+// secret-dependent branches.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut p = 0u8;
+    for _ in 0..8 {
+        p ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    p
+}
+
+// a^254 == a^-1 for a != 0.
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1u8;
+    let mut base = a;
+    let mut exp = 254u8;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Split `secret` into `count` shares, any `threshold` of which rebuild it.
+pub fn split(secret: &[u8], threshold: u8, count: u8) -> Result<Vec<Share>, Error> {
+    if count < threshold {
+        return Err(Error::InvalidShares);
+    }
+    let indices: Vec<u8> = (1..=count).collect();
+    split_at(secret, threshold, &indices)
+}
+
+// Shares of `secret` at the given x-coordinates.
+fn split_at(secret: &[u8], threshold: u8, indices: &[u8]) -> Result<Vec<Share>, Error> {
+    if threshold == 0 || indices.contains(&0) {
+        return Err(Error::InvalidShares);
+    }
+    let degree = threshold as usize - 1;
+    let mut coefficients = vec![0u8; secret.len() * degree];
+    randombytes_into(&mut coefficients);
+
+    let shares = indices
+        .iter()
+        .map(|&x| {
+            let data = secret
+                .iter()
+                .enumerate()
+                .map(|(i, s)| {
+                    // Horner's rule over s + c1*x + ... + c(t-1)*x^(t-1).
+                    let poly = &coefficients[i * degree..(i + 1) * degree];
+                    let high = poly.iter().rev().fold(0u8, |acc, c| gf_mul(acc, x) ^ c);
+                    gf_mul(high, x) ^ s
+                })
+                .collect();
+            Share { index: x, data }
+        })
+        .collect();
+    memzero(&mut coefficients);
+    Ok(shares)
+}
+
+// Lagrange basis polynomial for `index` over `indices`, evaluated at x = 0.
+fn lagrange_at_zero(index: u8, indices: &[u8]) -> u8 {
+    indices
+        .iter()
+        .filter(|&&other| other != index)
+        .fold(1u8, |acc, &other| {
+            gf_mul(acc, gf_mul(other, gf_inv(other ^ index)))
+        })
+}
+
+fn check_shares(shares: &[Share]) -> Result<usize, Error> {
+    let first = shares.first().ok_or(Error::InvalidShares)?;
+    let len = first.data.len();
+    for (i, share) in shares.iter().enumerate() {
+        if share.index == 0
+            || share.data.len() != len
+            || shares[..i].iter().any(|s| s.index == share.index)
+        {
+            return Err(Error::InvalidShares);
+        }
+    }
+    Ok(len)
+}
+
+/// Rebuild a secret from at least `threshold` distinct shares.
+///
+/// Shares carry no threshold information, so the caller passes the one used
+/// to split; fewer distinct shares than that fail with `InvalidShares`
+/// instead of yielding an unrelated value.
+pub fn combine(shares: &[Share], threshold: u8) -> Result<Vec<u8>, Error> {
+    let len = check_shares(shares)?;
+    if threshold == 0 || shares.len() < usize::from(threshold) {
+        return Err(Error::InvalidShares);
+    }
+    let indices: Vec<u8> = shares.iter().map(|share| share.index).collect();
+    let mut secret = vec![0u8; len];
+    for share in shares {
+        let basis = lagrange_at_zero(share.index, &indices);
+        for (out, y) in secret.iter_mut().zip(&share.data) {
+            *out ^= gf_mul(basis, *y);
+        }
+    }
+    Ok(secret)
+}
+
+/// Shares of zero for the holders at `indices`, from one refresh dealer.
+///
+/// In a proactive refresh every holder deals such a set and each holder
+/// folds in what it receives with [`Share::apply_refresh`]. The secret is
+/// unchanged while shares leaked before the refresh become useless.
+pub fn refresh_deltas(len: usize, threshold: u8, indices: &[u8]) -> Result<Vec<Share>, Error> {
+    split_at(&vec![0u8; len], threshold, indices)
+}
+
+/// Re-randomize all `shares` at once, for a single party holding them.
+pub fn refresh(shares: &[Share], threshold: u8) -> Result<Vec<Share>, Error> {
+    let len = check_shares(shares)?;
+    let indices: Vec<u8> = shares.iter().map(|share| share.index).collect();
+    let deltas = refresh_deltas(len, threshold, &indices)?;
+    let mut refreshed = shares.to_vec();
+    for (share, delta) in refreshed.iter_mut().zip(&deltas) {
+        share.apply_refresh(delta)?;
+    }
+    Ok(refreshed)
+}
+
+impl Share {
+    /// Fold in a share of zero dealt for this holder.
+    pub fn apply_refresh(&mut self, delta: &Share) -> Result<(), Error> {
+        if delta.index != self.index || delta.data.len() != self.data.len() {
+            return Err(Error::InvalidShares);
+        }
+        for (out, d) in self.data.iter_mut().zip(&delta.data) {
+            *out ^= d;
+        }
+        Ok(())
+    }
+
+    /// Deal this share to a new group of `new_count` holders with threshold
+    /// `new_threshold`.
+    ///
+    /// `signers` are the distinct indices of the old holders taking part, at
+    /// least the old threshold of them, each calling this once. The sub-share
+    /// at position `j` goes to new holder `j + 1`, who joins what it receives
+    /// from every signer with [`combine_reshares`]. The secret, and so the
+    /// public key, stays the same.
+    pub fn reshare(
+        &self,
+        signers: &[u8],
+        new_threshold: u8,
+        new_count: u8,
+    ) -> Result<Vec<SubShare>, Error> {
+        let repeated = signers
+            .iter()
+            .enumerate()
+            .any(|(i, x)| *x == 0 || signers[..i].contains(x));
+        if repeated || !signers.contains(&self.index) || new_count < new_threshold {
+            return Err(Error::InvalidShares);
+        }
+        let basis = lagrange_at_zero(self.index, signers);
+        let mut weighted: Vec<u8> = self.data.iter().map(|y| gf_mul(basis, *y)).collect();
+        let indices: Vec<u8> = (1..=new_count).collect();
+        let shares = split_at(&weighted, new_threshold, &indices);
+        memzero(&mut weighted);
+        let mut sorted = signers.to_vec();
+        sorted.sort_unstable();
+        Ok(shares?
+            .into_iter()
+            .map(|share| SubShare {
+                dealer: self.index,
+                signers: sorted.clone(),
+                share,
+            })
+            .collect())
+    }
+}
+
+/// Join the sub-shares one new holder received during [`Share::reshare`]
+/// into its new share.
+///
+/// Each sub-share is weighted for the exact set of signers it names, so the
+/// result is only a share of the old secret if there is exactly one
+/// sub-share from every signer, all naming the same set of at least
+/// `old_threshold` signers. Anything else fails with `InvalidShares`.
+pub fn combine_reshares(subshares: &[SubShare], old_threshold: u8) -> Result<Share, Error> {
+    let first = subshares.first().ok_or(Error::InvalidShares)?;
+    let signers = &first.signers;
+    let mut dealers: Vec<u8> = subshares.iter().map(|sub| sub.dealer).collect();
+    dealers.sort_unstable();
+    if old_threshold == 0
+        || signers.len() < usize::from(old_threshold)
+        || dealers != *signers
+        || subshares.iter().any(|sub| sub.signers != *signers)
+    {
+        return Err(Error::InvalidShares);
+    }
+    let mut share = Share {
+        index: first.share.index,
+        data: vec![0u8; first.share.data.len()],
+    };
+    for subshare in subshares {
+        share.apply_refresh(&subshare.share)?;
+    }
+    Ok(share)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gf_inverse() {
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+        }
+    }
+
+    #[test]
+    fn test_split_combine() {
+        let secret = b"a thirty-two byte ed25519 seed!!";
+        let shares = split(secret, 3, 5).unwrap();
+        assert_eq!(combine(&shares[1..4], 3).unwrap(), secret);
+        assert_eq!(
+            combine(
+                &[shares[4].clone(), shares[0].clone(), shares[2].clone()],
+                3
+            )
+            .unwrap(),
+            secret
+        );
+        assert!(combine(&shares[..2], 3).is_err());
+        assert!(combine(&shares[..2], 0).is_err());
+        assert!(combine(&[shares[0].clone(), shares[0].clone()], 2).is_err());
+    }
+
+    #[test]
+    fn test_refresh_and_reshare() {
+        use crate::KeyPair;
+        use cita_crypto_trait::CreateKey;
+
+        let seed = [7u8; 32];
+        let pubkey = *KeyPair::from_seed(&seed).pubkey();
+        let shares = split(&seed, 3, 5).unwrap();
+
+        let refreshed = refresh(&shares, 3).unwrap();
+        assert_ne!(refreshed[0].data, shares[0].data);
+        assert_eq!(combine(&refreshed[2..], 3).unwrap(), seed);
+        // Mixing shares from before and after a refresh gives nothing useful.
+        let mixed = [
+            shares[0].clone(),
+            refreshed[1].clone(),
+            refreshed[2].clone(),
+        ];
+        assert_ne!(combine(&mixed, 3).unwrap(), seed);
+
+        // Old holders 1, 3 and 4 move the key to a 2-of-3 group.
+        let signers = [1, 3, 4];
+        let dealt: Vec<Vec<SubShare>> = signers
+            .iter()
+            .map(|&i| refreshed[i as usize - 1].reshare(&signers, 2, 3).unwrap())
+            .collect();
+        let new_shares: Vec<Share> = (0..3)
+            .map(|j| {
+                let received: Vec<SubShare> = dealt.iter().map(|d| d[j].clone()).collect();
+                combine_reshares(&received, 3).unwrap()
+            })
+            .collect();
+        let mut recovered = [0u8; 32];
+        recovered.copy_from_slice(&combine(&new_shares[1..], 2).unwrap());
+        assert_eq!(*KeyPair::from_seed(&recovered).pubkey(), pubkey);
+        assert!(refreshed[1].reshare(&signers, 2, 3).is_err());
+        assert!(refreshed[0].reshare(&[1, 1, 3], 2, 3).is_err());
+        let partial: Vec<SubShare> = dealt[..2].iter().map(|d| d[0].clone()).collect();
+        assert!(combine_reshares(&partial, 3).is_err());
+
+        // A repeated sub-share in place of a missing signer's is caught even
+        // though the count reaches the threshold.
+        let repeated = vec![
+            dealt[0][0].clone(),
+            dealt[0][0].clone(),
+            dealt[1][0].clone(),
+        ];
+        assert!(combine_reshares(&repeated, 3).is_err());
+
+        // Four signers with an old threshold of three: three sub-shares are
+        // not enough, since each was weighted for all four.
+        let signers = [1, 2, 3, 4];
+        let dealt: Vec<Vec<SubShare>> = signers
+            .iter()
+            .map(|&i| refreshed[i as usize - 1].reshare(&signers, 2, 3).unwrap())
+            .collect();
+        let missing: Vec<SubShare> = dealt[..3].iter().map(|d| d[0].clone()).collect();
+        assert!(combine_reshares(&missing, 3).is_err());
+        let all: Vec<SubShare> = dealt.iter().map(|d| d[0].clone()).collect();
+        assert!(combine_reshares(&all, 3).is_ok());
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Error, Message, PrivKey, PubKey, Signature};
+use crate::hasher::hash_data;
+use cita_crypto_trait::Sign;
+use rlp::{Encodable, RlpStream};
+
+pub use cita_ed25519_derive::Signable;
+
+/// Types with one canonical digest for signing, usually derived with
+/// `#[derive(Signable)]` so signer and verifier cannot drift apart.
+pub trait Signable {
+    fn signing_hash(&self) -> Message;
+
+    fn sign_with(&self, privkey: &PrivKey) -> Result<Signature, Error> {
+        Signature::sign(privkey, &self.signing_hash())
+    }
+
+    fn verify_signature(&self, signature: &Signature, pubkey: &PubKey) -> Result<bool, Error> {
+        signature.verify_public(pubkey, &self.signing_hash())
+    }
+}
+
+/// The canonical encoding behind `#[derive(Signable)]`: an RLP list of the
+/// struct name followed by a `[name, value]` pair per field, hashed with the
+/// crate's hash.
+pub struct SigningHasher {
+    name: String,
+    fields: Vec<(String, Vec<u8>)>,
+}
+
+impl SigningHasher {
+    pub fn new(name: &str) -> Self {
+        SigningHasher {
+            name: name.to_owned(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn field<T: Encodable>(mut self, name: &str, value: &T) -> Self {
+        self.fields
+            .push((name.to_owned(), rlp::encode(value).to_vec()));
+        self
+    }
+
+    pub fn finish(self) -> Message {
+        let mut s = RlpStream::new_list(1 + self.fields.len());
+        s.append(&self.name);
+        for (name, value) in &self.fields {
+            s.begin_list(2);
+            s.append(name);
+            s.append_raw(value, 1);
+        }
+        hash_data(&s.out())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+    use cita_crypto_trait::CreateKey;
+
+    #[derive(Signable)]
+    struct Proposal {
+        height: u64,
+        #[signable(rename = "block")]
+        block_hash: Message,
+        #[signable(skip)]
+        #[allow(dead_code)]
+        received_at: u64,
+    }
+
+    #[test]
+    fn test_derive_signable() {
+        let proposal = Proposal {
+            height: 5,
+            block_hash: Message::from_low_u64_be(1),
+            received_at: 100,
+        };
+        let expected = SigningHasher::new("Proposal")
+            .field("height", &5u64)
+            .field("block", &Message::from_low_u64_be(1))
+            .finish();
+        assert_eq!(proposal.signing_hash(), expected);
+
+        let later = Proposal {
+            received_at: 200,
+            ..proposal
+        };
+        assert_eq!(later.signing_hash(), expected);
+
+        let keypair = KeyPair::gen_keypair();
+        let sig = later.sign_with(keypair.privkey()).unwrap();
+        assert!(later.verify_signature(&sig, keypair.pubkey()).unwrap());
+    }
+}
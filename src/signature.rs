@@ -15,20 +15,67 @@
 use super::{
     pubkey_to_address, Address, Error, KeyPair, Message, PrivKey, PubKey, SIGNATURE_BYTES_LEN,
 };
+use crate::curve::{
+    base_mul, canonical_point, hash_to_scalar, is_canonical_scalar, reduce32, scalar_add,
+    scalar_mul, ExpandedSecret,
+};
+use crate::hex::Hex;
+use crate::meter::{charge, Operation};
 use cita_crypto_trait::{CreateKey, Sign};
+use libsodium_sys as ffi;
 use rlp::*;
-use rustc_serialize::hex::ToHex;
-use serde::de::{Error as SerdeError, SeqAccess, Visitor};
-use serde::ser::SerializeSeq;
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use sodiumoxide::crypto::sign::{
-    sign_detached, verify_detached, PublicKey as EdPublicKey, SecretKey, Signature as EdSignature,
-};
-
-use std::fmt;
+use serde::{Deserialize, Deserializer, Serialize};
+use sodiumoxide::crypto::sign::{sign_detached, SecretKey};
+use sodiumoxide::randombytes::randombytes_into;
+use sodiumoxide::utils::{memcmp, memzero};
+
+use std::borrow::Borrow;
+use std::convert::TryFrom;
+use std::fmt::{self, Write};
 use std::ops::{Deref, DerefMut};
 
-pub struct Signature(pub [u8; 96]);
+/// Check the first 64 bytes of `signature` against `pubkey`, in place.
+pub(crate) fn verify_detached_bytes(
+    signature: &[u8; SIGNATURE_BYTES_LEN],
+    pubkey: &[u8; 32],
+    message: &Message,
+) -> bool {
+    charge(Operation::Verify, 1);
+    let ret = unsafe {
+        ffi::crypto_sign_ed25519_verify_detached(
+            signature.as_ptr(),
+            message.as_ptr(),
+            message.as_bytes().len() as u64,
+            pubkey.as_ptr(),
+        )
+    };
+    #[cfg(feature = "tracing")]
+    crate::trace::verify(pubkey, message, ret == 0);
+    ret == 0
+}
+
+#[derive(Serialize)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct Signature(#[serde(with = "crate::serde_array")] pub [u8; 96]);
+
+/// Human-readable formats accept any of the forms listed in
+/// [`crate::serde_flexible`]; binary formats expect the byte sequence
+/// `Serialize` writes.
+impl<'de> Deserialize<'de> for Signature {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            crate::serde_flexible::deserialize_any(deserializer)
+        } else {
+            crate::serde_array::deserialize(deserializer).map(Signature)
+        }
+    }
+}
 
 impl Signature {
     pub fn sig(&self) -> &[u8] {
@@ -38,6 +85,117 @@ impl Signature {
     pub fn pk(&self) -> &[u8] {
         &self.0[64..96]
     }
+
+    /// Verify against `pubkey` alone, treating the embedded public key as an
+    /// untrusted hint. Unlike `verify_public`, a signature whose trailing 32
+    /// bytes name a different key is still accepted if the 64-byte
+    /// signature itself is valid for `pubkey`.
+    pub fn verify_with(&self, pubkey: &PubKey, message: &Message) -> Result<bool, Error> {
+        if self.verify_detached_raw(&pubkey.0, message) {
+            Ok(true)
+        } else {
+            Err(Error::InvalidSignature)
+        }
+    }
+
+    /// Check the 64-byte signature against `pubkey` directly on the borrowed
+    /// buffers, without copying them into sodiumoxide's owned key and
+    /// signature types.
+    fn verify_detached_raw(&self, pubkey: &[u8; 32], message: &Message) -> bool {
+        verify_detached_bytes(&self.0, pubkey, message)
+    }
+
+    fn embedded_pubkey(&self) -> Result<&[u8; 32], Error> {
+        <&[u8; 32]>::try_from(self.pk()).map_err(|_| Error::InvalidPubKey)
+    }
+
+    /// Whether the embedded public key is `pubkey`, for callers of
+    /// [`Signature::verify_with`] that want to flag mismatching hints.
+    pub fn embedded_pubkey_matches(&self, pubkey: &PubKey) -> bool {
+        memcmp(self.pk(), pubkey.as_ref())
+    }
+
+    /// Whether this is the canonical encoding of the signature, see
+    /// [`Signature::normalized`].
+    pub fn is_normalized(&self) -> bool {
+        *self == self.normalized()
+    }
+
+    /// The canonical encoding of this signature: S reduced modulo the group
+    /// order, and R and the embedded public key with their y-coordinates
+    /// reduced modulo p.
+    ///
+    /// Lenient verifiers accept several byte strings for the same signature;
+    /// normalizing before hashing or deduplicating makes them compare equal.
+    /// Signatures produced by this crate are already normalized.
+    pub fn normalized(&self) -> Signature {
+        let mut r = [0u8; 32];
+        let mut s = [0u8; 32];
+        let mut pk = [0u8; 32];
+        r.copy_from_slice(&self.0[0..32]);
+        s.copy_from_slice(&self.0[32..64]);
+        pk.copy_from_slice(&self.0[64..96]);
+        if !is_canonical_scalar(&s) {
+            s = reduce32(&s);
+        }
+
+        let mut ret = [0u8; 96];
+        ret[0..32].copy_from_slice(&canonical_point(&r));
+        ret[32..64].copy_from_slice(&s);
+        ret[64..96].copy_from_slice(&canonical_point(&pk));
+        Signature(ret)
+    }
+
+    /// Sign with a hedged nonce: fresh randomness is hashed together with the
+    /// usual RFC 8032 nonce prefix, so a fault injected while signing the
+    /// same message twice cannot expose the key. The result verifies like
+    /// any other signature but is no longer deterministic.
+    ///
+    /// Like `sign`, fails with `InvalidPrivKey` when the public half of
+    /// `privkey` does not belong to its seed.
+    pub fn sign_hedged(privkey: &PrivKey, message: &Message) -> Result<Self, Error> {
+        let secret = ExpandedSecret::from_seed(&privkey.0[0..32]);
+        if !memcmp(&base_mul(&secret.scalar)?, &privkey.0[32..]) {
+            return Err(Error::InvalidPrivKey);
+        }
+        let mut noise = [0u8; 32];
+        randombytes_into(&mut noise);
+        let signature = Self::sign_expanded(&secret, &[&noise, &secret.prefix], message);
+        memzero(&mut noise);
+        signature
+    }
+
+    /// Sign with an already expanded secret, hashing `nonce_parts` and the
+    /// message into the nonce.
+    pub(crate) fn sign_expanded(
+        secret: &ExpandedSecret,
+        nonce_parts: &[&[u8]],
+        message: &Message,
+    ) -> Result<Self, Error> {
+        let pubkey = base_mul(&secret.scalar)?;
+        let mut parts = nonce_parts.to_vec();
+        parts.push(message.as_ref());
+        let mut r = hash_to_scalar(&parts);
+        let big_r = match base_mul(&r) {
+            Ok(point) => point,
+            Err(e) => {
+                memzero(&mut r);
+                return Err(e);
+            }
+        };
+        let k = hash_to_scalar(&[&big_r, &pubkey, message.as_ref()]);
+        let s = scalar_add(&r, &scalar_mul(&k, &secret.scalar));
+        memzero(&mut r);
+        charge(Operation::Sign, 1);
+        #[cfg(feature = "tracing")]
+        crate::trace::sign(&pubkey, message);
+
+        let mut ret = [0u8; 96];
+        ret[0..32].copy_from_slice(&big_r);
+        ret[32..64].copy_from_slice(&s);
+        ret[64..96].copy_from_slice(&pubkey);
+        Ok(Signature(ret))
+    }
 }
 
 impl PartialEq for Signature {
@@ -49,9 +207,7 @@ impl PartialEq for Signature {
 impl Decodable for Signature {
     fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
         rlp.decoder().decode_value(|bytes| {
-            let mut sig = [0u8; 96];
-            sig[0..96].copy_from_slice(bytes);
-            Ok(Signature(sig))
+            Signature::try_from(bytes).map_err(|_| DecoderError::RlpInvalidLength)
         })
     }
 }
@@ -62,52 +218,13 @@ impl Encodable for Signature {
     }
 }
 
-// TODO: Maybe it should be implemented with rust macro
-// https://github.com/rust-lang/rfcs/issues/1038
-impl<'de> Deserialize<'de> for Signature {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        struct SignatureVisitor;
-
-        impl<'de> Visitor<'de> for SignatureVisitor {
-            type Value = Signature;
-
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("ed25519 signature")
-            }
-
-            fn visit_seq<V>(self, mut visitor: V) -> Result<Self::Value, V::Error>
-            where
-                V: SeqAccess<'de>,
-            {
-                let mut signature = Signature([0u8; SIGNATURE_BYTES_LEN]);
-                for i in 0..SIGNATURE_BYTES_LEN {
-                    signature.0[i] = match visitor.next_element()? {
-                        Some(val) => val,
-                        None => return Err(SerdeError::invalid_length(SIGNATURE_BYTES_LEN, &self)),
-                    }
-                }
-                Ok(signature)
-            }
-        }
-
-        let visitor = SignatureVisitor;
-        deserializer.deserialize_seq(visitor)
-    }
-}
-
-impl Serialize for Signature {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut seq = serializer.serialize_seq(Some(SIGNATURE_BYTES_LEN))?;
-        for i in 0..SIGNATURE_BYTES_LEN {
-            seq.serialize_element(&self.0[i])?;
+impl Signature {
+    /// Append `signatures` as one RLP list, e.g. the votes in a block body.
+    pub fn rlp_append_list(signatures: &[Signature], s: &mut RlpStream) {
+        s.begin_list(signatures.len());
+        for signature in signatures {
+            s.append(signature);
         }
-        seq.end()
     }
 }
 
@@ -116,15 +233,15 @@ impl Eq for Signature {}
 impl fmt::Debug for Signature {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         f.debug_struct("Signature")
-            .field("signature", &self.0[0..64].to_hex())
-            .field("pubkey", &self.0[64..96].to_hex())
+            .field("signature", &Hex(&self.0[0..64]))
+            .field("pubkey", &Hex(&self.0[64..96]))
             .finish()
     }
 }
 
 impl fmt::Display for Signature {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "{}", self.0[..].to_hex())
+        fmt::Display::fmt(&Hex(&self.0), f)
     }
 }
 
@@ -146,39 +263,62 @@ impl From<[u8; 96]> for Signature {
     }
 }
 
-impl Into<[u8; 96]> for Signature {
-    fn into(self) -> [u8; 96] {
-        self.0
+impl From<Signature> for [u8; 96] {
+    fn from(sig: Signature) -> Self {
+        sig.0
     }
 }
 
-impl<'a> From<&'a [u8]> for Signature {
-    fn from(slice: &'a [u8]) -> Signature {
-        assert_eq!(slice.len(), SIGNATURE_BYTES_LEN);
+impl<'a> TryFrom<&'a [u8]> for Signature {
+    type Error = Error;
+
+    fn try_from(slice: &'a [u8]) -> Result<Self, Self::Error> {
+        if slice.len() != SIGNATURE_BYTES_LEN {
+            return Err(Error::InvalidLength);
+        }
         let mut bytes = [0u8; 96];
         bytes.copy_from_slice(slice);
-        Signature(bytes)
+        Ok(Signature(bytes))
     }
 }
 
-impl<'a> Into<&'a [u8]> for &'a Signature {
-    fn into(self) -> &'a [u8] {
+impl TryFrom<Vec<u8>> for Signature {
+    type Error = Error;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        Signature::try_from(&bytes[..])
+    }
+}
+
+impl AsRef<[u8]> for Signature {
+    fn as_ref(&self) -> &[u8] {
         &self.0[..]
     }
 }
 
+impl Borrow<[u8]> for Signature {
+    fn borrow(&self) -> &[u8] {
+        &self.0[..]
+    }
+}
+
+impl<'a> From<&'a Signature> for &'a [u8] {
+    fn from(sig: &'a Signature) -> Self {
+        &sig.0[..]
+    }
+}
+
 impl fmt::LowerHex for Signature {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for i in &self.0[..] {
-            write!(f, "{:02x}", i)?;
-        }
-        Ok(())
+        fmt::Display::fmt(&Hex(&self.0), f)
     }
 }
 
 impl From<Signature> for String {
     fn from(s: Signature) -> Self {
-        format!("{:x}", s)
+        let mut hex = String::with_capacity(SIGNATURE_BYTES_LEN * 2);
+        let _ = write!(hex, "{}", Hex(&s.0));
+        hex
     }
 }
 
@@ -203,12 +343,24 @@ impl Sign for Signature {
     type Error = Error;
     type Address = Address;
 
+    /// Fails with `InvalidPrivKey` when the public half of `privkey` does not
+    /// belong to its seed, which would otherwise yield signatures that never
+    /// verify.
     fn sign(privkey: &Self::PrivKey, message: &Self::Message) -> Result<Self, Self::Error> {
-        let keypair = KeyPair::from_privkey(*privkey)?;
-        let secret_key = SecretKey::from_slice(privkey.as_ref()).unwrap();
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&privkey.0[..32]);
+        let keypair = KeyPair::from_seed(&seed);
+        memzero(&mut seed);
+        if !memcmp(keypair.privkey().as_ref(), privkey.as_ref()) {
+            return Err(Error::InvalidPrivKey);
+        }
+        let secret_key = SecretKey::from_slice(privkey.as_ref()).ok_or(Error::InvalidPrivKey)?;
         let pubkey = keypair.pubkey();
         let mut ret = [0u8; 96];
         let sig = sign_detached(message.as_ref(), &secret_key);
+        charge(Operation::Sign, 1);
+        #[cfg(feature = "tracing")]
+        crate::trace::sign(pubkey.as_ref(), message);
 
         ret[0..64].copy_from_slice(sig.as_ref());
         ret[64..96].copy_from_slice(pubkey.as_ref());
@@ -216,22 +368,11 @@ impl Sign for Signature {
     }
 
     fn recover(&self, message: &Self::Message) -> Result<Self::PubKey, Self::Error> {
-        let sig = self.sig();
-        let pubkey = self.pk();
-
-        let mut sig_array = [0; 64];
-        sig_array.copy_from_slice(sig);
-
-        let is_valid = verify_detached(
-            &EdSignature::new(sig_array),
-            message.as_ref(),
-            &EdPublicKey::from_slice(pubkey).unwrap(),
-        );
-
-        if !is_valid {
-            Err(Error::InvalidSignature)
+        let pubkey = self.embedded_pubkey()?;
+        if self.verify_detached_raw(pubkey, message) {
+            Ok(PubKey::from(*pubkey))
         } else {
-            Ok(PubKey::from_slice(pubkey))
+            Err(Error::InvalidSignature)
         }
     }
 
@@ -240,39 +381,31 @@ impl Sign for Signature {
         pubkey: &Self::PubKey,
         message: &Self::Message,
     ) -> Result<bool, Self::Error> {
-        let sig = self.sig();
-        let pk = self.pk();
-
-        let mut sig_array = [0; 64];
-        sig_array.copy_from_slice(sig);
-
-        if pk != pubkey.as_ref() as &[u8] {
+        if !memcmp(self.pk(), pubkey.as_ref()) {
             return Err(Error::InvalidPubKey);
         }
-
-        let is_valid = verify_detached(
-            &EdSignature::new(sig_array),
-            message.as_ref(),
-            &EdPublicKey::from_slice(pubkey.as_ref()).unwrap(),
-        );
-        if !is_valid {
-            Err(Error::InvalidSignature)
-        } else {
+        if self.verify_detached_raw(&pubkey.0, message) {
             Ok(true)
+        } else {
+            Err(Error::InvalidSignature)
         }
     }
 
+    /// The embedded public key is hashed and compared first, so a signature
+    /// from any other address is rejected without touching the curve.
     fn verify_address(&self, address: &Address, message: &Message) -> Result<bool, Self::Error> {
-        let pubkey = self.recover(message)?;
-        let recover_address = pubkey_to_address(&pubkey);
-        Ok(address == &recover_address)
+        let pubkey = PubKey::from_slice(self.pk());
+        if !memcmp(pubkey_to_address(&pubkey).as_ref(), address.as_ref()) {
+            return Ok(false);
+        }
+        self.verify_public(&pubkey, message)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use bincode::{deserialize, serialize, Infinite};
+    use bincode::{deserialize, serialize};
     use cita_crypto_trait::CreateKey;
 
     const MESSAGE: [u8; 32] = [
@@ -289,6 +422,105 @@ mod tests {
         assert!(sig.verify_public(keypair.pubkey(), &msg).unwrap());
     }
 
+    #[test]
+    fn test_verify_with_ignores_embedded_pubkey() {
+        let keypair = KeyPair::gen_keypair();
+        let other = KeyPair::gen_keypair();
+        let msg = Message::from_slice(&MESSAGE[..]);
+        let mut sig = Signature::sign(keypair.privkey(), &msg).unwrap();
+        sig.0[64..96].copy_from_slice(other.pubkey().as_ref());
+
+        assert!(!sig.embedded_pubkey_matches(keypair.pubkey()));
+        assert!(sig.verify_public(keypair.pubkey(), &msg).is_err());
+        assert!(sig.verify_with(keypair.pubkey(), &msg).unwrap());
+        assert!(sig.verify_with(other.pubkey(), &msg).is_err());
+    }
+
+    #[test]
+    fn test_normalized() {
+        let keypair = KeyPair::gen_keypair();
+        let msg = Message::from_slice(&MESSAGE[..]);
+        let sig = Signature::sign(keypair.privkey(), &msg).unwrap();
+        assert!(sig.is_normalized());
+
+        // S + L encodes the same scalar.
+        let mut malleated = sig.clone();
+        let mut carry = 0u16;
+        for (i, l) in crate::curve::GROUP_ORDER.iter().enumerate() {
+            let sum = u16::from(malleated.0[32 + i]) + u16::from(*l) + carry;
+            malleated.0[32 + i] = sum as u8;
+            carry = sum >> 8;
+        }
+        assert!(!malleated.is_normalized());
+        assert_eq!(malleated.normalized(), sig);
+
+        // y = p + 1 is a non-canonical encoding of y = 1.
+        let mut odd_pk = sig.clone();
+        odd_pk.0[64] = 0xee;
+        for b in &mut odd_pk.0[65..96] {
+            *b = 0xff;
+        }
+        odd_pk.0[95] = 0x7f;
+        let normalized = odd_pk.normalized();
+        assert_eq!(normalized.pk()[0], 1);
+        assert!(normalized.pk()[1..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn test_sign_hedged() {
+        let keypair = KeyPair::gen_keypair();
+        let msg = Message::from_slice(&MESSAGE[..]);
+        let sig1 = Signature::sign_hedged(keypair.privkey(), &msg).unwrap();
+        let sig2 = Signature::sign_hedged(keypair.privkey(), &msg).unwrap();
+        assert_ne!(sig1, sig2);
+        assert!(sig1.verify_public(keypair.pubkey(), &msg).unwrap());
+        assert!(sig2.verify_public(keypair.pubkey(), &msg).unwrap());
+
+        let mut corrupted = *keypair.privkey();
+        corrupted.0[40] ^= 1;
+        assert!(matches!(
+            Signature::sign_hedged(&corrupted, &msg),
+            Err(Error::InvalidPrivKey)
+        ));
+    }
+
+    #[test]
+    fn test_verify_public_rejects_other_pubkey() {
+        let keypair = KeyPair::gen_keypair();
+        let msg = Message::from_slice(&MESSAGE[..]);
+        let sig = Signature::sign(keypair.privkey(), &msg).unwrap();
+        assert!(sig.embedded_pubkey_matches(keypair.pubkey()));
+
+        let mut other = *keypair.pubkey();
+        other.0[31] ^= 1;
+        assert!(!sig.embedded_pubkey_matches(&other));
+        assert!(matches!(
+            sig.verify_public(&other, &msg),
+            Err(Error::InvalidPubKey)
+        ));
+    }
+
+    #[test]
+    fn test_corrupted_keys() {
+        let keypair = KeyPair::gen_keypair();
+        let msg = Message::from_slice(&MESSAGE[..]);
+        for i in [0, 31, 32, 63] {
+            let mut corrupted = *keypair.privkey();
+            corrupted.0[i] ^= 0x40;
+            assert!(matches!(
+                Signature::sign(&corrupted, &msg),
+                Err(Error::InvalidPrivKey)
+            ));
+        }
+
+        let mut sig = Signature::sign(keypair.privkey(), &msg).unwrap();
+        sig.0[64..].copy_from_slice(&[0xff; 32]);
+        assert!(sig.recover(&msg).is_err());
+        assert!(sig
+            .verify_public(&PubKey::from_slice(&[0xff; 32]), &msg)
+            .is_err());
+    }
+
     #[test]
     fn test_verify_address() {
         let keypair = KeyPair::gen_keypair();
@@ -296,6 +528,11 @@ mod tests {
         let msg = Message::from_slice(&MESSAGE[..]);
         let sig = Signature::sign(keypair.privkey(), &msg).unwrap();
         assert!(sig.verify_address(&address, &msg).unwrap());
+        assert!(!sig.verify_address(&Address::zero(), &msg).unwrap());
+
+        let mut forged = sig.clone();
+        forged.0[0] ^= 1;
+        assert!(forged.verify_address(&address, &msg).is_err());
     }
 
     #[test]
@@ -313,7 +550,39 @@ mod tests {
         let sig = Signature::sign(keypair.privkey(), &msg).unwrap();
         let sig = &sig;
         let slice: &[u8] = sig.into();
-        assert_eq!(Signature::from(slice), *sig);
+        assert_eq!(Signature::try_from(slice).unwrap(), *sig);
+        assert!(matches!(
+            Signature::try_from(&slice[1..]),
+            Err(Error::InvalidLength)
+        ));
+    }
+
+    #[test]
+    fn test_byte_conversions() {
+        let keypair = KeyPair::gen_keypair();
+        let sig = Signature::sign(keypair.privkey(), &Message::from_slice(&MESSAGE[..])).unwrap();
+        let bytes: &[u8] = sig.as_ref();
+        let borrowed: &[u8] = sig.borrow();
+        assert_eq!(bytes, borrowed);
+        assert_eq!(Signature::try_from(bytes.to_vec()).unwrap(), sig);
+        assert!(Signature::try_from(bytes[1..].to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_rlp() {
+        let keypair = KeyPair::gen_keypair();
+        let msg = Message::from_slice(&MESSAGE[..]);
+        let sig = Signature::sign(keypair.privkey(), &msg).unwrap();
+        assert_eq!(rlp::decode::<Signature>(&rlp::encode(&sig)).unwrap(), sig);
+        let short = rlp::encode(&sig.0[..95].to_vec());
+        assert!(rlp::decode::<Signature>(&short).is_err());
+
+        let sigs = vec![sig.clone(), Signature::default(), sig];
+        let mut s = RlpStream::new();
+        Signature::rlp_append_list(&sigs, &mut s);
+        let encoded = s.out();
+        assert_eq!(encoded, rlp::encode_list::<Signature, _>(&sigs));
+        assert_eq!(rlp::decode_list::<Signature>(&encoded), sigs);
     }
 
     #[test]
@@ -321,7 +590,10 @@ mod tests {
         let keypair = KeyPair::gen_keypair();
         let msg = Message::from_slice(&MESSAGE[..]);
         let sig = Signature::sign(keypair.privkey(), &msg).unwrap();
-        let se_result = serialize(&sig, Infinite).unwrap();
+        let se_result = serialize(&sig).unwrap();
+        // Length-prefixed byte sequence, as before the derive.
+        assert_eq!(se_result.len(), 8 + SIGNATURE_BYTES_LEN);
+        assert_eq!(&se_result[8..], &sig.0[..]);
         let de_result: Signature = deserialize(&se_result).unwrap();
         assert_eq!(sig, de_result);
     }
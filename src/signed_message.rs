@@ -0,0 +1,115 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{pubkey_to_address, Address, Error, Message, PrivKey, PubKey, Signature};
+use crate::hasher::hash_data;
+use cita_crypto_trait::Sign;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+const SIGNED_MESSAGE_DOMAIN: &[u8] = b"cita-ed25519 signed message";
+
+/// A value of type `T` signed in its canonical bincode encoding.
+///
+/// The encoded bytes travel with the signature, so verification checks
+/// exactly what was signed rather than a re-encoding of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct SignedMessage<T> {
+    #[serde(with = "crate::serde_hex")]
+    payload: Vec<u8>,
+    signer: Address,
+    signature: Signature,
+    #[serde(skip)]
+    _marker: PhantomData<fn() -> T>,
+}
+
+fn payload_hash(payload: &[u8]) -> Message {
+    let mut bytes = SIGNED_MESSAGE_DOMAIN.to_vec();
+    bytes.extend_from_slice(payload);
+    hash_data(&bytes)
+}
+
+impl<T: Serialize + DeserializeOwned> SignedMessage<T> {
+    pub fn sign(value: &T, privkey: &PrivKey) -> Result<Self, Error> {
+        let payload = bincode::serialize(value).map_err(|_| Error::InvalidMessage)?;
+        let signature = Signature::sign(privkey, &payload_hash(&payload))?;
+        Ok(SignedMessage {
+            signer: pubkey_to_address(&PubKey::from_slice(signature.pk())),
+            payload,
+            signature,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Check the signature and signer, then decode the value.
+    pub fn verify(&self) -> Result<T, Error> {
+        if !self
+            .signature
+            .verify_address(&self.signer, &payload_hash(&self.payload))?
+        {
+            return Err(Error::InvalidPubKey);
+        }
+        bincode::deserialize(&self.payload).map_err(|_| Error::InvalidMessage)
+    }
+
+    /// Address of the claimed signer; only trustworthy after [`Self::verify`].
+    pub fn signer(&self) -> Address {
+        self.signer
+    }
+
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    /// The message the signature covers.
+    pub fn signing_hash(&self) -> Message {
+        payload_hash(&self.payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+    use cita_crypto_trait::CreateKey;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Vote {
+        height: u64,
+        round: u32,
+        proposal: Message,
+    }
+
+    #[test]
+    fn test_signed_message() {
+        let keypair = KeyPair::gen_keypair();
+        let vote = Vote {
+            height: 10,
+            round: 1,
+            proposal: Message::from_low_u64_be(99),
+        };
+        let signed = SignedMessage::sign(&vote, keypair.privkey()).unwrap();
+        assert_eq!(signed.signer(), keypair.address());
+
+        let json = serde_json::to_string(&signed).unwrap();
+        let decoded: SignedMessage<Vote> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.verify().unwrap(), vote);
+
+        let mut tampered = decoded;
+        tampered.payload[0] ^= 1;
+        assert!(tampered.verify().is_err());
+    }
+}
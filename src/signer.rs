@@ -12,23 +12,286 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::{Address, KeyPair, PrivKey};
-use cita_crypto_trait::CreateKey;
+use super::{Address, Error, KeyPair, Message, PrivKey, PubKey, Signature};
+use crate::hex::Hex;
+use crate::watermark::{read_checked, write_checked};
+use cita_crypto_trait::{CreateKey, Sign};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
-#[derive(Default)]
+/// How a [`Signer`] derives signature nonces.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SigningMode {
+    /// Plain RFC 8032 signing; the same message always yields the same signature.
+    #[default]
+    Deterministic,
+    /// Mix fresh randomness into the nonce, see [`Signature::sign_hedged`].
+    Hedged,
+}
+
+/// Bounds on how many signatures one key may produce.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct UsagePolicy {
+    /// From this many signatures on, [`Signer::needs_rotation`] reports true.
+    pub warn_after: Option<u64>,
+    /// Refuse to sign once this many signatures have been produced.
+    pub max_signatures: Option<u64>,
+}
+
+/// Signature counts per public key, kept across restarts by durable stores.
+pub trait UsageStore: Send + Sync {
+    /// Signatures counted for `pubkey` so far.
+    fn count(&self, pubkey: &PubKey) -> Result<u64, Error>;
+    /// Count one more signature for `pubkey` unless `limit` are counted
+    /// already, as one step. Must not return before the new count would
+    /// survive a crash.
+    fn reserve(&self, pubkey: &PubKey, limit: u64) -> Result<(), Error>;
+    /// Take back a reservation whose signature was never made.
+    fn release(&self, pubkey: &PubKey) -> Result<(), Error>;
+}
+
+/// Keeps counts in memory only, for tests and throwaway nodes.
+#[derive(Debug, Default)]
+pub struct MemoryUsageStore {
+    counts: Mutex<HashMap<PubKey, u64>>,
+}
+
+impl MemoryUsageStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl UsageStore for MemoryUsageStore {
+    fn count(&self, pubkey: &PubKey) -> Result<u64, Error> {
+        Ok(self
+            .counts
+            .lock()
+            .unwrap()
+            .get(pubkey)
+            .copied()
+            .unwrap_or(0))
+    }
+
+    fn reserve(&self, pubkey: &PubKey, limit: u64) -> Result<(), Error> {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(*pubkey).or_insert(0);
+        if *count >= limit {
+            return Err(Error::SigningLimitReached);
+        }
+        *count += 1;
+        Ok(())
+    }
+
+    fn release(&self, pubkey: &PubKey) -> Result<(), Error> {
+        if let Some(count) = self.counts.lock().unwrap().get_mut(pubkey) {
+            *count = count.saturating_sub(1);
+        }
+        Ok(())
+    }
+}
+
+/// Keeps each key's count in its own checksummed file in a directory,
+/// named after the public key and replaced atomically on every change.
+///
+/// A crash between reserving and signing leaves the signature counted,
+/// never the other way round. The files must not be shared with another
+/// store or process.
+#[derive(Debug)]
+pub struct FileUsageStore {
+    dir: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileUsageStore {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        FileUsageStore {
+            dir: dir.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn path(&self, pubkey: &PubKey) -> PathBuf {
+        self.dir.join(format!("{}.usage", Hex(&pubkey.0)))
+    }
+
+    fn save(&self, pubkey: &PubKey, count: u64) -> Result<(), Error> {
+        write_checked(&self.path(pubkey), &rlp::encode(&count))
+    }
+}
+
+impl UsageStore for FileUsageStore {
+    /// A missing file means no signatures yet; a file that fails its
+    /// checksum is an error.
+    fn count(&self, pubkey: &PubKey) -> Result<u64, Error> {
+        match read_checked(&self.path(pubkey))? {
+            Some(body) => Ok(rlp::decode(&body)?),
+            None => Ok(0),
+        }
+    }
+
+    fn reserve(&self, pubkey: &PubKey, limit: u64) -> Result<(), Error> {
+        let _guard = self.lock.lock().unwrap();
+        let count = self.count(pubkey)?;
+        if count >= limit {
+            return Err(Error::SigningLimitReached);
+        }
+        self.save(pubkey, count + 1)
+    }
+
+    fn release(&self, pubkey: &PubKey) -> Result<(), Error> {
+        let _guard = self.lock.lock().unwrap();
+        let count = self.count(pubkey)?;
+        self.save(pubkey, count.saturating_sub(1))
+    }
+}
+
+/// The public facts about a [`Signer`], for logging which identity a process
+/// has loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SignerIdentity {
+    pub pubkey: PubKey,
+    pub address: Address,
+    /// `None` when the usage store could not be read.
+    pub signature_count: Option<u64>,
+}
+
+impl fmt::Display for SignerIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "address: {} pubkey: {} signatures: ",
+            Hex(&self.address.0),
+            Hex(&self.pubkey.0),
+        )?;
+        match self.signature_count {
+            Some(count) => write!(f, "{}", count),
+            None => f.write_str("unknown"),
+        }
+    }
+}
+
+/// Signs with a loaded key, counting every signature against the key in a
+/// [`UsageStore`].
+///
+/// The store is private, so a `Signer` is built with [`Signer::new`],
+/// `From<PrivKey>` or `Default` rather than a struct literal. Counts follow
+/// the public key, so replacing `keypair` switches to the new key's count.
 pub struct Signer {
     pub keypair: KeyPair,
     pub address: Address,
+    pub signing_mode: SigningMode,
+    pub usage_policy: UsagePolicy,
+    usage_store: Arc<dyn UsageStore>,
 }
 
-impl From<PrivKey> for Signer {
-    fn from(privkey: PrivKey) -> Self {
-        let keypair = KeyPair::from_privkey(privkey).unwrap();
+impl Default for Signer {
+    fn default() -> Self {
+        Signer::new(KeyPair::default())
+    }
+}
+
+impl Signer {
+    /// A signer for `keypair` with default settings and counts kept in
+    /// memory.
+    pub fn new(keypair: KeyPair) -> Self {
         Signer {
             address: keypair.address(),
             keypair,
+            signing_mode: SigningMode::default(),
+            usage_policy: UsagePolicy::default(),
+            usage_store: Arc::new(MemoryUsageStore::new()),
         }
     }
+
+    pub fn with_signing_mode(mut self, signing_mode: SigningMode) -> Self {
+        self.signing_mode = signing_mode;
+        self
+    }
+
+    pub fn with_usage_policy(mut self, usage_policy: UsagePolicy) -> Self {
+        self.usage_policy = usage_policy;
+        self
+    }
+
+    /// Count signatures in `usage_store`, e.g. a [`FileUsageStore`] so that
+    /// limits hold across restarts.
+    pub fn with_usage_store(mut self, usage_store: Arc<dyn UsageStore>) -> Self {
+        self.usage_store = usage_store;
+        self
+    }
+
+    /// Signatures counted for the current key so far.
+    pub fn signature_count(&self) -> Result<u64, Error> {
+        self.usage_store.count(self.keypair.pubkey())
+    }
+
+    /// Whether the key has reached the policy's warning threshold.
+    pub fn needs_rotation(&self) -> Result<bool, Error> {
+        match self.usage_policy.warn_after {
+            Some(warn) => Ok(self.signature_count()? >= warn),
+            None => Ok(false),
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Check `signature` over `message` against this signer's own key.
+    pub fn verify(&self, message: &Message, signature: &Signature) -> Result<bool, Error> {
+        signature.verify_public(self.keypair.pubkey(), message)
+    }
+
+    /// Sign `message` and return the RLP encoding of the signature.
+    pub fn sign_and_encode(&self, message: &Message) -> Result<Vec<u8>, Error> {
+        self.sign(message)
+            .map(|signature| rlp::encode(&signature).to_vec())
+    }
+
+    /// Sign `message` and return the signature as lower-case hex.
+    pub fn sign_to_hex(&self, message: &Message) -> Result<String, Error> {
+        self.sign(message).map(String::from)
+    }
+
+    pub fn identity(&self) -> SignerIdentity {
+        SignerIdentity {
+            pubkey: *self.keypair.pubkey(),
+            address: self.address,
+            signature_count: self.signature_count().ok(),
+        }
+    }
+
+    /// Sign `message`, or fail with `SigningLimitReached` once the key has
+    /// made `max_signatures`. A failed signing attempt is not counted.
+    pub fn sign(&self, message: &Message) -> Result<Signature, Error> {
+        let pubkey = self.keypair.pubkey();
+        let limit = self.usage_policy.max_signatures.unwrap_or(u64::MAX);
+        self.usage_store.reserve(pubkey, limit)?;
+        let signature = match self.signing_mode {
+            SigningMode::Deterministic => Signature::sign(self.keypair.privkey(), message),
+            SigningMode::Hedged => Signature::sign_hedged(self.keypair.privkey(), message),
+        };
+        if signature.is_err() {
+            self.usage_store.release(pubkey)?;
+        }
+        signature
+    }
+}
+
+impl fmt::Display for Signer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.identity().fmt(f)
+    }
+}
+
+impl From<PrivKey> for Signer {
+    fn from(privkey: PrivKey) -> Self {
+        Signer::new(KeyPair::from_privkey(privkey).unwrap())
+    }
 }
 
 #[cfg(test)]
@@ -39,9 +302,102 @@ mod tests {
     #[test]
     fn test_signer() {
         let keypair = KeyPair::gen_keypair();
-        let signer = Signer::from(keypair.privkey().clone());
+        let signer = Signer::from(*keypair.privkey());
         assert_eq!(signer.keypair.privkey(), keypair.privkey());
         assert_eq!(signer.keypair.pubkey(), keypair.pubkey());
         assert_eq!(signer.address, keypair.address());
+
+        let identity = signer.identity();
+        assert_eq!(identity.pubkey, *keypair.pubkey());
+        assert_eq!(identity.address, keypair.address());
+        assert_eq!(signer.to_string(), identity.to_string());
+    }
+
+    #[test]
+    fn test_signing_mode() {
+        let keypair = KeyPair::gen_keypair();
+        let msg = Message::from_low_u64_be(7);
+        let signer = Signer::from(*keypair.privkey());
+        assert_eq!(signer.sign(&msg).unwrap(), signer.sign(&msg).unwrap());
+
+        let signer = signer.with_signing_mode(SigningMode::Hedged);
+        let sig = signer.sign(&msg).unwrap();
+        assert_ne!(sig, signer.sign(&msg).unwrap());
+        assert!(sig.verify_public(keypair.pubkey(), &msg).unwrap());
+    }
+
+    #[test]
+    fn test_signer_conveniences() {
+        let signer = Signer::from(*KeyPair::gen_keypair().privkey());
+        let msg = Message::from_low_u64_be(9);
+        assert_eq!(signer.address(), signer.keypair.address());
+
+        let encoded = signer.sign_and_encode(&msg).unwrap();
+        let sig: Signature = rlp::decode(&encoded).unwrap();
+        assert!(signer.verify(&msg, &sig).unwrap());
+        assert_eq!(signer.sign_to_hex(&msg).unwrap(), String::from(sig));
+    }
+
+    #[test]
+    fn test_usage_policy() {
+        let keypair = KeyPair::gen_keypair();
+        let msg = Message::from_low_u64_be(7);
+        let signer = Signer::from(*keypair.privkey()).with_usage_policy(UsagePolicy {
+            warn_after: Some(2),
+            max_signatures: Some(3),
+        });
+        signer.sign(&msg).unwrap();
+        assert!(!signer.needs_rotation().unwrap());
+        signer.sign(&msg).unwrap();
+        assert!(signer.needs_rotation().unwrap());
+        signer.sign(&msg).unwrap();
+        assert!(signer.sign(&msg).is_err());
+        assert_eq!(signer.signature_count().unwrap(), 3);
+        assert_eq!(signer.identity().signature_count, Some(3));
+
+        // The count belongs to the key, not to the signer.
+        let mut signer = signer;
+        signer.keypair = KeyPair::gen_keypair();
+        assert_eq!(signer.signature_count().unwrap(), 0);
+        signer.sign(&msg).unwrap();
+        signer.keypair = keypair;
+        assert!(signer.sign(&msg).is_err());
+
+        // A failed signature does not use up quota.
+        let broken = Signer::default().with_usage_policy(UsagePolicy {
+            warn_after: None,
+            max_signatures: Some(1),
+        });
+        assert!(broken.sign(&msg).is_err());
+        assert_eq!(broken.signature_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_file_usage_store() {
+        let dir = std::env::temp_dir().join(format!("cita-ed25519-{}-usage", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let keypair = KeyPair::gen_keypair();
+        let msg = Message::from_low_u64_be(7);
+        let policy = UsagePolicy {
+            warn_after: None,
+            max_signatures: Some(2),
+        };
+        let signer = Signer::from(*keypair.privkey())
+            .with_usage_policy(policy)
+            .with_usage_store(Arc::new(FileUsageStore::new(&dir)));
+        signer.sign(&msg).unwrap();
+        signer.sign(&msg).unwrap();
+
+        // A restarted signer keeps refusing.
+        let restarted = Signer::from(*keypair.privkey())
+            .with_usage_policy(policy)
+            .with_usage_store(Arc::new(FileUsageStore::new(&dir)));
+        assert_eq!(restarted.signature_count().unwrap(), 2);
+        assert!(matches!(
+            restarted.sign(&msg),
+            Err(Error::SigningLimitReached)
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
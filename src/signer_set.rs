@@ -0,0 +1,146 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{pubkey_to_address, Address, Error, PubKey};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+type EpochMembers = BTreeMap<u64, Vec<Address>>;
+
+/// Known signers per epoch, for the cheap "is this a current validator?"
+/// check that should run before any signature verification.
+///
+/// Each epoch's members are kept as a sorted array, so a lookup is a binary
+/// search over contiguous memory.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "EpochMembers", into = "EpochMembers")]
+pub struct SignerSet {
+    epochs: EpochMembers,
+}
+
+fn sorted(mut members: Vec<Address>) -> Vec<Address> {
+    members.sort_unstable();
+    members.dedup();
+    members
+}
+
+impl From<EpochMembers> for SignerSet {
+    fn from(epochs: EpochMembers) -> Self {
+        SignerSet {
+            epochs: epochs
+                .into_iter()
+                .map(|(epoch, members)| (epoch, sorted(members)))
+                .collect(),
+        }
+    }
+}
+
+impl From<SignerSet> for EpochMembers {
+    fn from(set: SignerSet) -> Self {
+        set.epochs
+    }
+}
+
+impl SignerSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Install the members of a new epoch, which must be later than every
+    /// epoch already known.
+    pub fn insert_epoch<I>(&mut self, epoch: u64, members: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = Address>,
+    {
+        if self.current_epoch().is_some_and(|current| epoch <= current) {
+            return Err(Error::InvalidEpoch);
+        }
+        self.epochs
+            .insert(epoch, sorted(members.into_iter().collect()));
+        Ok(())
+    }
+
+    pub fn insert_epoch_pubkeys(&mut self, epoch: u64, pubkeys: &[PubKey]) -> Result<(), Error> {
+        self.insert_epoch(epoch, pubkeys.iter().map(pubkey_to_address))
+    }
+
+    /// Forget every epoch before `epoch`.
+    pub fn prune_before(&mut self, epoch: u64) {
+        self.epochs = self.epochs.split_off(&epoch);
+    }
+
+    pub fn current_epoch(&self) -> Option<u64> {
+        self.epochs.keys().next_back().copied()
+    }
+
+    /// Whether `address` belongs to the current epoch.
+    pub fn contains(&self, address: &Address) -> bool {
+        self.epochs
+            .values()
+            .next_back()
+            .is_some_and(|members| members.binary_search(address).is_ok())
+    }
+
+    pub fn contains_pubkey(&self, pubkey: &PubKey) -> bool {
+        self.contains(&pubkey_to_address(pubkey))
+    }
+
+    /// Whether `address` belonged to `epoch`.
+    pub fn contains_at(&self, epoch: u64, address: &Address) -> bool {
+        self.epochs
+            .get(&epoch)
+            .is_some_and(|members| members.binary_search(address).is_ok())
+    }
+
+    /// Members of the current epoch, sorted.
+    pub fn members(&self) -> &[Address] {
+        self.epochs.values().next_back().map_or(&[], |m| &m[..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+    use cita_crypto_trait::CreateKey;
+
+    #[test]
+    fn test_signer_set() {
+        let keys: Vec<PubKey> = (0..4).map(|_| *KeyPair::gen_keypair().pubkey()).collect();
+        let mut set = SignerSet::new();
+        set.insert_epoch_pubkeys(1, &keys[..3]).unwrap();
+        assert!(set.contains_pubkey(&keys[0]));
+        assert!(!set.contains_pubkey(&keys[3]));
+
+        set.insert_epoch_pubkeys(2, &keys[1..]).unwrap();
+        assert!(!set.contains_pubkey(&keys[0]));
+        assert!(set.contains_at(1, &pubkey_to_address(&keys[0])));
+        assert!(set.insert_epoch_pubkeys(2, &keys).is_err());
+
+        set.prune_before(2);
+        assert!(!set.contains_at(1, &pubkey_to_address(&keys[0])));
+        assert_eq!(set.current_epoch(), Some(2));
+        assert_eq!(set.members().len(), 3);
+    }
+
+    #[test]
+    fn test_signer_set_serde_resorts() {
+        let a = Address::from_low_u64_be(1);
+        let b = Address::from_low_u64_be(2);
+        let encoded = bincode::serialize(&EpochMembers::from([(5u64, vec![b, a, b])])).unwrap();
+        let set: SignerSet = bincode::deserialize(&encoded).unwrap();
+        assert_eq!(set.members(), &[a, b]);
+        assert!(set.contains(&a));
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Committee selection and shuffling driven by signatures.
+//!
+//! A signature over an agreed seed, e.g. the previous block hash, is hashed
+//! into randomness that anyone holding the public key can recompute.
+//!
+//! Ed25519 signatures are not unique: a signer who ignores RFC 8032 can make
+//! many valid signatures over the same seed and keep the one it likes. Use
+//! these helpers only where such grinding gains nothing or is bounded by
+//! other means; a VRF is required otherwise.
+
+use super::{Error, KeyPair, Message, PubKey, Signature};
+use crate::hasher::hash_data;
+use cita_crypto_trait::{CreateKey, Sign};
+use cita_types::H256;
+use rlp::RlpStream;
+
+const SEED_DOMAIN: &[u8] = b"cita-ed25519 sortition seed";
+const OUTPUT_DOMAIN: &[u8] = b"cita-ed25519 sortition output";
+
+/// The message to sign for `round`, given the shared `seed`.
+pub fn sortition_message(round: u64, seed: &H256) -> Message {
+    let mut s = RlpStream::new_list(3);
+    s.append(&SEED_DOMAIN);
+    s.append(&round);
+    s.append(seed);
+    hash_data(&s.out())
+}
+
+/// Sign the sortition message for `round`.
+pub fn sortition_ticket(keypair: &KeyPair, round: u64, seed: &H256) -> Result<Signature, Error> {
+    Signature::sign(keypair.privkey(), &sortition_message(round, seed))
+}
+
+// The `counter`-th 64-bit draw from a signature.
+fn draw(seed_signature: &Signature, counter: u64) -> u64 {
+    let mut s = RlpStream::new_list(3);
+    s.append(&OUTPUT_DOMAIN);
+    s.append(&seed_signature.sig());
+    s.append(&counter);
+    let hash = hash_data(&s.out());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash.0[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+/// Whether the holder of `weight` out of `total` stake is selected, with
+/// probability `weight / total`.
+pub fn sortition(seed_signature: &Signature, weight: u64, total: u64) -> bool {
+    if total == 0 {
+        return false;
+    }
+    let weight = weight.min(total) as u128;
+    // draw / 2^64 < weight / total
+    (draw(seed_signature, 0) as u128) * (total as u128) < weight << 64
+}
+
+/// Check that `ticket` is `pubkey`'s signature for `round` and recompute
+/// the selection.
+pub fn verify_sortition(
+    pubkey: &PubKey,
+    round: u64,
+    seed: &H256,
+    ticket: &Signature,
+    weight: u64,
+    total: u64,
+) -> Result<bool, Error> {
+    ticket.verify_public(pubkey, &sortition_message(round, seed))?;
+    Ok(sortition(ticket, weight, total))
+}
+
+// Uniform in 0..bound, rejecting draws that would bias the result.
+fn uniform(seed_signature: &Signature, counter: &mut u64, bound: u64) -> u64 {
+    let zone = u64::MAX - u64::MAX % bound;
+    loop {
+        let value = draw(seed_signature, *counter);
+        *counter += 1;
+        if value < zone {
+            return value % bound;
+        }
+    }
+}
+
+/// Fisher-Yates shuffle of `items`, the same for everyone holding
+/// `seed_signature`.
+pub fn shuffle<T>(items: &mut [T], seed_signature: &Signature) {
+    let mut counter = 1;
+    for i in (1..items.len()).rev() {
+        let j = uniform(seed_signature, &mut counter, i as u64 + 1) as usize;
+        items.swap(i, j);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sortition() {
+        let seed = H256::from_low_u64_be(499);
+        let keys: Vec<KeyPair> = (0..400).map(|_| KeyPair::gen_keypair()).collect();
+        let tickets: Vec<Signature> = keys
+            .iter()
+            .map(|k| sortition_ticket(k, 1, &seed).unwrap())
+            .collect();
+
+        assert!(tickets.iter().all(|t| sortition(t, 10, 10)));
+        assert!(tickets.iter().all(|t| !sortition(t, 0, 10)));
+        let selected = tickets.iter().filter(|t| sortition(t, 1, 4)).count();
+        assert!((50..150).contains(&selected), "selected {}", selected);
+
+        let ticket = &tickets[0];
+        let expected = sortition(ticket, 1, 4);
+        assert_eq!(
+            verify_sortition(keys[0].pubkey(), 1, &seed, ticket, 1, 4).unwrap(),
+            expected
+        );
+        assert!(verify_sortition(keys[1].pubkey(), 1, &seed, ticket, 1, 4).is_err());
+        assert!(verify_sortition(keys[0].pubkey(), 2, &seed, ticket, 1, 4).is_err());
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let keypair = KeyPair::gen_keypair();
+        let ticket = sortition_ticket(&keypair, 7, &H256::zero()).unwrap();
+        let mut first: Vec<u32> = (0..50).collect();
+        let mut second = first.clone();
+        shuffle(&mut first, &ticket);
+        shuffle(&mut second, &ticket);
+        assert_eq!(first, second);
+        assert_ne!(first, (0..50).collect::<Vec<u32>>());
+        first.sort();
+        assert_eq!(first, (0..50).collect::<Vec<u32>>());
+    }
+}
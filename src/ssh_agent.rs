@@ -0,0 +1,331 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{pubkey_to_address, Address, Error, Message, PubKey, Signature, SIGNATURE_BYTES_LEN};
+use sodiumoxide::crypto::sign::{
+    verify_detached, PublicKey as EdPublicKey, Signature as EdSignature,
+};
+use std::env;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::Mutex;
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+const SSH_ED25519: &[u8] = b"ssh-ed25519";
+// Agents never send anything close to this for ed25519 keys.
+const MAX_AGENT_MESSAGE_LEN: usize = 256 * 1024;
+
+/// Signs messages with an Ed25519 key held by a running ssh-agent.
+///
+/// The private key never leaves the agent; the agent's 64-byte signature is
+/// checked locally and returned in the usual 96-byte `sig || pubkey` format.
+///
+/// Requests from several threads take turns on the one connection. A reply
+/// that cannot be framed leaves the stream out of step, so the connection is
+/// dropped and every later request fails with `AgentUnavailable`.
+pub struct SshAgentSigner {
+    stream: Mutex<Option<UnixStream>>,
+    pubkey: PubKey,
+}
+
+impl SshAgentSigner {
+    /// Connect to the agent named by `SSH_AUTH_SOCK`.
+    ///
+    /// See [`SshAgentSigner::connect`] for how the key is selected.
+    pub fn from_env(pubkey: Option<&PubKey>) -> Result<Self, Error> {
+        let path = env::var_os("SSH_AUTH_SOCK").ok_or(Error::AgentUnavailable)?;
+        Self::connect(path, pubkey)
+    }
+
+    /// Connect to the agent listening on `path`.
+    ///
+    /// Uses `pubkey` if given, otherwise the first Ed25519 identity the agent
+    /// offers.
+    pub fn connect<P: AsRef<Path>>(path: P, pubkey: Option<&PubKey>) -> Result<Self, Error> {
+        let stream = UnixStream::connect(path).map_err(|_| Error::AgentUnavailable)?;
+        let mut signer = SshAgentSigner {
+            stream: Mutex::new(Some(stream)),
+            pubkey: PubKey::default(),
+        };
+        let identities = signer.identities()?;
+        signer.pubkey = match pubkey {
+            Some(pubkey) => *identities
+                .iter()
+                .find(|id| *id == pubkey)
+                .ok_or(Error::InvalidPubKey)?,
+            None => *identities.first().ok_or(Error::InvalidPubKey)?,
+        };
+        Ok(signer)
+    }
+
+    /// List the Ed25519 public keys held by the agent.
+    pub fn identities(&self) -> Result<Vec<PubKey>, Error> {
+        let reply = self.request(&[SSH_AGENTC_REQUEST_IDENTITIES])?;
+        let mut reader = WireReader::new(&reply);
+        if reader.byte()? != SSH_AGENT_IDENTITIES_ANSWER {
+            return Err(Error::AgentFailure);
+        }
+        let count = reader.u32()?;
+        let mut keys = Vec::new();
+        for _ in 0..count {
+            let blob = reader.string()?;
+            let _comment = reader.string()?;
+            let mut blob = WireReader::new(blob);
+            if blob.string()? != SSH_ED25519 {
+                continue;
+            }
+            let pk = blob.string()?;
+            if pk.len() == 32 {
+                keys.push(PubKey::from_slice(pk));
+            }
+        }
+        Ok(keys)
+    }
+
+    pub fn pubkey(&self) -> &PubKey {
+        &self.pubkey
+    }
+
+    pub fn address(&self) -> Address {
+        pubkey_to_address(&self.pubkey)
+    }
+
+    pub fn sign(&self, message: &Message) -> Result<Signature, Error> {
+        let mut key_blob = Vec::new();
+        put_string(&mut key_blob, SSH_ED25519);
+        put_string(&mut key_blob, self.pubkey.as_ref());
+
+        let mut request = vec![SSH_AGENTC_SIGN_REQUEST];
+        put_string(&mut request, &key_blob);
+        put_string(&mut request, message.as_ref());
+        request.extend_from_slice(&0u32.to_be_bytes());
+
+        let reply = self.request(&request)?;
+        let mut reader = WireReader::new(&reply);
+        if reader.byte()? != SSH_AGENT_SIGN_RESPONSE {
+            return Err(Error::AgentFailure);
+        }
+        let mut sig_blob = WireReader::new(reader.string()?);
+        if sig_blob.string()? != SSH_ED25519 {
+            return Err(Error::AgentFailure);
+        }
+        let sig = sig_blob.string()?;
+        if sig.len() != 64 {
+            return Err(Error::AgentFailure);
+        }
+
+        let mut sig_array = [0u8; 64];
+        sig_array.copy_from_slice(sig);
+        let pk = EdPublicKey::from_slice(self.pubkey.as_ref()).ok_or(Error::InvalidPubKey)?;
+        if !verify_detached(&EdSignature::from(sig_array), message.as_ref(), &pk) {
+            return Err(Error::InvalidSignature);
+        }
+
+        let mut ret = [0u8; SIGNATURE_BYTES_LEN];
+        ret[0..64].copy_from_slice(sig);
+        ret[64..96].copy_from_slice(self.pubkey.as_ref());
+        Ok(Signature(ret))
+    }
+
+    fn request(&self, body: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut guard = self.stream.lock().unwrap_or_else(|e| e.into_inner());
+        let stream = guard.as_mut().ok_or(Error::AgentUnavailable)?;
+        let reply = exchange(stream, body);
+        if reply.is_err() {
+            *guard = None;
+        }
+        let reply = reply?;
+        if reply[0] == SSH_AGENT_FAILURE {
+            return Err(Error::AgentFailure);
+        }
+        Ok(reply)
+    }
+}
+
+// One request frame out, one reply frame in. Any error leaves the stream at
+// an unknown position.
+fn exchange(stream: &mut UnixStream, body: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut frame = Vec::with_capacity(body.len() + 4);
+    put_string(&mut frame, body);
+    stream
+        .write_all(&frame)
+        .map_err(|_| Error::AgentUnavailable)?;
+
+    let mut len = [0u8; 4];
+    stream
+        .read_exact(&mut len)
+        .map_err(|_| Error::AgentUnavailable)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len == 0 || len > MAX_AGENT_MESSAGE_LEN {
+        return Err(Error::AgentFailure);
+    }
+    let mut reply = vec![0u8; len];
+    stream
+        .read_exact(&mut reply)
+        .map_err(|_| Error::AgentUnavailable)?;
+    Ok(reply)
+}
+
+fn put_string(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(data);
+}
+
+struct WireReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> WireReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        WireReader { data }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if self.data.len() < n {
+            return Err(Error::AgentFailure);
+        }
+        let (head, tail) = self.data.split_at(n);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_be_bytes(bytes))
+    }
+
+    fn string(&mut self) -> Result<&'a [u8], Error> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+    use cita_crypto_trait::{CreateKey, Sign};
+    use sodiumoxide::crypto::sign::{sign_detached, SecretKey};
+    use std::os::unix::net::UnixListener;
+    use std::thread;
+
+    // Minimal agent holding a single key, enough to exercise the protocol.
+    fn spawn_agent(keypair: &KeyPair) -> std::path::PathBuf {
+        let path = env::temp_dir().join(format!(
+            "cita-ed25519-agent-{}-{}",
+            std::process::id(),
+            keypair.pubkey().to_low_u64_be()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let secret = SecretKey::from_slice(keypair.privkey().as_ref()).unwrap();
+        let pubkey = *keypair.pubkey();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut key_blob = Vec::new();
+            put_string(&mut key_blob, SSH_ED25519);
+            put_string(&mut key_blob, pubkey.as_ref());
+            loop {
+                let mut len = [0u8; 4];
+                if stream.read_exact(&mut len).is_err() {
+                    return;
+                }
+                let mut body = vec![0u8; u32::from_be_bytes(len) as usize];
+                stream.read_exact(&mut body).unwrap();
+                let mut reply = Vec::new();
+                match body[0] {
+                    SSH_AGENTC_REQUEST_IDENTITIES => {
+                        reply.push(SSH_AGENT_IDENTITIES_ANSWER);
+                        reply.extend_from_slice(&1u32.to_be_bytes());
+                        put_string(&mut reply, &key_blob);
+                        put_string(&mut reply, b"test key");
+                    }
+                    SSH_AGENTC_SIGN_REQUEST => {
+                        let mut reader = WireReader::new(&body[1..]);
+                        assert_eq!(reader.string().unwrap(), &key_blob[..]);
+                        let data = reader.string().unwrap();
+                        let sig = sign_detached(data, &secret);
+                        let mut sig_blob = Vec::new();
+                        put_string(&mut sig_blob, SSH_ED25519);
+                        put_string(&mut sig_blob, sig.as_ref());
+                        reply.push(SSH_AGENT_SIGN_RESPONSE);
+                        put_string(&mut reply, &sig_blob);
+                    }
+                    _ => reply.push(SSH_AGENT_FAILURE),
+                }
+                let mut frame = Vec::new();
+                put_string(&mut frame, &reply);
+                stream.write_all(&frame).unwrap();
+            }
+        });
+        path
+    }
+
+    #[test]
+    fn test_agent_sign() {
+        let keypair = KeyPair::gen_keypair();
+        let path = spawn_agent(&keypair);
+        let signer = SshAgentSigner::connect(&path, None).unwrap();
+        assert_eq!(signer.pubkey(), keypair.pubkey());
+        assert_eq!(signer.address(), keypair.address());
+
+        let msg = Message::from_low_u64_be(42);
+        let sig = signer.sign(&msg).unwrap();
+        assert_eq!(sig, Signature::sign(keypair.privkey(), &msg).unwrap());
+        assert!(sig.verify_public(keypair.pubkey(), &msg).unwrap());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_agent_concurrent_sign() {
+        let keypair = KeyPair::gen_keypair();
+        let path = spawn_agent(&keypair);
+        let signer = SshAgentSigner::connect(&path, None).unwrap();
+        thread::scope(|scope| {
+            for t in 0..4u64 {
+                let signer = &signer;
+                let pubkey = keypair.pubkey();
+                scope.spawn(move || {
+                    for i in 0..25 {
+                        let msg = Message::from_low_u64_be(t * 100 + i);
+                        let sig = signer.sign(&msg).unwrap();
+                        assert!(sig.verify_public(pubkey, &msg).unwrap());
+                    }
+                });
+            }
+        });
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_agent_missing_key() {
+        let keypair = KeyPair::gen_keypair();
+        let path = spawn_agent(&keypair);
+        let other = KeyPair::gen_keypair();
+        assert!(SshAgentSigner::connect(&path, Some(other.pubkey())).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
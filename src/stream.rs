@@ -0,0 +1,299 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sign or verify data as it passes through `std::io`.
+//!
+//! The signed message is the SHA-256 digest of the whole stream, so the
+//! signature can also be checked with the ordinary [`Sign`] methods once the
+//! digest is known.
+
+use super::{Error, Message, PrivKey, PubKey, Signature};
+use cita_crypto_trait::Sign;
+use libsodium_sys as ffi;
+use serde::{Deserialize, Serialize};
+use sodiumoxide::crypto::hash::sha256;
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+/// Forwards writes to `inner` and signs everything written.
+pub struct SigningWriter<'a, W: Write> {
+    inner: W,
+    state: sha256::State,
+    privkey: &'a PrivKey,
+}
+
+impl<'a, W: Write> SigningWriter<'a, W> {
+    pub fn new(inner: W, privkey: &'a PrivKey) -> Self {
+        SigningWriter {
+            inner,
+            state: sha256::State::new(),
+            privkey,
+        }
+    }
+
+    /// Sign the data written so far and hand back the inner writer, which is
+    /// not flushed.
+    pub fn finish(self) -> Result<(W, Signature), Error> {
+        let message = Message::from(self.state.finalize().0);
+        let signature = Signature::sign(self.privkey, &message)?;
+        Ok((self.inner, signature))
+    }
+}
+
+impl<'a, W: Write> Write for SigningWriter<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.state.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct VerifyStateFields {
+    pubkey: PubKey,
+    state: [u32; 8],
+    count: u64,
+    #[serde(with = "crate::serde_array")]
+    buf: [u8; 64],
+}
+
+/// Verification of a signature over data fed in pieces, e.g. as it arrives
+/// from the network.
+///
+/// The state can be serialized between pieces and resumed later, even in
+/// another process; it holds only the running hash and the public key.
+/// Deserializing rejects a bit count that is not a whole number of bytes
+/// and buffer bytes past the ones the count says are pending.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "VerifyStateFields", into = "VerifyStateFields")]
+pub struct VerifyState {
+    pubkey: PubKey,
+    state: [u32; 8],
+    count: u64,
+    buf: [u8; 64],
+}
+
+impl VerifyState {
+    pub fn new(pubkey: PubKey) -> Self {
+        let mut raw = ffi::crypto_hash_sha256_state {
+            state: [0; 8],
+            count: 0,
+            buf: [0; 64],
+        };
+        unsafe { ffi::crypto_hash_sha256_init(&mut raw) };
+        VerifyState::from_raw(pubkey, raw)
+    }
+
+    // libsodium leaves stale bytes after the pending ones; clear them so
+    // every state has a single serialized form.
+    fn from_raw(pubkey: PubKey, raw: ffi::crypto_hash_sha256_state) -> Self {
+        let mut buf = raw.buf;
+        buf[pending_len(raw.count)..]
+            .iter_mut()
+            .for_each(|b| *b = 0);
+        VerifyState {
+            pubkey,
+            state: raw.state,
+            count: raw.count,
+            buf,
+        }
+    }
+
+    fn raw(&self) -> ffi::crypto_hash_sha256_state {
+        ffi::crypto_hash_sha256_state {
+            state: self.state,
+            count: self.count,
+            buf: self.buf,
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        let mut raw = self.raw();
+        unsafe { ffi::crypto_hash_sha256_update(&mut raw, data.as_ptr(), data.len() as u64) };
+        *self = VerifyState::from_raw(self.pubkey, raw);
+    }
+
+    /// Bytes fed so far.
+    pub fn len(&self) -> u64 {
+        self.count / 8
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn pubkey(&self) -> &PubKey {
+        &self.pubkey
+    }
+
+    /// Check `signature` against everything fed so far.
+    pub fn finish(self, signature: &Signature) -> Result<(), Error> {
+        let mut raw = self.raw();
+        let mut digest = [0u8; 32];
+        unsafe { ffi::crypto_hash_sha256_final(&mut raw, digest.as_mut_ptr()) };
+        signature.verify_public(&self.pubkey, &Message::from(digest))?;
+        Ok(())
+    }
+}
+
+// Bytes in the buffer waiting for a full block, given the bit count.
+fn pending_len(count: u64) -> usize {
+    ((count / 8) % 64) as usize
+}
+
+impl TryFrom<VerifyStateFields> for VerifyState {
+    type Error = Error;
+
+    fn try_from(fields: VerifyStateFields) -> Result<Self, Self::Error> {
+        if fields.count % 8 != 0
+            || fields.buf[pending_len(fields.count)..]
+                .iter()
+                .any(|b| *b != 0)
+        {
+            return Err(Error::InvalidEncoding);
+        }
+        Ok(VerifyState {
+            pubkey: fields.pubkey,
+            state: fields.state,
+            count: fields.count,
+            buf: fields.buf,
+        })
+    }
+}
+
+impl From<VerifyState> for VerifyStateFields {
+    fn from(state: VerifyState) -> Self {
+        VerifyStateFields {
+            pubkey: state.pubkey,
+            state: state.state,
+            count: state.count,
+            buf: state.buf,
+        }
+    }
+}
+
+/// Forwards reads from `inner` and checks a signature over everything read.
+pub struct VerifyingReader<R: Read> {
+    inner: R,
+    state: VerifyState,
+}
+
+impl<R: Read> VerifyingReader<R> {
+    pub fn new(inner: R, pubkey: PubKey) -> Self {
+        VerifyingReader::resume(inner, VerifyState::new(pubkey))
+    }
+
+    /// Continue verifying from a saved state, reading the rest from `inner`.
+    pub fn resume(inner: R, state: VerifyState) -> Self {
+        VerifyingReader { inner, state }
+    }
+
+    /// Stop reading and return the state so far, e.g. to persist it.
+    pub fn into_state(self) -> VerifyState {
+        self.state
+    }
+
+    /// Check `signature` against the data read so far.
+    ///
+    /// Callers must read the stream to its end first; anything left unread is
+    /// not covered. Data handed out before this returns is unauthenticated.
+    pub fn finish(self, signature: &Signature) -> Result<R, Error> {
+        self.state.finish(signature)?;
+        Ok(self.inner)
+    }
+}
+
+impl<R: Read> Read for VerifyingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.state.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+    use cita_crypto_trait::CreateKey;
+
+    #[test]
+    fn test_stream_sign_verify() {
+        let keypair = KeyPair::gen_keypair();
+        let payload: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+
+        let mut writer = SigningWriter::new(Vec::new(), keypair.privkey());
+        for chunk in payload.chunks(777) {
+            writer.write_all(chunk).unwrap();
+        }
+        let (written, sig) = writer.finish().unwrap();
+        assert_eq!(written, payload);
+        let digest = Message::from(sha256::hash(&payload).0);
+        assert!(sig.verify_public(keypair.pubkey(), &digest).unwrap());
+
+        let mut reader = VerifyingReader::new(&written[..], *keypair.pubkey());
+        let mut read_back = Vec::new();
+        reader.read_to_end(&mut read_back).unwrap();
+        assert!(reader.finish(&sig).is_ok());
+
+        let mut tampered = written.clone();
+        tampered[5000] ^= 1;
+        let mut reader = VerifyingReader::new(&tampered[..], *keypair.pubkey());
+        reader.read_to_end(&mut Vec::new()).unwrap();
+        assert!(reader.finish(&sig).is_err());
+    }
+
+    #[test]
+    fn test_verify_state_resume() {
+        let keypair = KeyPair::gen_keypair();
+        let payload = vec![0x42u8; 1000];
+        let digest = Message::from(sha256::hash(&payload).0);
+        let sig = Signature::sign(keypair.privkey(), &digest).unwrap();
+
+        let mut state = VerifyState::new(*keypair.pubkey());
+        state.update(&payload[..333]);
+        let saved = serde_json::to_string(&state).unwrap();
+
+        let mut reader = VerifyingReader::resume(
+            &payload[333..],
+            serde_json::from_str::<VerifyState>(&saved).unwrap(),
+        );
+        reader.read_to_end(&mut Vec::new()).unwrap();
+        let state = reader.into_state();
+        assert_eq!(state.len(), 1000);
+        assert!(state.finish(&sig).is_ok());
+    }
+
+    #[test]
+    fn test_verify_state_rejects_inconsistent_fields() {
+        let mut state = VerifyState::new(PubKey::from_low_u64_be(1));
+        // Two full blocks leave stale bytes behind in libsodium's buffer.
+        state.update(&[0x42u8; 130]);
+        assert!(state.buf[2..].iter().all(|b| *b == 0));
+        let fields = VerifyStateFields::from(state.clone());
+        assert!(VerifyState::try_from(fields).is_ok());
+
+        let mut fields = VerifyStateFields::from(state.clone());
+        fields.count += 1;
+        assert!(VerifyState::try_from(fields).is_err());
+        let mut fields = VerifyStateFields::from(state);
+        fields.buf[2] = 1;
+        let saved = serde_json::to_string(&fields).unwrap();
+        assert!(serde_json::from_str::<VerifyState>(&saved).is_err());
+    }
+}
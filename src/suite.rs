@@ -0,0 +1,213 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signature suites by id, so a network mixing algorithms can tag, negotiate
+//! and verify signatures through one entry point.
+//!
+//! Only Ed25519 lives in this crate; adapters for the other CITA algorithms
+//! implement [`SignatureSuite`] elsewhere and are added with
+//! [`SuiteRegistry::register`].
+
+use super::{pubkey_from_slice, pubkey_to_address, Address, Error, Message, Signature};
+use cita_crypto_trait::Sign;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+pub const SECP256K1_SUITE_ID: u8 = 1;
+pub const ED25519_SUITE_ID: u8 = 2;
+pub const SM2_SUITE_ID: u8 = 3;
+
+/// What a suite supports beyond plain verification.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The signer's address can be recovered from signature and message.
+    pub recoverable: bool,
+    /// Signatures carry the signer's public key.
+    pub embeds_pubkey: bool,
+}
+
+pub trait SignatureSuite: Send + Sync {
+    fn id(&self) -> u8;
+    fn name(&self) -> &'static str;
+    fn capabilities(&self) -> Capabilities;
+    fn verify(&self, pubkey: &[u8], message: &Message, signature: &[u8]) -> Result<bool, Error>;
+    /// Only called when [`Capabilities::recoverable`] is set.
+    fn recover_address(&self, message: &Message, signature: &[u8]) -> Result<Address, Error>;
+}
+
+/// The suite implemented by this crate.
+pub struct Ed25519Suite;
+
+impl SignatureSuite for Ed25519Suite {
+    fn id(&self) -> u8 {
+        ED25519_SUITE_ID
+    }
+
+    fn name(&self) -> &'static str {
+        "ed25519"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            recoverable: true,
+            embeds_pubkey: true,
+        }
+    }
+
+    fn verify(&self, pubkey: &[u8], message: &Message, signature: &[u8]) -> Result<bool, Error> {
+        let pubkey = pubkey_from_slice(pubkey).map_err(|_| Error::InvalidPubKey)?;
+        let signature = Signature::try_from(signature)?;
+        signature.verify_public(&pubkey, message)
+    }
+
+    fn recover_address(&self, message: &Message, signature: &[u8]) -> Result<Address, Error> {
+        let signature = Signature::try_from(signature)?;
+        Ok(pubkey_to_address(&signature.recover(message)?))
+    }
+}
+
+/// A signature prefixed with the id of the suite that made it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaggedSignature {
+    pub suite_id: u8,
+    pub signature: Vec<u8>,
+}
+
+impl TaggedSignature {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.signature.len());
+        bytes.push(self.suite_id);
+        bytes.extend_from_slice(&self.signature);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let (suite_id, signature) = bytes.split_first().ok_or(Error::InvalidSignature)?;
+        Ok(TaggedSignature {
+            suite_id: *suite_id,
+            signature: signature.to_vec(),
+        })
+    }
+}
+
+impl From<&Signature> for TaggedSignature {
+    fn from(signature: &Signature) -> Self {
+        TaggedSignature {
+            suite_id: ED25519_SUITE_ID,
+            signature: signature.0.to_vec(),
+        }
+    }
+}
+
+/// Registered suites, looked up by id.
+#[derive(Default)]
+pub struct SuiteRegistry {
+    suites: BTreeMap<u8, Box<dyn SignatureSuite>>,
+}
+
+impl SuiteRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry holding the suites built into this crate.
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(Ed25519Suite));
+        registry
+    }
+
+    /// Add `suite`, replacing any suite registered under the same id.
+    pub fn register(&mut self, suite: Box<dyn SignatureSuite>) {
+        self.suites.insert(suite.id(), suite);
+    }
+
+    pub fn get(&self, id: u8) -> Option<&dyn SignatureSuite> {
+        self.suites.get(&id).map(|suite| suite.as_ref())
+    }
+
+    pub fn ids(&self) -> Vec<u8> {
+        self.suites.keys().copied().collect()
+    }
+
+    /// The first suite in `preferred` that both sides support.
+    pub fn negotiate(&self, preferred: &[u8], peer: &[u8]) -> Option<u8> {
+        preferred
+            .iter()
+            .copied()
+            .find(|id| peer.contains(id) && self.suites.contains_key(id))
+    }
+
+    pub fn verify(
+        &self,
+        pubkey: &[u8],
+        message: &Message,
+        tagged: &TaggedSignature,
+    ) -> Result<bool, Error> {
+        let suite = self.get(tagged.suite_id).ok_or(Error::UnsupportedSuite)?;
+        suite.verify(pubkey, message, &tagged.signature)
+    }
+
+    pub fn recover_address(
+        &self,
+        message: &Message,
+        tagged: &TaggedSignature,
+    ) -> Result<Address, Error> {
+        let suite = self.get(tagged.suite_id).ok_or(Error::UnsupportedSuite)?;
+        if !suite.capabilities().recoverable {
+            return Err(Error::UnsupportedSuite);
+        }
+        suite.recover_address(message, &tagged.signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+    use cita_crypto_trait::CreateKey;
+
+    #[test]
+    fn test_suite_registry() {
+        let registry = SuiteRegistry::with_builtin();
+        let keypair = KeyPair::gen_keypair();
+        let message = Message::from([9u8; 32]);
+        let signature = Signature::sign(keypair.privkey(), &message).unwrap();
+
+        let tagged =
+            TaggedSignature::from_bytes(&TaggedSignature::from(&signature).to_bytes()).unwrap();
+        assert!(registry
+            .verify(keypair.pubkey().as_ref(), &message, &tagged)
+            .unwrap());
+        assert_eq!(
+            registry.recover_address(&message, &tagged).unwrap(),
+            keypair.address()
+        );
+
+        let unknown = TaggedSignature {
+            suite_id: SM2_SUITE_ID,
+            ..tagged
+        };
+        assert!(registry
+            .verify(keypair.pubkey().as_ref(), &message, &unknown)
+            .is_err());
+        assert_eq!(
+            registry.negotiate(
+                &[SM2_SUITE_ID, ED25519_SUITE_ID],
+                &[ED25519_SUITE_ID, SM2_SUITE_ID]
+            ),
+            Some(ED25519_SUITE_ID)
+        );
+    }
+}
@@ -0,0 +1,105 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reproducible accounts for development networks, so tooling derives the
+//! same keys from the same seed instead of shipping its own key lists.
+//!
+//! The private keys are derived from a public string; never fund them on a
+//! network that matters.
+
+use super::{Address, KeyPair, PrivKey, PubKey};
+use cita_crypto_trait::CreateKey;
+use cita_types::U256;
+use serde::{Deserialize, Serialize};
+use sodiumoxide::crypto::hash::sha256;
+
+const TESTNET_DOMAIN: &[u8] = b"cita-ed25519 testnet account";
+
+/// One derived test account.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestnetAccount {
+    pub index: u64,
+    pub privkey: PrivKey,
+    pub pubkey: PubKey,
+    pub address: Address,
+}
+
+/// A balance to credit in a genesis block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenesisEntry {
+    pub address: Address,
+    pub balance: U256,
+}
+
+impl TestnetAccount {
+    pub fn keypair(&self) -> KeyPair {
+        KeyPair::from_privkey(self.privkey).unwrap()
+    }
+
+    pub fn genesis_entry(&self, balance: U256) -> GenesisEntry {
+        GenesisEntry {
+            address: self.address,
+            balance,
+        }
+    }
+}
+
+/// The `index`-th account for `seed`.
+pub fn testnet_account(seed: &str, index: u64) -> TestnetAccount {
+    let mut state = sha256::State::new();
+    state.update(TESTNET_DOMAIN);
+    state.update(&(seed.len() as u64).to_be_bytes());
+    state.update(seed.as_bytes());
+    state.update(&index.to_be_bytes());
+    let keypair = KeyPair::from_seed(&state.finalize().0);
+    TestnetAccount {
+        index,
+        privkey: *keypair.privkey(),
+        pubkey: *keypair.pubkey(),
+        address: keypair.address(),
+    }
+}
+
+/// The first `n` accounts for `seed`.
+pub fn testnet_accounts(seed: &str, n: usize) -> Vec<TestnetAccount> {
+    (0..n as u64).map(|i| testnet_account(seed, i)).collect()
+}
+
+/// Genesis entries crediting `balance` to each of the first `n` accounts.
+pub fn testnet_genesis(seed: &str, n: usize, balance: U256) -> Vec<GenesisEntry> {
+    testnet_accounts(seed, n)
+        .iter()
+        .map(|account| account.genesis_entry(balance))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_testnet_accounts() {
+        let accounts = testnet_accounts("devnet", 3);
+        assert_eq!(accounts, testnet_accounts("devnet", 3));
+        assert_ne!(accounts[0], testnet_accounts("devnet2", 1)[0]);
+        assert_ne!(accounts[0].address, accounts[1].address);
+        assert_eq!(accounts[2].keypair().address(), accounts[2].address);
+
+        let genesis = testnet_genesis("devnet", 3, U256::from(1_000_000u64));
+        assert_eq!(genesis[1].address, accounts[1].address);
+        let json = serde_json::to_string(&genesis).unwrap();
+        let decoded: Vec<GenesisEntry> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, genesis);
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signatures sealed behind sequential hashing, e.g. sealed bids that must
+//! stay hidden until an auction closes.
+//!
+//! The signed message and signature are encrypted under a key reached by
+//! hashing a public puzzle `iterations` times in sequence. Anyone can open
+//! the commitment after doing that work, and the signer can open it at once
+//! by publishing the key. Sequential hashing has no trapdoor, so sealing
+//! costs the signer the same work; choose `iterations` by measuring the
+//! hash rate of the fastest expected solver, not the signer's.
+//!
+//! The chain is always SHA-256, whatever hash the build selects, so every
+//! build opens the same commitments at the same cost.
+
+use super::{Error, KeyPair, Message, PubKey, Signature, SIGNATURE_BYTES_LEN};
+use cita_crypto_trait::{CreateKey, Sign};
+use cita_types::H256;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+use sodiumoxide::crypto::hash::sha256;
+use sodiumoxide::crypto::secretbox;
+use sodiumoxide::randombytes::randombytes_into;
+use sodiumoxide::utils::memzero;
+
+const TIMELOCK_DOMAIN: &[u8] = b"cita-ed25519 timelock";
+
+/// A message and its signature, hidden until the puzzle is solved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimelockCommitment {
+    pub puzzle: H256,
+    pub iterations: u64,
+    #[serde(with = "crate::serde_hex")]
+    pub nonce: Vec<u8>,
+    #[serde(with = "crate::serde_hex")]
+    pub ciphertext: Vec<u8>,
+}
+
+/// What a [`TimelockCommitment`] reveals once opened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelockOpening {
+    pub message: Message,
+    pub signature: Signature,
+}
+
+impl TimelockOpening {
+    pub fn signer(&self) -> PubKey {
+        PubKey::from_slice(self.signature.pk())
+    }
+}
+
+fn chain_start(puzzle: &H256, iterations: u64) -> H256 {
+    let mut s = RlpStream::new_list(3);
+    s.append(&TIMELOCK_DOMAIN);
+    s.append(puzzle);
+    s.append(&iterations);
+    H256(sha256::hash(&s.out()).0)
+}
+
+/// Sign `message` with `keypair` and seal the result for `iterations`
+/// sequential hashes. Returns the commitment and the key that opens it early.
+pub fn timelock_sign(
+    keypair: &KeyPair,
+    message: &Message,
+    iterations: u64,
+) -> Result<(TimelockCommitment, H256), Error> {
+    let signature = Signature::sign(keypair.privkey(), message)?;
+    let mut puzzle = H256::zero();
+    randombytes_into(&mut puzzle.0);
+    let mut commitment = TimelockCommitment {
+        puzzle,
+        iterations,
+        nonce: Vec::new(),
+        ciphertext: Vec::new(),
+    };
+    let key = commitment.solve();
+
+    let nonce = secretbox::gen_nonce();
+    let mut plaintext = message.0.to_vec();
+    plaintext.extend_from_slice(&signature.0);
+    commitment.ciphertext = secretbox::seal(&plaintext, &nonce, &secretbox::Key(key.0));
+    commitment.nonce = nonce.0.to_vec();
+    memzero(&mut plaintext);
+    Ok((commitment, key))
+}
+
+impl TimelockCommitment {
+    /// The opening key, found by hashing the puzzle `iterations` times.
+    pub fn solve(&self) -> H256 {
+        let mut key = chain_start(&self.puzzle, self.iterations);
+        for _ in 0..self.iterations {
+            key = H256(sha256::hash(&key.0).0);
+        }
+        key
+    }
+
+    /// Decrypt with `key` and check the revealed signature. A wrong key gives
+    /// `DecryptionFailed`.
+    pub fn open(&self, key: &H256) -> Result<TimelockOpening, Error> {
+        let nonce = secretbox::Nonce::from_slice(&self.nonce).ok_or(Error::InvalidEncoding)?;
+        let mut plaintext = secretbox::open(&self.ciphertext, &nonce, &secretbox::Key(key.0))
+            .map_err(|_| Error::DecryptionFailed)?;
+        if plaintext.len() != 32 + SIGNATURE_BYTES_LEN {
+            return Err(Error::InvalidEncoding);
+        }
+        let message = Message::from_slice(&plaintext[..32]);
+        let mut bytes = [0u8; SIGNATURE_BYTES_LEN];
+        bytes.copy_from_slice(&plaintext[32..]);
+        memzero(&mut plaintext);
+        let opening = TimelockOpening {
+            message,
+            signature: Signature(bytes),
+        };
+        opening
+            .signature
+            .verify_public(&opening.signer(), &opening.message)?;
+        Ok(opening)
+    }
+
+    /// Solve the puzzle and open.
+    pub fn force_open(&self) -> Result<TimelockOpening, Error> {
+        self.open(&self.solve())
+    }
+}
+
+impl Encodable for TimelockCommitment {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(4);
+        s.append(&self.puzzle);
+        s.append(&self.iterations);
+        s.append(&self.nonce);
+        s.append(&self.ciphertext);
+    }
+}
+
+impl Decodable for TimelockCommitment {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 4 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(TimelockCommitment {
+            puzzle: rlp.val_at(0)?,
+            iterations: rlp.val_at(1)?,
+            nonce: rlp.val_at(2)?,
+            ciphertext: rlp.val_at(3)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timelock_open() {
+        let keypair = KeyPair::gen_keypair();
+        let bid = Message::from_low_u64_be(500);
+        let (commitment, key) = timelock_sign(&keypair, &bid, 1000).unwrap();
+        let commitment: TimelockCommitment = rlp::decode(&rlp::encode(&commitment)).unwrap();
+
+        let early = commitment.open(&key).unwrap();
+        assert_eq!(early.message, bid);
+        assert_eq!(&early.signer(), keypair.pubkey());
+        assert_eq!(commitment.force_open().unwrap(), early);
+        assert!(matches!(
+            commitment.open(&H256::zero()),
+            Err(Error::DecryptionFailed)
+        ));
+
+        let mut shortened = commitment;
+        shortened.iterations = 10;
+        assert!(shortened.force_open().is_err());
+    }
+}
@@ -0,0 +1,164 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `tracing` events for key generation, signing and verification.
+//!
+//! Events carry only public values: public keys and message hashes. Nothing
+//! derived from a private key is ever attached to an event; callers adding
+//! their own fields can wrap secrets in [`Redacted`].
+
+use super::{Message, PubKey};
+use crate::hex::Hex;
+use std::fmt;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+const TARGET: &str = "cita_ed25519";
+
+/// Formats as `<redacted>` whatever it holds.
+#[derive(Clone, Copy, Default)]
+pub struct Redacted<T>(pub T);
+
+impl<T> fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+impl<T> fmt::Display for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+static SAMPLE_ONE_IN: AtomicU32 = AtomicU32::new(1);
+static SAMPLE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Emit one in `one_in` signing and successful verification events; zero
+/// turns them off. Key generation and rejected signatures are always
+/// reported.
+pub fn set_trace_sampling(one_in: u32) {
+    SAMPLE_ONE_IN.store(one_in, Ordering::Relaxed);
+}
+
+fn sampled() -> bool {
+    match SAMPLE_ONE_IN.load(Ordering::Relaxed) {
+        0 => false,
+        1 => true,
+        n => SAMPLE_COUNTER.fetch_add(1, Ordering::Relaxed) % n as u64 == 0,
+    }
+}
+
+pub(crate) fn keygen(pubkey: &PubKey) {
+    tracing::info!(target: TARGET, pubkey = %Hex(&pubkey.0), "generated key pair");
+}
+
+pub(crate) fn sign(pubkey: &[u8], message: &Message) {
+    if sampled() {
+        tracing::debug!(
+            target: TARGET,
+            pubkey = %Hex(pubkey),
+            message = %Hex(&message.0),
+            "signed message"
+        );
+    }
+}
+
+pub(crate) fn verify(pubkey: &[u8], message: &Message, valid: bool) {
+    if !valid {
+        tracing::debug!(
+            target: TARGET,
+            pubkey = %Hex(pubkey),
+            message = %Hex(&message.0),
+            "rejected signature"
+        );
+    } else if sampled() {
+        tracing::trace!(
+            target: TARGET,
+            pubkey = %Hex(pubkey),
+            message = %Hex(&message.0),
+            "verified signature"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeyPair, Signature};
+    use cita_crypto_trait::{CreateKey, Sign};
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    // Collects every field of every event and span as text.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl Visit for Recorder {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            let line = format!("{}={:?}", field.name(), value);
+            self.0.lock().unwrap().push(line);
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes) -> Id {
+            span.record(&mut self.clone());
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, values: &Record) {
+            values.record(&mut self.clone());
+        }
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event) {
+            event.record(&mut self.clone());
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn test_events_carry_no_secrets() {
+        let recorder = Recorder::default();
+        let lines = recorder.0.clone();
+        let keypair = tracing::subscriber::with_default(recorder, || {
+            let keypair = KeyPair::gen_keypair();
+            let message = Message::from_low_u64_be(497);
+            let mut signature = Signature::sign(keypair.privkey(), &message).unwrap();
+            signature.recover(&message).unwrap();
+            signature.0[0] ^= 1;
+            assert!(signature.recover(&message).is_err());
+            tracing::info!(seed = %Redacted(&keypair.privkey().0[..32]), "custom");
+            keypair
+        });
+
+        let lines = lines.lock().unwrap().join("\n");
+        for message in [
+            "generated key pair",
+            "signed message",
+            "verified signature",
+            "rejected signature",
+        ] {
+            assert!(lines.contains(message), "missing {:?}", message);
+        }
+        assert!(lines.contains(&Hex(&keypair.pubkey().0).to_string()));
+        assert!(lines.contains("seed=<redacted>"));
+        let seed = Hex(&keypair.privkey().0[..32]).to_string();
+        assert!(!lines.contains(&seed[..16]));
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::bech32::{self, CHARSET};
+use super::{Error, KeyPair, ADDR_BYTES_LEN};
+use cita_crypto_trait::CreateKey;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+// Workers report progress and check for cancellation this often.
+const VANITY_BATCH: u64 = 1024;
+
+/// Tuning for [`KeyPair::gen_vanity_with`].
+pub struct VanityOptions<'a> {
+    pub threads: usize,
+    /// Stops the search when set; the search then returns `Error::Cancelled`.
+    pub cancel: Option<&'a AtomicBool>,
+    /// Called from worker threads with the total number of keys tried so far.
+    pub progress: Option<&'a (dyn Fn(u64) + Sync)>,
+}
+
+impl<'a> VanityOptions<'a> {
+    pub fn new(threads: usize) -> Self {
+        VanityOptions {
+            threads,
+            cancel: None,
+            progress: None,
+        }
+    }
+}
+
+// The bech32 form spends 32 characters on the address bytes; the checksum
+// after them cannot be searched for.
+const BECH32_DATA_LEN: usize = (ADDR_BYTES_LEN * 8).div_ceil(5);
+
+enum Pattern {
+    Hex(Vec<u8>),
+    Bech32(Vec<u8>),
+}
+
+impl Pattern {
+    fn parse(prefix: &str) -> Result<Pattern, Error> {
+        match parse_hex_prefix(prefix) {
+            Ok(nibbles) => Ok(Pattern::Hex(nibbles)),
+            Err(_) => parse_bech32_prefix(prefix).map(Pattern::Bech32),
+        }
+    }
+
+    fn matches(&self, address: &[u8]) -> bool {
+        match self {
+            Pattern::Hex(nibbles) => address_has_prefix(address, nibbles),
+            Pattern::Bech32(groups) => bech32::to_base32(address).starts_with(groups),
+        }
+    }
+}
+
+fn parse_hex_prefix(prefix: &str) -> Result<Vec<u8>, Error> {
+    let prefix = prefix.strip_prefix("0x").unwrap_or(prefix);
+    if prefix.len() > ADDR_BYTES_LEN * 2 {
+        return Err(Error::InvalidPrefix);
+    }
+    prefix
+        .chars()
+        .map(|c| c.to_digit(16).map(|d| d as u8).ok_or(Error::InvalidPrefix))
+        .collect()
+}
+
+// `hrp1data`, all lower or all upper case. Only the data characters
+// constrain the search; any valid human-readable part fits every address.
+fn parse_bech32_prefix(prefix: &str) -> Result<Vec<u8>, Error> {
+    if prefix.bytes().any(|b| b.is_ascii_lowercase())
+        && prefix.bytes().any(|b| b.is_ascii_uppercase())
+    {
+        return Err(Error::InvalidPrefix);
+    }
+    let prefix = prefix.to_ascii_lowercase();
+    let (hrp, data) = prefix.rsplit_once('1').ok_or(Error::InvalidPrefix)?;
+    if !bech32::valid_hrp(hrp) || data.len() > BECH32_DATA_LEN {
+        return Err(Error::InvalidPrefix);
+    }
+    data.bytes()
+        .map(|c| {
+            CHARSET
+                .iter()
+                .position(|&x| x == c)
+                .map(|v| v as u8)
+                .ok_or(Error::InvalidPrefix)
+        })
+        .collect()
+}
+
+fn address_has_prefix(address: &[u8], nibbles: &[u8]) -> bool {
+    nibbles.iter().enumerate().all(|(i, nibble)| {
+        let byte = address[i / 2];
+        let actual = if i % 2 == 0 { byte >> 4 } else { byte & 0x0f };
+        actual == *nibble
+    })
+}
+
+impl KeyPair {
+    /// Search for a key pair whose address starts with `prefix`.
+    ///
+    /// `prefix` is hex, optionally after `0x`, or else the start of a
+    /// bech32 address as written by [`address_to_bech32`], such as
+    /// `cita1qqq`. A prefix that reads as hex is taken as hex. Each extra
+    /// hex digit makes the search 16 times longer, each bech32 character
+    /// 32 times.
+    ///
+    /// [`address_to_bech32`]: crate::address_to_bech32
+    pub fn gen_vanity(prefix: &str, threads: usize) -> Result<KeyPair, Error> {
+        Self::gen_vanity_with(prefix, &VanityOptions::new(threads))
+    }
+
+    pub fn gen_vanity_with(prefix: &str, options: &VanityOptions) -> Result<KeyPair, Error> {
+        let pattern = Pattern::parse(prefix)?;
+        let found: Mutex<Option<KeyPair>> = Mutex::new(None);
+        let done = AtomicBool::new(false);
+        let attempts = AtomicU64::new(0);
+        let cancelled = || options.cancel.is_some_and(|c| c.load(Ordering::Relaxed));
+
+        thread::scope(|scope| {
+            for _ in 0..options.threads.max(1) {
+                scope.spawn(|| {
+                    while !done.load(Ordering::Relaxed) && !cancelled() {
+                        for _ in 0..VANITY_BATCH {
+                            let keypair = KeyPair::gen_keypair();
+                            if pattern.matches(&keypair.address().0) {
+                                done.store(true, Ordering::Relaxed);
+                                found.lock().unwrap().get_or_insert(keypair);
+                                return;
+                            }
+                        }
+                        let total = attempts.fetch_add(VANITY_BATCH, Ordering::Relaxed);
+                        if let Some(progress) = options.progress {
+                            progress(total + VANITY_BATCH);
+                        }
+                    }
+                });
+            }
+        });
+
+        found.into_inner().unwrap().ok_or(Error::Cancelled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_serialize::hex::ToHex;
+
+    #[test]
+    fn test_gen_vanity() {
+        let keypair = KeyPair::gen_vanity("0xA", 2).unwrap();
+        assert!(keypair.address().0.to_hex().starts_with('a'));
+        assert!(KeyPair::gen_vanity("xyz", 1).is_err());
+    }
+
+    #[test]
+    fn test_gen_vanity_bech32() {
+        let keypair = KeyPair::gen_vanity("cita1qq", 2).unwrap();
+        let encoded = crate::address_to_bech32("cita", &keypair.address()).unwrap();
+        assert!(encoded.starts_with("cita1qq"));
+        let keypair = KeyPair::gen_vanity("CITA1Z", 2).unwrap();
+        let encoded = crate::address_to_bech32("cita", &keypair.address()).unwrap();
+        assert!(encoded.starts_with("cita1z"));
+
+        // 'b' is not in the bech32 alphabet; mixed case is not bech32.
+        assert!(KeyPair::gen_vanity("cita1b", 1).is_err());
+        assert!(KeyPair::gen_vanity("Cita1q", 1).is_err());
+        assert!(KeyPair::gen_vanity(&format!("cita1{}", "q".repeat(33)), 1).is_err());
+    }
+
+    #[test]
+    fn test_gen_vanity_cancel() {
+        let cancel = AtomicBool::new(false);
+        let calls = AtomicU64::new(0);
+        let progress = |_: u64| {
+            if calls.fetch_add(1, Ordering::Relaxed) == 2 {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        };
+        let options = VanityOptions {
+            threads: 2,
+            cancel: Some(&cancel),
+            progress: Some(&progress),
+        };
+        // 40 hex digits will not be found before cancellation.
+        let prefix = "f".repeat(40);
+        assert!(KeyPair::gen_vanity_with(&prefix, &options).is_err());
+        assert!(calls.load(Ordering::Relaxed) >= 3);
+    }
+}
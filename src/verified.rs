@@ -0,0 +1,167 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Types that can only be obtained by verifying, so an API can demand proof
+//! of verification in its signature instead of trusting callers to check.
+
+use super::{
+    pubkey_to_address, verify_evidence, Address, DoubleSignEvidence, Error, KeyAttestation,
+    Message, PubKey, Signature,
+};
+use cita_crypto_trait::Sign;
+use std::ops::Deref;
+
+/// Values that know how to check themselves.
+pub trait Verify {
+    /// What verification needs besides the value itself.
+    type Context;
+
+    fn verify(&self, context: &Self::Context) -> Result<(), Error>;
+}
+
+impl Verify for KeyAttestation {
+    type Context = ();
+
+    fn verify(&self, _: &()) -> Result<(), Error> {
+        KeyAttestation::verify(self)
+    }
+}
+
+impl Verify for DoubleSignEvidence {
+    type Context = ();
+
+    fn verify(&self, _: &()) -> Result<(), Error> {
+        verify_evidence(self)
+    }
+}
+
+/// A `T` whose [`Verify::verify`] has succeeded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verified<T>(T);
+
+impl<T: Verify> Verified<T> {
+    pub fn new(value: T, context: &T::Context) -> Result<Self, Error> {
+        value.verify(context)?;
+        Ok(Verified(value))
+    }
+}
+
+impl<T> Verified<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Verified<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// A signature known to be valid for `pubkey` over `message`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedSignature {
+    signature: Signature,
+    pubkey: PubKey,
+    message: Message,
+}
+
+impl VerifiedSignature {
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    pub fn pubkey(&self) -> &PubKey {
+        &self.pubkey
+    }
+
+    pub fn address(&self) -> Address {
+        pubkey_to_address(&self.pubkey)
+    }
+
+    pub fn message(&self) -> &Message {
+        &self.message
+    }
+
+    pub fn into_signature(self) -> Signature {
+        self.signature
+    }
+}
+
+impl Signature {
+    /// Verify against `pubkey` and keep the proof.
+    pub fn into_verified(
+        self,
+        pubkey: &PubKey,
+        message: &Message,
+    ) -> Result<VerifiedSignature, Error> {
+        self.verify_public(pubkey, message)?;
+        Ok(VerifiedSignature {
+            signature: self,
+            pubkey: *pubkey,
+            message: *message,
+        })
+    }
+
+    /// Verify with the embedded public key, which then must hash to
+    /// `address`, and keep the proof.
+    pub fn into_verified_for(
+        self,
+        address: &Address,
+        message: &Message,
+    ) -> Result<VerifiedSignature, Error> {
+        if !self.verify_address(address, message)? {
+            return Err(Error::InvalidPubKey);
+        }
+        let pubkey = PubKey::from_slice(self.pk());
+        Ok(VerifiedSignature {
+            signature: self,
+            pubkey,
+            message: *message,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AttestationMetadata, KeyPair};
+    use cita_crypto_trait::CreateKey;
+
+    #[test]
+    fn test_verified() {
+        let keypair = KeyPair::gen_keypair();
+        let message = Message::from_low_u64_be(3);
+        let sig = Signature::sign(keypair.privkey(), &message).unwrap();
+
+        let verified = sig
+            .clone()
+            .into_verified(keypair.pubkey(), &message)
+            .unwrap();
+        assert_eq!(verified.address(), keypair.address());
+        assert!(sig
+            .clone()
+            .into_verified(&PubKey::zero(), &message)
+            .is_err());
+        assert!(sig.into_verified_for(&Address::zero(), &message).is_err());
+
+        let ca = KeyPair::gen_keypair();
+        let attestation =
+            KeyAttestation::create(&keypair, &ca, AttestationMetadata::default()).unwrap();
+        let verified = Verified::new(attestation, &()).unwrap();
+        assert_eq!(verified.subject, *keypair.pubkey());
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Watch-only tracking of keys and addresses, for services that verify
+//! signatures but must never hold a private key.
+
+use super::{pubkey_to_address, Address, Error, ExtendedPubKey, Message, PubKey, Signature};
+use cita_crypto_trait::Sign;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize)]
+struct WatchListFields {
+    entries: BTreeMap<Address, Option<PubKey>>,
+}
+
+/// Watched addresses, each with its public key once known.
+///
+/// An address added on its own has no key until a valid signature from it is
+/// [`observe`](WatchList::observe)d. Deserializing rejects a key that does
+/// not hash to its address.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "WatchListFields", into = "WatchListFields")]
+pub struct WatchList {
+    entries: BTreeMap<Address, Option<PubKey>>,
+}
+
+impl WatchList {
+    pub fn new() -> Self {
+        WatchList::default()
+    }
+
+    pub fn add_pubkey(&mut self, pubkey: &PubKey) -> Address {
+        let address = pubkey_to_address(pubkey);
+        self.entries.insert(address, Some(*pubkey));
+        address
+    }
+
+    /// Watch `address` without knowing its key; a key already known is kept.
+    pub fn add_address(&mut self, address: Address) {
+        self.entries.entry(address).or_insert(None);
+    }
+
+    /// Watch the first `count` children of `xpub`.
+    pub fn add_derived(&mut self, xpub: &ExtendedPubKey, count: u32) -> Result<(), Error> {
+        for index in 0..count {
+            self.add_pubkey(&xpub.derive_pubkey(index)?);
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, address: &Address) -> bool {
+        self.entries.remove(address).is_some()
+    }
+
+    pub fn contains(&self, address: &Address) -> bool {
+        self.entries.contains_key(address)
+    }
+
+    pub fn pubkey(&self, address: &Address) -> Option<&PubKey> {
+        self.entries.get(address).and_then(Option::as_ref)
+    }
+
+    pub fn addresses(&self) -> impl Iterator<Item = &Address> {
+        self.entries.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The watched address that made `signature` over `message`, or `None`
+    /// if the signature is invalid or its signer is not watched.
+    pub fn signer_of(&self, message: &Message, signature: &Signature) -> Option<Address> {
+        let pubkey = PubKey::from_slice(signature.pk());
+        let address = pubkey_to_address(&pubkey);
+        match self.entries.get(&address)? {
+            Some(known) if known != &pubkey => None,
+            _ => signature
+                .verify_public(&pubkey, message)
+                .ok()
+                .map(|_| address),
+        }
+    }
+
+    /// Like [`signer_of`](WatchList::signer_of), also recording the signer's
+    /// key if only its address was watched.
+    pub fn observe(&mut self, message: &Message, signature: &Signature) -> Option<Address> {
+        let address = self.signer_of(message, signature)?;
+        self.entries
+            .insert(address, Some(PubKey::from_slice(signature.pk())));
+        Some(address)
+    }
+
+    /// Every watched address among the valid `signatures` over `message`,
+    /// each once, in address order.
+    pub fn signers(&self, message: &Message, signatures: &[Signature]) -> Vec<Address> {
+        let mut signers: Vec<Address> = signatures
+            .iter()
+            .filter_map(|signature| self.signer_of(message, signature))
+            .collect();
+        signers.sort_unstable();
+        signers.dedup();
+        signers
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(self).map_err(|_| Error::InvalidEncoding)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(|_| Error::InvalidEncoding)
+    }
+}
+
+impl TryFrom<WatchListFields> for WatchList {
+    type Error = Error;
+
+    fn try_from(fields: WatchListFields) -> Result<Self, Self::Error> {
+        let mismatched = fields.entries.iter().any(|(address, pubkey)| {
+            pubkey.is_some_and(|pubkey| pubkey_to_address(&pubkey) != *address)
+        });
+        if mismatched {
+            return Err(Error::InvalidPubKey);
+        }
+        Ok(WatchList {
+            entries: fields.entries,
+        })
+    }
+}
+
+impl From<WatchList> for WatchListFields {
+    fn from(watch: WatchList) -> Self {
+        WatchListFields {
+            entries: watch.entries,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+    use cita_crypto_trait::CreateKey;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn test_watch_list() {
+        let alice = KeyPair::gen_keypair();
+        let bob = KeyPair::gen_keypair();
+        let carol = KeyPair::gen_keypair();
+        let mut watch = WatchList::new();
+        watch.add_pubkey(alice.pubkey());
+        watch.add_address(bob.address());
+        assert!(watch.pubkey(&bob.address()).is_none());
+
+        let msg = Message::from_low_u64_be(9);
+        let sigs: Vec<Signature> = [&alice, &bob, &carol]
+            .iter()
+            .map(|k| Signature::sign(k.privkey(), &msg).unwrap())
+            .collect();
+        assert_eq!(watch.signer_of(&msg, &sigs[2]), None);
+        let other = Message::from_low_u64_be(10);
+        assert_eq!(watch.signer_of(&other, &sigs[0]), None);
+        let mut expected = vec![alice.address(), bob.address()];
+        expected.sort_unstable();
+        assert_eq!(watch.signers(&msg, &sigs), expected);
+
+        assert_eq!(watch.observe(&msg, &sigs[1]), Some(bob.address()));
+        assert_eq!(watch.pubkey(&bob.address()), Some(bob.pubkey()));
+
+        let path = env::temp_dir().join(format!("cita-ed25519-{}-watch.json", process::id()));
+        watch.save(&path).unwrap();
+        let loaded = WatchList::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, watch);
+
+        // A key stored under someone else's address is refused on load.
+        let mut forged = serde_json::to_value(&watch).unwrap();
+        let bob_key = serde_json::to_value(bob.pubkey()).unwrap();
+        let alice_address = serde_json::to_value(alice.address()).unwrap();
+        forged["entries"][alice_address.as_str().unwrap()] = bob_key;
+        assert!(serde_json::from_value::<WatchList>(forged).is_err());
+    }
+}
@@ -0,0 +1,281 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The highest vote a validator key has signed, kept so that a restarted
+//! signer cannot be tricked into signing a conflicting vote.
+
+use super::{vote_message, Error, KeyPair, Signature};
+use cita_crypto_trait::{CreateKey, Sign};
+use cita_types::H256;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use sodiumoxide::crypto::hash::sha256;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The last vote signed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watermark {
+    pub height: u64,
+    pub round: u64,
+    pub proposal: H256,
+}
+
+impl Watermark {
+    /// Whether signing `next` after `self` is safe: it must be a later round,
+    /// or the very same vote again.
+    pub fn allows(&self, next: &Watermark) -> bool {
+        (next.height, next.round) > (self.height, self.round) || next == self
+    }
+}
+
+/// Durable storage for one key's watermark.
+pub trait WatermarkStore {
+    fn load(&self) -> Result<Option<Watermark>, Error>;
+    /// Must not return before the watermark would survive a crash.
+    fn save(&self, watermark: &Watermark) -> Result<(), Error>;
+    /// Save `next` if the stored watermark allows it, as one step: two
+    /// concurrent calls with conflicting votes must not both succeed.
+    fn advance(&self, next: &Watermark) -> Result<(), Error>;
+}
+
+fn check_allows(current: Option<Watermark>, next: &Watermark) -> Result<(), Error> {
+    match current {
+        Some(current) if !current.allows(next) => Err(Error::WatermarkViolation),
+        _ => Ok(()),
+    }
+}
+
+/// Keeps the watermark in memory only, for tests and throwaway nodes.
+#[derive(Debug, Default)]
+pub struct MemoryWatermarkStore {
+    watermark: Mutex<Option<Watermark>>,
+}
+
+impl MemoryWatermarkStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl WatermarkStore for MemoryWatermarkStore {
+    fn load(&self) -> Result<Option<Watermark>, Error> {
+        Ok(*self.watermark.lock().unwrap())
+    }
+
+    fn save(&self, watermark: &Watermark) -> Result<(), Error> {
+        *self.watermark.lock().unwrap() = Some(*watermark);
+        Ok(())
+    }
+
+    fn advance(&self, next: &Watermark) -> Result<(), Error> {
+        let mut watermark = self.watermark.lock().unwrap();
+        check_allows(*watermark, next)?;
+        *watermark = Some(*next);
+        Ok(())
+    }
+}
+
+/// Keeps the watermark in a file as its RLP encoding followed by a SHA-256
+/// checksum, replaced atomically on every save.
+///
+/// [`WatermarkStore::advance`] is atomic among users of the same store value;
+/// the file must not be shared with another store or process.
+#[derive(Debug)]
+pub struct FileWatermarkStore {
+    path: PathBuf,
+    advance: Mutex<()>,
+}
+
+impl FileWatermarkStore {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        FileWatermarkStore {
+            path: path.into(),
+            advance: Mutex::new(()),
+        }
+    }
+}
+
+impl WatermarkStore for FileWatermarkStore {
+    /// A missing file means no vote was signed yet; a file that fails its
+    /// checksum is an error, never treated as empty.
+    fn load(&self) -> Result<Option<Watermark>, Error> {
+        read_checked(&self.path)?
+            .map(|body| Ok(rlp::decode(&body)?))
+            .transpose()
+    }
+
+    fn save(&self, watermark: &Watermark) -> Result<(), Error> {
+        write_checked(&self.path, &rlp::encode(watermark))
+    }
+
+    fn advance(&self, next: &Watermark) -> Result<(), Error> {
+        let _guard = self.advance.lock().unwrap();
+        check_allows(self.load()?, next)?;
+        self.save(next)
+    }
+}
+
+// The body of a file written by `write_checked`, or `None` if there is no
+// file. A failed checksum is an error, never treated as a missing file.
+pub(crate) fn read_checked(path: &Path) -> Result<Option<Vec<u8>>, Error> {
+    let mut bytes = Vec::new();
+    match File::open(path) {
+        Ok(mut file) => file.read_to_end(&mut bytes)?,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    if bytes.len() < sha256::DIGESTBYTES {
+        return Err(Error::InvalidEncoding);
+    }
+    let (body, checksum) = bytes.split_at(bytes.len() - sha256::DIGESTBYTES);
+    if sha256::hash(body).0[..] != checksum[..] {
+        return Err(Error::InvalidEncoding);
+    }
+    bytes.truncate(bytes.len() - sha256::DIGESTBYTES);
+    Ok(Some(bytes))
+}
+
+// Replace `path` with `body` and its SHA-256 checksum, durably.
+pub(crate) fn write_checked(path: &Path, body: &[u8]) -> Result<(), Error> {
+    let mut bytes = body.to_vec();
+    bytes.extend_from_slice(&sha256::hash(body).0);
+
+    let mut tmp = path.to_path_buf().into_os_string();
+    tmp.push(".tmp");
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&tmp)?;
+    file.write_all(&bytes)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    // Persist the rename itself.
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Sign a vote for `proposal` at `height` and `round`, refusing anything the
+/// stored watermark does not allow. The new watermark is saved before the
+/// signature is made.
+pub fn sign_vote<S: WatermarkStore + ?Sized>(
+    store: &S,
+    keypair: &KeyPair,
+    height: u64,
+    round: u64,
+    proposal: &H256,
+) -> Result<Signature, Error> {
+    let next = Watermark {
+        height,
+        round,
+        proposal: *proposal,
+    };
+    store.advance(&next)?;
+    Signature::sign(keypair.privkey(), &vote_message(height, round, proposal))
+}
+
+impl Encodable for Watermark {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3);
+        s.append(&self.height);
+        s.append(&self.round);
+        s.append(&self.proposal);
+    }
+}
+
+impl Decodable for Watermark {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 3 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(Watermark {
+            height: rlp.val_at(0)?,
+            round: rlp.val_at(1)?,
+            proposal: rlp.val_at(2)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    fn exercise<S: WatermarkStore>(store: &S) {
+        let keypair = KeyPair::gen_keypair();
+        let (p1, p2) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+        sign_vote(store, &keypair, 5, 0, &p1).unwrap();
+        sign_vote(store, &keypair, 5, 0, &p1).unwrap();
+        assert!(sign_vote(store, &keypair, 5, 0, &p2).is_err());
+        assert!(sign_vote(store, &keypair, 4, 3, &p2).is_err());
+        sign_vote(store, &keypair, 5, 1, &p2).unwrap();
+        assert_eq!(store.load().unwrap().unwrap().round, 1);
+    }
+
+    // Conflicting votes for the same round from two threads: exactly one
+    // may be signed.
+    fn race<S: WatermarkStore + Sync>(store: &S) {
+        let keypair = KeyPair::gen_keypair();
+        for height in 100..150 {
+            let signed: usize = std::thread::scope(|scope| {
+                let handles: Vec<_> = (1..=2u64)
+                    .map(|p| {
+                        let keypair = &keypair;
+                        scope.spawn(move || {
+                            sign_vote(store, keypair, height, 0, &H256::from_low_u64_be(p)).is_ok()
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|h| h.join().unwrap() as usize)
+                    .sum()
+            });
+            assert_eq!(signed, 1);
+        }
+    }
+
+    #[test]
+    fn test_memory_watermark_store() {
+        exercise(&MemoryWatermarkStore::new());
+        race(&MemoryWatermarkStore::new());
+    }
+
+    #[test]
+    fn test_file_watermark_store() {
+        let path = env::temp_dir().join(format!("cita-ed25519-watermark-{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let store = FileWatermarkStore::new(&path);
+        assert_eq!(store.load().unwrap(), None);
+        exercise(&store);
+
+        // A restarted signer sees the same watermark.
+        let reopened = FileWatermarkStore::new(&path);
+        assert_eq!(reopened.load().unwrap(), store.load().unwrap());
+
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[2] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        assert!(reopened.load().is_err());
+        fs::remove_file(&path).unwrap();
+
+        race(&FileWatermarkStore::new(&path));
+        fs::remove_file(&path).unwrap();
+    }
+}
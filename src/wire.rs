@@ -0,0 +1,154 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{
+    pubkey_from_slice, EscrowBlob, GuardianShare, PubKey, Signature, PUBKEY_BYTES_LEN,
+    SEAL_OVERHEAD, SIGNATURE_BYTES_LEN,
+};
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Write `payload` preceded by its length as a big-endian `u32`.
+pub fn write_frame<W: Write>(writer: &mut W, payload: &[u8]) -> io::Result<()> {
+    if payload.len() > u32::MAX as usize {
+        return Err(invalid_data("frame too long"));
+    }
+    writer.write_all(&(payload.len() as u32).to_be_bytes())?;
+    writer.write_all(payload)
+}
+
+/// Read a frame written by [`write_frame`].
+///
+/// The length prefix is checked against `max_len` before anything is
+/// allocated, so a hostile peer cannot make us reserve a huge buffer.
+pub fn read_frame<R: Read>(reader: &mut R, max_len: usize) -> io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > max_len {
+        return Err(invalid_data("frame exceeds maximum length"));
+    }
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+/// Length-prefixed framing for the crate's types.
+pub trait WireFormat: Sized {
+    /// Largest encoding accepted by `read_from`.
+    const MAX_WIRE_LEN: usize;
+
+    fn to_wire_bytes(&self) -> Vec<u8>;
+
+    fn from_wire_bytes(bytes: &[u8]) -> io::Result<Self>;
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_frame(writer, &self.to_wire_bytes())
+    }
+
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Self::from_wire_bytes(&read_frame(reader, Self::MAX_WIRE_LEN)?)
+    }
+}
+
+impl WireFormat for Signature {
+    const MAX_WIRE_LEN: usize = SIGNATURE_BYTES_LEN;
+
+    fn to_wire_bytes(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    fn from_wire_bytes(bytes: &[u8]) -> io::Result<Self> {
+        Signature::try_from(bytes).map_err(|_| invalid_data("invalid signature length"))
+    }
+}
+
+impl WireFormat for PubKey {
+    const MAX_WIRE_LEN: usize = PUBKEY_BYTES_LEN;
+
+    fn to_wire_bytes(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    fn from_wire_bytes(bytes: &[u8]) -> io::Result<Self> {
+        pubkey_from_slice(bytes).map_err(|_| invalid_data("invalid public key length"))
+    }
+}
+
+// Envelopes carry a sealed private key or key share plus a little metadata.
+const MAX_ENVELOPE_LEN: usize = 256 + SEAL_OVERHEAD;
+
+impl WireFormat for EscrowBlob {
+    const MAX_WIRE_LEN: usize = MAX_ENVELOPE_LEN;
+
+    fn to_wire_bytes(&self) -> Vec<u8> {
+        rlp::encode(self).to_vec()
+    }
+
+    fn from_wire_bytes(bytes: &[u8]) -> io::Result<Self> {
+        rlp::decode(bytes).map_err(|_| invalid_data("invalid escrow blob"))
+    }
+}
+
+impl WireFormat for GuardianShare {
+    const MAX_WIRE_LEN: usize = MAX_ENVELOPE_LEN;
+
+    fn to_wire_bytes(&self) -> Vec<u8> {
+        rlp::encode(self).to_vec()
+    }
+
+    fn from_wire_bytes(bytes: &[u8]) -> io::Result<Self> {
+        rlp::decode(bytes).map_err(|_| invalid_data("invalid guardian share"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{escrow, KeyPair, Message};
+    use cita_crypto_trait::{CreateKey, Sign};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_round_trip() {
+        let keypair = KeyPair::gen_keypair();
+        let sig = Signature::sign(keypair.privkey(), &Message::from_low_u64_be(1)).unwrap();
+        let blob = escrow(keypair.privkey(), keypair.pubkey()).unwrap();
+
+        let mut buf = Vec::new();
+        sig.write_to(&mut buf).unwrap();
+        keypair.pubkey().write_to(&mut buf).unwrap();
+        blob.write_to(&mut buf).unwrap();
+
+        let mut reader = Cursor::new(buf);
+        assert_eq!(Signature::read_from(&mut reader).unwrap(), sig);
+        assert_eq!(&PubKey::read_from(&mut reader).unwrap(), keypair.pubkey());
+        assert_eq!(EscrowBlob::read_from(&mut reader).unwrap(), blob);
+    }
+
+    #[test]
+    fn test_rejects_bad_lengths() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, &[0u8; 95]).unwrap();
+        assert!(Signature::read_from(&mut Cursor::new(&buf)).is_err());
+
+        // A huge length prefix is refused without reading further.
+        let huge = u32::MAX.to_be_bytes();
+        assert!(read_frame(&mut Cursor::new(&huge[..]), 1024).is_err());
+    }
+}